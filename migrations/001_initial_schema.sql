@@ -1,6 +1,3 @@
--- Enable TimescaleDB extension
-CREATE EXTENSION IF NOT EXISTS timescaledb;
-
 -- Blocks table
 CREATE TABLE blocks (
     height BIGINT PRIMARY KEY,
@@ -78,9 +75,3 @@ CREATE INDEX idx_outputs_spent ON outputs(spent);
 CREATE INDEX idx_runes_transactions_rune_id ON runes_transactions(rune_id);
 CREATE INDEX idx_runes_transactions_timestamp ON runes_transactions(timestamp DESC);
 CREATE INDEX idx_bridge_events_status ON bridge_events(status);
-
--- Convert tables to hypertables for time-series optimization
-SELECT create_hypertable('blocks', 'timestamp', chunk_time_interval => INTERVAL '1 day', if_not_exists => TRUE);
-SELECT create_hypertable('transactions', 'timestamp', chunk_time_interval => INTERVAL '1 day', if_not_exists => TRUE);
-SELECT create_hypertable('runes_transactions', 'timestamp', chunk_time_interval => INTERVAL '1 day', if_not_exists => TRUE);
-SELECT create_hypertable('bridge_events', 'timestamp', chunk_time_interval => INTERVAL '1 day', if_not_exists => TRUE);
\ No newline at end of file
@@ -0,0 +1,19 @@
+-- Inscriptions revealed in taproot witness envelopes
+CREATE TABLE inscriptions (
+    inscription_id VARCHAR(80) PRIMARY KEY, -- '<txid>i<index>'
+    number BIGINT NOT NULL UNIQUE,
+    txid VARCHAR(64) NOT NULL REFERENCES transactions(txid),
+    input_index INT NOT NULL,
+    block_height BIGINT NOT NULL,
+    content_type VARCHAR(255),
+    content_encoding VARCHAR(64),
+    metaprotocol VARCHAR(255),
+    content_length BIGINT NOT NULL,
+    content_hash VARCHAR(64) NOT NULL, -- sha256 of the body
+    content BYTEA, -- NULL when stored in the blob directory
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    indexed_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_inscriptions_block_height ON inscriptions(block_height);
+CREATE INDEX idx_inscriptions_content_type ON inscriptions(content_type);
@@ -0,0 +1,27 @@
+-- Current location of each inscription
+ALTER TABLE inscriptions
+    ADD COLUMN location_txid VARCHAR(64),
+    ADD COLUMN location_vout INT,
+    ADD COLUMN location_offset BIGINT,
+    ADD COLUMN address VARCHAR(100);
+
+-- Inscription transfer history, one row per move
+CREATE TABLE inscription_transfers (
+    id SERIAL PRIMARY KEY,
+    inscription_id VARCHAR(80) NOT NULL REFERENCES inscriptions(inscription_id),
+    txid VARCHAR(64) NOT NULL,
+    block_height BIGINT NOT NULL,
+    from_txid VARCHAR(64),
+    from_vout INT,
+    from_offset BIGINT,
+    to_txid VARCHAR(64), -- NULL when the inscription was lost
+    to_vout INT,
+    to_offset BIGINT,
+    to_address VARCHAR(100),
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    indexed_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_inscriptions_location ON inscriptions(location_txid, location_vout);
+CREATE INDEX idx_inscriptions_address ON inscriptions(address);
+CREATE INDEX idx_inscription_transfers_inscription_id ON inscription_transfers(inscription_id);
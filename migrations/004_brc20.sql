@@ -0,0 +1,51 @@
+-- BRC-20 tokens, keyed by lowercase tick
+CREATE TABLE brc20_tokens (
+    tick VARCHAR(16) PRIMARY KEY,
+    inscription_id VARCHAR(80) NOT NULL REFERENCES inscriptions(inscription_id),
+    max_supply NUMERIC NOT NULL,
+    mint_limit NUMERIC NOT NULL,
+    decimals INT NOT NULL,
+    minted NUMERIC NOT NULL DEFAULT 0,
+    deployer VARCHAR(100),
+    block_height BIGINT NOT NULL,
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    indexed_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- Per-address balances; transferable is locked in unspent transfer inscriptions
+CREATE TABLE brc20_balances (
+    address VARCHAR(100) NOT NULL,
+    tick VARCHAR(16) NOT NULL REFERENCES brc20_tokens(tick),
+    available NUMERIC NOT NULL DEFAULT 0,
+    transferable NUMERIC NOT NULL DEFAULT 0,
+    PRIMARY KEY (address, tick)
+);
+
+-- Transfer inscriptions awaiting their first move
+CREATE TABLE brc20_transfers (
+    inscription_id VARCHAR(80) PRIMARY KEY REFERENCES inscriptions(inscription_id),
+    tick VARCHAR(16) NOT NULL REFERENCES brc20_tokens(tick),
+    amount NUMERIC NOT NULL,
+    from_address VARCHAR(100) NOT NULL,
+    to_address VARCHAR(100),
+    used BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- Valid BRC-20 operations in chain order
+CREATE TABLE brc20_events (
+    id SERIAL PRIMARY KEY,
+    inscription_id VARCHAR(80) NOT NULL,
+    operation VARCHAR(20) NOT NULL, -- 'deploy', 'mint', 'inscribe_transfer', 'transfer'
+    tick VARCHAR(16) NOT NULL,
+    amount NUMERIC,
+    from_address VARCHAR(100),
+    to_address VARCHAR(100),
+    txid VARCHAR(64) NOT NULL,
+    block_height BIGINT NOT NULL,
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    indexed_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_brc20_balances_tick ON brc20_balances(tick);
+CREATE INDEX idx_brc20_events_tick ON brc20_events(tick);
+CREATE INDEX idx_brc20_events_addresses ON brc20_events(from_address, to_address);
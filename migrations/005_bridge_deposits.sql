@@ -0,0 +1,7 @@
+-- Locate bridge events on chain so deposits can be confirmed and deduplicated
+ALTER TABLE bridge_events
+    ADD COLUMN vout INT,
+    ADD COLUMN block_height BIGINT;
+
+CREATE INDEX idx_bridge_events_txid ON bridge_events(txid, vout);
+CREATE INDEX idx_bridge_events_block_height ON bridge_events(block_height);
@@ -0,0 +1,13 @@
+-- Audit trail of bridge event status changes
+-- (pending -> confirmed -> finalized, or -> orphaned on reorg)
+CREATE TABLE bridge_event_transitions (
+    id SERIAL PRIMARY KEY,
+    event_id INT NOT NULL,
+    from_status VARCHAR(20),
+    to_status VARCHAR(20) NOT NULL,
+    block_height BIGINT, -- indexed tip when the transition happened
+    reason TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_bridge_event_transitions_event_id ON bridge_event_transitions(event_id);
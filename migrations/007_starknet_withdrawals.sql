@@ -0,0 +1,13 @@
+-- Starknet transaction hashes are 0x-prefixed felts
+ALTER TABLE bridge_events
+    ALTER COLUMN starknet_tx_hash TYPE VARCHAR(66),
+    ADD COLUMN starknet_event_index INT;
+
+CREATE INDEX idx_bridge_events_starknet_tx_hash ON bridge_events(starknet_tx_hash);
+
+-- Progress of external chain followers
+CREATE TABLE sync_cursors (
+    name VARCHAR(64) PRIMARY KEY,
+    block_number BIGINT NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
@@ -0,0 +1,22 @@
+-- Bitcoin headers submitted to the Starknet light-client contract
+CREATE TABLE header_relay_submissions (
+    id SERIAL PRIMARY KEY,
+    from_height BIGINT NOT NULL,
+    to_height BIGINT NOT NULL,
+    starknet_tx_hash VARCHAR(66),
+    status VARCHAR(20) NOT NULL,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_header_relay_submissions_status ON header_relay_submissions(status);
+
+-- Headers covered by a live submission; removed when it fails or is reorged out
+CREATE TABLE relayed_headers (
+    height BIGINT PRIMARY KEY,
+    hash VARCHAR(64) NOT NULL,
+    submission_id INT NOT NULL REFERENCES header_relay_submissions(id)
+);
+
+CREATE INDEX idx_relayed_headers_submission_id ON relayed_headers(submission_id);
@@ -0,0 +1,6 @@
+-- Raw 80-byte header and transaction order, needed to build inclusion proofs
+ALTER TABLE blocks ADD COLUMN header VARCHAR(160);
+
+ALTER TABLE transactions ADD COLUMN tx_index INT;
+
+CREATE INDEX idx_transactions_block_height_tx_index ON transactions(block_height, tx_index);
@@ -0,0 +1,13 @@
+-- Full header fields and block statistics; NULL for blocks indexed before this migration
+ALTER TABLE blocks
+    ADD COLUMN version INT,
+    ADD COLUMN bits BIGINT,
+    ADD COLUMN nonce BIGINT,
+    ADD COLUMN difficulty DOUBLE PRECISION,
+    -- Cumulative proof of work, 64 hex digits so it orders lexicographically
+    ADD COLUMN chainwork VARCHAR(64),
+    ADD COLUMN median_time TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN tx_count INT,
+    ADD COLUMN size INT,
+    ADD COLUMN stripped_size INT,
+    ADD COLUMN weight INT;
@@ -0,0 +1,15 @@
+-- Transaction inputs with the prevout they spend, resolved at index time
+CREATE TABLE inputs (
+    txid VARCHAR(64) NOT NULL,
+    vin INT NOT NULL,
+    -- NULL for coinbase inputs
+    prev_txid VARCHAR(64),
+    prev_vout INT,
+    script_sig TEXT NOT NULL,
+    witness TEXT[] NOT NULL DEFAULT '{}',
+    sequence BIGINT NOT NULL,
+    -- NULL when the prevout predates the indexer start height
+    value BIGINT,
+    address VARCHAR(100),
+    PRIMARY KEY (txid, vin)
+);
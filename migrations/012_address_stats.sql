@@ -0,0 +1,54 @@
+-- Per-address flows of each transaction; the source for history and for undoing stats on reorg
+CREATE TABLE address_transactions (
+    address VARCHAR(100) NOT NULL,
+    txid VARCHAR(64) NOT NULL,
+    block_height BIGINT NOT NULL,
+    tx_index INT NOT NULL DEFAULT 0,
+    received BIGINT NOT NULL DEFAULT 0,
+    sent BIGINT NOT NULL DEFAULT 0,
+    funded_outputs INT NOT NULL DEFAULT 0,
+    spent_outputs INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (address, txid)
+);
+
+CREATE INDEX idx_address_transactions_history
+    ON address_transactions(address, block_height DESC, tx_index DESC);
+CREATE INDEX idx_address_transactions_block_height ON address_transactions(block_height);
+
+-- Running totals per address, maintained as transactions are indexed
+CREATE TABLE address_stats (
+    address VARCHAR(100) PRIMARY KEY,
+    received BIGINT NOT NULL DEFAULT 0,
+    sent BIGINT NOT NULL DEFAULT 0,
+    tx_count INT NOT NULL DEFAULT 0,
+    funded_txo_count INT NOT NULL DEFAULT 0,
+    spent_txo_count INT NOT NULL DEFAULT 0,
+    first_seen_height BIGINT,
+    last_seen_height BIGINT
+);
+
+CREATE INDEX idx_outputs_address_unspent ON outputs(address) WHERE NOT spent;
+
+-- Backfill from already indexed outputs
+WITH flows AS (
+    SELECT address, txid, value AS received, 0::BIGINT AS sent, 1 AS funded, 0 AS spent
+    FROM outputs WHERE address IS NOT NULL
+    UNION ALL
+    SELECT address, spending_txid, 0, value, 0, 1
+    FROM outputs WHERE address IS NOT NULL AND spending_txid IS NOT NULL
+)
+INSERT INTO address_transactions
+(address, txid, block_height, tx_index, received, sent, funded_outputs, spent_outputs)
+SELECT f.address, f.txid, t.block_height, COALESCE(t.tx_index, 0),
+       SUM(f.received), SUM(f.sent), SUM(f.funded), SUM(f.spent)
+FROM flows f
+JOIN transactions t ON t.txid = f.txid
+GROUP BY f.address, f.txid, t.block_height, t.tx_index;
+
+INSERT INTO address_stats
+(address, received, sent, tx_count, funded_txo_count, spent_txo_count,
+ first_seen_height, last_seen_height)
+SELECT address, SUM(received), SUM(sent), COUNT(*), SUM(funded_outputs), SUM(spent_outputs),
+       MIN(block_height), MAX(block_height)
+FROM address_transactions
+GROUP BY address;
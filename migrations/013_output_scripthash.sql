@@ -0,0 +1,10 @@
+-- Electrum scripthash: sha256 of the scriptPubKey, byte-reversed, hex
+ALTER TABLE outputs ADD COLUMN scripthash VARCHAR(64);
+
+UPDATE outputs o SET scripthash = (
+    SELECT string_agg(substr(h.digest, 63 - 2 * i, 2), '' ORDER BY i)
+    FROM (SELECT encode(sha256(decode(o.script_pubkey, 'hex')), 'hex') AS digest) h,
+         generate_series(0, 31) i
+);
+
+CREATE INDEX idx_outputs_scripthash ON outputs(scripthash);
@@ -0,0 +1,4 @@
+-- Indexes backing keyset pagination of the list endpoints
+CREATE INDEX idx_runes_transactions_height_id ON runes_transactions(block_height DESC, id DESC);
+CREATE INDEX idx_inscriptions_address_number ON inscriptions(address, number DESC);
+CREATE INDEX idx_brc20_tokens_height_tick ON brc20_tokens(block_height, tick);
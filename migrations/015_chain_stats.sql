@@ -0,0 +1,75 @@
+-- What each indexed block added, so totals can be maintained and undone on reorg
+CREATE TABLE block_stats (
+    height BIGINT PRIMARY KEY,
+    tx_count BIGINT NOT NULL,
+    output_count BIGINT NOT NULL,
+    output_value BIGINT NOT NULL,
+    -- Indexed outputs spent by this block
+    spent_count BIGINT NOT NULL,
+    spent_value BIGINT NOT NULL,
+    runes_transactions BIGINT NOT NULL,
+    runes_etched BIGINT NOT NULL,
+    runes_minted BIGINT NOT NULL,
+    runes_burned BIGINT NOT NULL,
+    bridge_deposit_volume BIGINT NOT NULL,
+    bridge_withdrawal_volume BIGINT NOT NULL,
+    indexed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Running totals over block_stats; a single row
+CREATE TABLE chain_stats (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    blocks BIGINT NOT NULL DEFAULT 0,
+    transactions BIGINT NOT NULL DEFAULT 0,
+    outputs BIGINT NOT NULL DEFAULT 0,
+    utxo_count BIGINT NOT NULL DEFAULT 0,
+    utxo_value BIGINT NOT NULL DEFAULT 0,
+    runes_transactions BIGINT NOT NULL DEFAULT 0,
+    runes_etched BIGINT NOT NULL DEFAULT 0,
+    runes_minted BIGINT NOT NULL DEFAULT 0,
+    runes_burned BIGINT NOT NULL DEFAULT 0,
+    bridge_deposit_volume BIGINT NOT NULL DEFAULT 0,
+    bridge_withdrawal_volume BIGINT NOT NULL DEFAULT 0,
+    -- Best chain height last reported by the node
+    node_height BIGINT
+);
+
+INSERT INTO block_stats
+    (height, tx_count, output_count, output_value, spent_count, spent_value, runes_transactions,
+     runes_etched, runes_minted, runes_burned, bridge_deposit_volume, bridge_withdrawal_volume)
+SELECT b.height,
+       COALESCE(t.tx_count, 0), COALESCE(o.output_count, 0), COALESCE(o.output_value, 0),
+       COALESCE(i.spent_count, 0), COALESCE(i.spent_value, 0),
+       COALESCE(r.runes_transactions, 0), COALESCE(r.etched, 0), COALESCE(r.minted, 0),
+       COALESCE(r.burned, 0), COALESCE(e.deposits, 0), COALESCE(e.withdrawals, 0)
+FROM blocks b
+LEFT JOIN (SELECT block_height, COUNT(*) AS tx_count FROM transactions GROUP BY block_height) t
+    ON t.block_height = b.height
+LEFT JOIN (SELECT t.block_height, COUNT(*) AS output_count, SUM(o.value) AS output_value
+           FROM outputs o JOIN transactions t ON t.txid = o.txid GROUP BY t.block_height) o
+    ON o.block_height = b.height
+LEFT JOIN (SELECT t.block_height, COUNT(*) AS spent_count, SUM(i.value) AS spent_value
+           FROM inputs i JOIN transactions t ON t.txid = i.txid
+           WHERE i.value IS NOT NULL GROUP BY t.block_height) i
+    ON i.block_height = b.height
+LEFT JOIN (SELECT block_height, COUNT(*) AS runes_transactions,
+                  COUNT(*) FILTER (WHERE operation = 'etch') AS etched,
+                  COUNT(*) FILTER (WHERE operation = 'mint') AS minted,
+                  COUNT(*) FILTER (WHERE operation = 'burn') AS burned
+           FROM runes_transactions GROUP BY block_height) r
+    ON r.block_height = b.height
+LEFT JOIN (SELECT block_height,
+                  SUM(amount) FILTER (WHERE event_type = 'deposit') AS deposits,
+                  SUM(amount) FILTER (WHERE event_type = 'withdrawal') AS withdrawals
+           FROM bridge_events WHERE status <> 'orphaned' GROUP BY block_height) e
+    ON e.block_height = b.height;
+
+INSERT INTO chain_stats
+    (blocks, transactions, outputs, utxo_count, utxo_value, runes_transactions, runes_etched,
+     runes_minted, runes_burned, bridge_deposit_volume, bridge_withdrawal_volume)
+SELECT COUNT(*), COALESCE(SUM(tx_count), 0), COALESCE(SUM(output_count), 0),
+       COALESCE(SUM(output_count - spent_count), 0), COALESCE(SUM(output_value - spent_value), 0),
+       COALESCE(SUM(runes_transactions), 0), COALESCE(SUM(runes_etched), 0),
+       COALESCE(SUM(runes_minted), 0), COALESCE(SUM(runes_burned), 0),
+       COALESCE(SUM(bridge_deposit_volume), 0), COALESCE(SUM(bridge_withdrawal_volume), 0)
+FROM block_stats;
@@ -0,0 +1,11 @@
+-- TimescaleDB requires every unique key on a hypertable to include the
+-- partition column. These append-only event logs are the tables converted
+-- when TIMESCALEDB_ENABLED is set, so key them on (id, timestamp).
+ALTER TABLE runes_transactions DROP CONSTRAINT runes_transactions_pkey;
+ALTER TABLE runes_transactions ADD PRIMARY KEY (id, timestamp);
+
+ALTER TABLE inscription_transfers DROP CONSTRAINT inscription_transfers_pkey;
+ALTER TABLE inscription_transfers ADD PRIMARY KEY (id, timestamp);
+
+ALTER TABLE brc20_events DROP CONSTRAINT brc20_events_pkey;
+ALTER TABLE brc20_events ADD PRIMARY KEY (id, timestamp);
@@ -0,0 +1,68 @@
+-- Store hashes and scripts as raw bytes instead of hex text. Hashes are kept
+-- in internal byte order, as they appear on the wire; the API still displays
+-- them byte-reversed like bitcoind.
+
+-- Display hex -> internal bytes. USING clauses can't hold subqueries.
+CREATE FUNCTION pg_temp.hash_bytes(display TEXT) RETURNS BYTEA AS $$
+    SELECT decode(string_agg(substr(display, i, 2), '' ORDER BY i DESC), 'hex')
+    FROM generate_series(1, length(display), 2) i
+$$ LANGUAGE SQL IMMUTABLE STRICT;
+
+-- Referencing columns can't change type independently of transactions.txid
+ALTER TABLE outputs DROP CONSTRAINT outputs_txid_fkey;
+ALTER TABLE runes_transactions DROP CONSTRAINT runes_transactions_txid_fkey;
+ALTER TABLE inscriptions DROP CONSTRAINT inscriptions_txid_fkey;
+
+ALTER TABLE blocks
+    ALTER COLUMN hash TYPE BYTEA USING pg_temp.hash_bytes(hash),
+    ALTER COLUMN prev_hash TYPE BYTEA USING pg_temp.hash_bytes(prev_hash),
+    ALTER COLUMN merkle_root TYPE BYTEA USING pg_temp.hash_bytes(merkle_root),
+    ALTER COLUMN header TYPE BYTEA USING decode(header, 'hex');
+
+ALTER TABLE transactions
+    ALTER COLUMN txid TYPE BYTEA USING pg_temp.hash_bytes(txid),
+    ALTER COLUMN block_hash TYPE BYTEA USING pg_temp.hash_bytes(block_hash);
+
+ALTER TABLE outputs
+    ALTER COLUMN txid TYPE BYTEA USING pg_temp.hash_bytes(txid),
+    ALTER COLUMN script_pubkey TYPE BYTEA USING decode(script_pubkey, 'hex'),
+    ALTER COLUMN spending_txid TYPE BYTEA USING pg_temp.hash_bytes(spending_txid),
+    ALTER COLUMN scripthash TYPE BYTEA USING pg_temp.hash_bytes(scripthash);
+
+ALTER TABLE inputs
+    ALTER COLUMN txid TYPE BYTEA USING pg_temp.hash_bytes(txid),
+    ALTER COLUMN prev_txid TYPE BYTEA USING pg_temp.hash_bytes(prev_txid),
+    ALTER COLUMN script_sig TYPE BYTEA USING decode(script_sig, 'hex');
+
+ALTER TABLE runes_transactions
+    ALTER COLUMN txid TYPE BYTEA USING pg_temp.hash_bytes(txid);
+
+ALTER TABLE bridge_events
+    ALTER COLUMN txid TYPE BYTEA USING pg_temp.hash_bytes(txid);
+
+ALTER TABLE inscriptions
+    ALTER COLUMN txid TYPE BYTEA USING pg_temp.hash_bytes(txid),
+    ALTER COLUMN location_txid TYPE BYTEA USING pg_temp.hash_bytes(location_txid);
+
+ALTER TABLE inscription_transfers
+    ALTER COLUMN txid TYPE BYTEA USING pg_temp.hash_bytes(txid),
+    ALTER COLUMN from_txid TYPE BYTEA USING pg_temp.hash_bytes(from_txid),
+    ALTER COLUMN to_txid TYPE BYTEA USING pg_temp.hash_bytes(to_txid);
+
+ALTER TABLE brc20_events
+    ALTER COLUMN txid TYPE BYTEA USING pg_temp.hash_bytes(txid);
+
+ALTER TABLE address_transactions
+    ALTER COLUMN txid TYPE BYTEA USING pg_temp.hash_bytes(txid);
+
+ALTER TABLE relayed_headers
+    ALTER COLUMN hash TYPE BYTEA USING pg_temp.hash_bytes(hash);
+
+ALTER TABLE outputs ADD CONSTRAINT outputs_txid_fkey
+    FOREIGN KEY (txid) REFERENCES transactions(txid);
+ALTER TABLE runes_transactions ADD CONSTRAINT runes_transactions_txid_fkey
+    FOREIGN KEY (txid) REFERENCES transactions(txid);
+ALTER TABLE inscriptions ADD CONSTRAINT inscriptions_txid_fkey
+    FOREIGN KEY (txid) REFERENCES transactions(txid);
+
+DROP FUNCTION pg_temp.hash_bytes(TEXT);
@@ -0,0 +1,10 @@
+-- Retention policies prune transaction detail in the background. Pruned
+-- transactions keep their row, so blocks still list every txid.
+ALTER TABLE transactions ADD COLUMN pruned BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Pruning finds spends by transaction and protocol data by txid
+CREATE INDEX idx_outputs_spending_txid ON outputs(spending_txid);
+CREATE INDEX idx_runes_transactions_txid ON runes_transactions(txid);
+CREATE INDEX idx_inscriptions_txid ON inscriptions(txid);
+CREATE INDEX idx_inscription_transfers_txid ON inscription_transfers(txid);
+CREATE INDEX idx_brc20_events_txid ON brc20_events(txid);
@@ -0,0 +1,40 @@
+-- Hourly and daily chain activity for /analytics/timeseries. Buckets are
+-- maintained per block alongside chain_stats, so dashboards never scan the
+-- raw tables and the same schema works with or without TimescaleDB.
+
+-- Fees claimed by the coinbase above the block subsidy
+ALTER TABLE block_stats ADD COLUMN fees BIGINT NOT NULL DEFAULT 0;
+
+UPDATE block_stats s
+SET fees = GREATEST(c.value - (5000000000::BIGINT >> LEAST(s.height / 210000, 63)::INT), 0)
+FROM (
+    SELECT t.block_height, SUM(o.value) AS value
+    FROM transactions t JOIN outputs o ON o.txid = t.txid
+    WHERE t.tx_index = 0
+    GROUP BY t.block_height
+) c
+WHERE c.block_height = s.height;
+
+CREATE TABLE timeseries_buckets (
+    -- `1h` or `1d`
+    resolution VARCHAR(4) NOT NULL,
+    -- Start of the bucket in UTC, by block timestamp
+    bucket TIMESTAMP WITH TIME ZONE NOT NULL,
+    blocks BIGINT NOT NULL,
+    tx_count BIGINT NOT NULL,
+    fees BIGINT NOT NULL,
+    runes_mints BIGINT NOT NULL,
+    -- Total size of the bucket's blocks in bytes
+    block_size BIGINT NOT NULL,
+    PRIMARY KEY (resolution, bucket)
+);
+
+INSERT INTO timeseries_buckets
+    (resolution, bucket, blocks, tx_count, fees, runes_mints, block_size)
+SELECT r.resolution,
+       date_trunc(r.unit, b.timestamp AT TIME ZONE 'UTC') AT TIME ZONE 'UTC' AS bucket,
+       COUNT(*), SUM(s.tx_count), SUM(s.fees), SUM(s.runes_minted), COALESCE(SUM(b.size), 0)
+FROM block_stats s
+JOIN blocks b ON b.height = s.height
+CROSS JOIN (VALUES ('1h', 'hour'), ('1d', 'day')) AS r(resolution, unit)
+GROUP BY r.resolution, bucket;
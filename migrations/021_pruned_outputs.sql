@@ -0,0 +1,13 @@
+-- What Electrum histories need of the spent outputs the retention policy
+-- drops: the script they paid and the transaction that spent them.
+-- Outputs pruned before this table existed are not recovered.
+CREATE TABLE pruned_outputs (
+    txid BYTEA NOT NULL,
+    vout INT NOT NULL,
+    scripthash BYTEA,
+    spending_txid BYTEA NOT NULL,
+    PRIMARY KEY (txid, vout)
+);
+
+CREATE INDEX idx_pruned_outputs_scripthash ON pruned_outputs(scripthash);
+CREATE INDEX idx_pruned_outputs_spending_txid ON pruned_outputs(spending_txid);
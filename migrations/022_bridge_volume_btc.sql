@@ -0,0 +1,18 @@
+-- Bridge volumes count BTC only: Runes deposits carry rune units in
+-- amount. Recomputing also picks up withdrawals whose payout was matched
+-- after its block's stats were recorded.
+UPDATE block_stats AS s SET
+    bridge_deposit_volume = COALESCE((
+        SELECT SUM(amount) FROM bridge_events e
+        WHERE e.block_height = s.height AND e.event_type = 'deposit'
+          AND e.status <> 'orphaned' AND e.asset = 'BTC'
+    ), 0),
+    bridge_withdrawal_volume = COALESCE((
+        SELECT SUM(amount) FROM bridge_events e
+        WHERE e.block_height = s.height AND e.event_type = 'withdrawal'
+          AND e.status <> 'orphaned' AND e.asset = 'BTC'
+    ), 0);
+
+UPDATE chain_stats SET
+    bridge_deposit_volume = (SELECT COALESCE(SUM(bridge_deposit_volume), 0) FROM block_stats),
+    bridge_withdrawal_volume = (SELECT COALESCE(SUM(bridge_withdrawal_volume), 0) FROM block_stats);
@@ -0,0 +1,14 @@
+-- Store witness stacks as raw bytes, like the other scripts since 017.
+
+-- Hex items -> bytes, keeping their order. USING clauses can't hold subqueries.
+CREATE FUNCTION pg_temp.witness_bytes(items TEXT[]) RETURNS BYTEA[] AS $$
+    SELECT COALESCE(array_agg(decode(item, 'hex') ORDER BY n), '{}')
+    FROM unnest(items) WITH ORDINALITY AS t(item, n)
+$$ LANGUAGE SQL IMMUTABLE STRICT;
+
+ALTER TABLE inputs ALTER COLUMN witness DROP DEFAULT;
+
+ALTER TABLE inputs
+    ALTER COLUMN witness TYPE BYTEA[] USING pg_temp.witness_bytes(witness);
+
+ALTER TABLE inputs ALTER COLUMN witness SET DEFAULT '{}';
@@ -0,0 +1,313 @@
+-- SQLite schema, equivalent to the PostgreSQL migrations in the parent directory.
+-- Timestamps are RFC 3339 text, NUMERIC amounts are decimal strings, and arrays
+-- are JSON.
+
+CREATE TABLE blocks (
+    height INTEGER PRIMARY KEY,
+    hash TEXT NOT NULL UNIQUE,
+    prev_hash TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    merkle_root TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    -- Raw 80-byte header, hex
+    header TEXT,
+    version INTEGER,
+    bits INTEGER,
+    nonce INTEGER,
+    difficulty REAL,
+    -- Cumulative proof of work, 64 hex digits so it orders lexicographically
+    chainwork TEXT,
+    median_time TEXT,
+    tx_count INTEGER,
+    size INTEGER,
+    stripped_size INTEGER,
+    weight INTEGER
+);
+
+CREATE INDEX idx_blocks_timestamp ON blocks(timestamp DESC);
+
+CREATE TABLE transactions (
+    txid TEXT PRIMARY KEY,
+    block_height INTEGER NOT NULL REFERENCES blocks(height),
+    block_hash TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    locktime INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    weight INTEGER NOT NULL,
+    fee INTEGER,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    tx_index INTEGER
+);
+
+CREATE INDEX idx_transactions_block_height_tx_index ON transactions(block_height, tx_index);
+
+CREATE TABLE outputs (
+    txid TEXT NOT NULL REFERENCES transactions(txid),
+    vout INTEGER NOT NULL,
+    value INTEGER NOT NULL,
+    script_pubkey TEXT NOT NULL,
+    address TEXT,
+    spent BOOLEAN NOT NULL DEFAULT FALSE,
+    spending_txid TEXT,
+    spending_vin INTEGER,
+    -- Electrum scripthash: sha256 of the scriptPubKey, byte-reversed, hex
+    scripthash TEXT,
+    PRIMARY KEY (txid, vout)
+);
+
+CREATE INDEX idx_outputs_address ON outputs(address);
+CREATE INDEX idx_outputs_address_unspent ON outputs(address) WHERE NOT spent;
+CREATE INDEX idx_outputs_spending_txid ON outputs(spending_txid);
+CREATE INDEX idx_outputs_scripthash ON outputs(scripthash);
+
+CREATE TABLE inputs (
+    txid TEXT NOT NULL,
+    vin INTEGER NOT NULL,
+    -- NULL for coinbase inputs
+    prev_txid TEXT,
+    prev_vout INTEGER,
+    script_sig TEXT NOT NULL,
+    -- JSON array of hex stack items
+    witness TEXT NOT NULL DEFAULT '[]',
+    sequence INTEGER NOT NULL,
+    -- NULL when the prevout predates the indexer start height
+    value INTEGER,
+    address TEXT,
+    PRIMARY KEY (txid, vin)
+);
+
+CREATE TABLE runes_transactions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    txid TEXT NOT NULL REFERENCES transactions(txid),
+    block_height INTEGER NOT NULL,
+    rune_id TEXT,
+    operation TEXT NOT NULL,
+    amount TEXT,
+    from_address TEXT,
+    to_address TEXT,
+    metadata TEXT,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_runes_transactions_txid ON runes_transactions(txid);
+CREATE INDEX idx_runes_transactions_height_id ON runes_transactions(block_height DESC, id DESC);
+
+CREATE TABLE inscriptions (
+    inscription_id TEXT PRIMARY KEY,
+    number INTEGER NOT NULL UNIQUE,
+    txid TEXT NOT NULL REFERENCES transactions(txid),
+    input_index INTEGER NOT NULL,
+    block_height INTEGER NOT NULL,
+    content_type TEXT,
+    content_encoding TEXT,
+    metaprotocol TEXT,
+    content_length INTEGER NOT NULL,
+    content_hash TEXT NOT NULL,
+    content BLOB,
+    location_txid TEXT,
+    location_vout INTEGER,
+    location_offset INTEGER,
+    address TEXT,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_inscriptions_block_height ON inscriptions(block_height);
+CREATE INDEX idx_inscriptions_content_type ON inscriptions(content_type);
+CREATE INDEX idx_inscriptions_location ON inscriptions(location_txid, location_vout);
+CREATE INDEX idx_inscriptions_address_number ON inscriptions(address, number DESC);
+
+CREATE TABLE inscription_transfers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    inscription_id TEXT NOT NULL REFERENCES inscriptions(inscription_id),
+    txid TEXT NOT NULL,
+    block_height INTEGER NOT NULL,
+    from_txid TEXT,
+    from_vout INTEGER,
+    from_offset INTEGER,
+    to_txid TEXT,
+    to_vout INTEGER,
+    to_offset INTEGER,
+    to_address TEXT,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_inscription_transfers_inscription_id ON inscription_transfers(inscription_id);
+CREATE INDEX idx_inscription_transfers_block_height ON inscription_transfers(block_height);
+
+CREATE TABLE brc20_tokens (
+    tick TEXT PRIMARY KEY,
+    inscription_id TEXT NOT NULL REFERENCES inscriptions(inscription_id),
+    max_supply TEXT NOT NULL,
+    mint_limit TEXT NOT NULL,
+    decimals INTEGER NOT NULL,
+    minted TEXT NOT NULL DEFAULT '0',
+    deployer TEXT,
+    block_height INTEGER NOT NULL,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_brc20_tokens_height_tick ON brc20_tokens(block_height, tick);
+
+CREATE TABLE brc20_balances (
+    address TEXT NOT NULL,
+    tick TEXT NOT NULL REFERENCES brc20_tokens(tick),
+    available TEXT NOT NULL DEFAULT '0',
+    transferable TEXT NOT NULL DEFAULT '0',
+    PRIMARY KEY (address, tick)
+);
+
+CREATE INDEX idx_brc20_balances_tick ON brc20_balances(tick);
+
+CREATE TABLE brc20_transfers (
+    inscription_id TEXT PRIMARY KEY REFERENCES inscriptions(inscription_id),
+    tick TEXT NOT NULL REFERENCES brc20_tokens(tick),
+    amount TEXT NOT NULL,
+    from_address TEXT NOT NULL,
+    to_address TEXT,
+    used BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE brc20_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    inscription_id TEXT NOT NULL,
+    operation TEXT NOT NULL,
+    tick TEXT NOT NULL,
+    amount TEXT,
+    from_address TEXT,
+    to_address TEXT,
+    txid TEXT NOT NULL,
+    block_height INTEGER NOT NULL,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_brc20_events_tick ON brc20_events(tick);
+CREATE INDEX idx_brc20_events_block_height ON brc20_events(block_height);
+
+CREATE TABLE bridge_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_type TEXT NOT NULL,
+    txid TEXT,
+    vout INTEGER,
+    block_height INTEGER,
+    starknet_tx_hash TEXT,
+    starknet_event_index INTEGER,
+    bitcoin_address TEXT,
+    starknet_address TEXT,
+    amount INTEGER,
+    asset TEXT,
+    status TEXT DEFAULT 'pending',
+    metadata TEXT,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_bridge_events_status ON bridge_events(status);
+CREATE INDEX idx_bridge_events_txid ON bridge_events(txid, vout);
+CREATE INDEX idx_bridge_events_block_height ON bridge_events(block_height);
+CREATE INDEX idx_bridge_events_starknet_tx_hash ON bridge_events(starknet_tx_hash);
+
+CREATE TABLE bridge_event_transitions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_id INTEGER NOT NULL,
+    from_status TEXT,
+    to_status TEXT NOT NULL,
+    block_height INTEGER,
+    reason TEXT,
+    created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_bridge_event_transitions_event_id ON bridge_event_transitions(event_id);
+
+CREATE TABLE sync_cursors (
+    name TEXT PRIMARY KEY,
+    block_number INTEGER NOT NULL,
+    updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE header_relay_submissions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    from_height INTEGER NOT NULL,
+    to_height INTEGER NOT NULL,
+    starknet_tx_hash TEXT,
+    status TEXT NOT NULL,
+    error TEXT,
+    created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_header_relay_submissions_status ON header_relay_submissions(status);
+
+CREATE TABLE relayed_headers (
+    height INTEGER PRIMARY KEY,
+    hash TEXT NOT NULL,
+    submission_id INTEGER NOT NULL REFERENCES header_relay_submissions(id)
+);
+
+CREATE INDEX idx_relayed_headers_submission_id ON relayed_headers(submission_id);
+
+CREATE TABLE address_transactions (
+    address TEXT NOT NULL,
+    txid TEXT NOT NULL,
+    block_height INTEGER NOT NULL,
+    tx_index INTEGER NOT NULL DEFAULT 0,
+    received INTEGER NOT NULL DEFAULT 0,
+    sent INTEGER NOT NULL DEFAULT 0,
+    funded_outputs INTEGER NOT NULL DEFAULT 0,
+    spent_outputs INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (address, txid)
+);
+
+CREATE INDEX idx_address_transactions_history
+    ON address_transactions(address, block_height DESC, tx_index DESC);
+CREATE INDEX idx_address_transactions_block_height ON address_transactions(block_height);
+
+CREATE TABLE address_stats (
+    address TEXT PRIMARY KEY,
+    received INTEGER NOT NULL DEFAULT 0,
+    sent INTEGER NOT NULL DEFAULT 0,
+    tx_count INTEGER NOT NULL DEFAULT 0,
+    funded_txo_count INTEGER NOT NULL DEFAULT 0,
+    spent_txo_count INTEGER NOT NULL DEFAULT 0,
+    first_seen_height INTEGER,
+    last_seen_height INTEGER
+);
+
+CREATE TABLE block_stats (
+    height INTEGER PRIMARY KEY,
+    tx_count INTEGER NOT NULL,
+    output_count INTEGER NOT NULL,
+    output_value INTEGER NOT NULL,
+    spent_count INTEGER NOT NULL,
+    spent_value INTEGER NOT NULL,
+    runes_transactions INTEGER NOT NULL,
+    runes_etched INTEGER NOT NULL,
+    runes_minted INTEGER NOT NULL,
+    runes_burned INTEGER NOT NULL,
+    bridge_deposit_volume INTEGER NOT NULL,
+    bridge_withdrawal_volume INTEGER NOT NULL,
+    indexed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE chain_stats (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    blocks INTEGER NOT NULL DEFAULT 0,
+    transactions INTEGER NOT NULL DEFAULT 0,
+    outputs INTEGER NOT NULL DEFAULT 0,
+    utxo_count INTEGER NOT NULL DEFAULT 0,
+    utxo_value INTEGER NOT NULL DEFAULT 0,
+    runes_transactions INTEGER NOT NULL DEFAULT 0,
+    runes_etched INTEGER NOT NULL DEFAULT 0,
+    runes_minted INTEGER NOT NULL DEFAULT 0,
+    runes_burned INTEGER NOT NULL DEFAULT 0,
+    bridge_deposit_volume INTEGER NOT NULL DEFAULT 0,
+    bridge_withdrawal_volume INTEGER NOT NULL DEFAULT 0,
+    node_height INTEGER
+);
+
+INSERT INTO chain_stats DEFAULT VALUES;
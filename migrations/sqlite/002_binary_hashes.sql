@@ -0,0 +1,443 @@
+-- Store hashes and scripts as raw bytes instead of hex text, matching the
+-- PostgreSQL schema. Hashes are kept in internal byte order; the API still
+-- displays them byte-reversed like bitcoind.
+--
+-- SQLite can't change a column's type, so the affected tables are copied into
+-- new ones. Their foreign keys point at the other new tables and follow them
+-- when they are renamed; tables that only reference a rebuilt one are copied
+-- too, since dropping a referenced table would orphan their rows.
+
+-- Display hex -> internal bytes: hex digit pairs read last to first
+CREATE TEMP TABLE hash_positions (i INTEGER PRIMARY KEY);
+WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 32)
+INSERT INTO hash_positions SELECT i FROM n;
+
+CREATE TABLE blocks_new (
+    height INTEGER PRIMARY KEY,
+    hash BLOB NOT NULL UNIQUE,
+    prev_hash BLOB NOT NULL,
+    timestamp TEXT NOT NULL,
+    merkle_root BLOB NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    -- Raw 80-byte header
+    header BLOB,
+    version INTEGER,
+    bits INTEGER,
+    nonce INTEGER,
+    difficulty REAL,
+    -- Cumulative proof of work, 64 hex digits so it orders lexicographically
+    chainwork TEXT,
+    median_time TEXT,
+    tx_count INTEGER,
+    size INTEGER,
+    stripped_size INTEGER,
+    weight INTEGER
+);
+
+INSERT INTO blocks_new
+SELECT height,
+       (SELECT unhex(group_concat(substr(hash, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       (SELECT unhex(group_concat(substr(prev_hash, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       timestamp,
+       (SELECT unhex(group_concat(substr(merkle_root, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       indexed_at,
+       unhex(header),
+       version,
+       bits,
+       nonce,
+       difficulty,
+       chainwork,
+       median_time,
+       tx_count,
+       size,
+       stripped_size,
+       weight
+FROM blocks;
+
+CREATE TABLE transactions_new (
+    txid BLOB PRIMARY KEY,
+    block_height INTEGER NOT NULL REFERENCES blocks_new(height),
+    block_hash BLOB NOT NULL,
+    version INTEGER NOT NULL,
+    locktime INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    weight INTEGER NOT NULL,
+    fee INTEGER,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    tx_index INTEGER
+);
+
+INSERT INTO transactions_new
+SELECT (SELECT unhex(group_concat(substr(txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       block_height,
+       (SELECT unhex(group_concat(substr(block_hash, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       version,
+       locktime,
+       size,
+       weight,
+       fee,
+       timestamp,
+       indexed_at,
+       tx_index
+FROM transactions;
+
+CREATE TABLE outputs_new (
+    txid BLOB NOT NULL REFERENCES transactions_new(txid),
+    vout INTEGER NOT NULL,
+    value INTEGER NOT NULL,
+    script_pubkey BLOB NOT NULL,
+    address TEXT,
+    spent BOOLEAN NOT NULL DEFAULT FALSE,
+    spending_txid BLOB,
+    spending_vin INTEGER,
+    -- Electrum scripthash: sha256 of the scriptPubKey
+    scripthash BLOB,
+    PRIMARY KEY (txid, vout)
+);
+
+INSERT INTO outputs_new
+SELECT (SELECT unhex(group_concat(substr(txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       vout,
+       value,
+       unhex(script_pubkey),
+       address,
+       spent,
+       (SELECT unhex(group_concat(substr(spending_txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       spending_vin,
+       (SELECT unhex(group_concat(substr(scripthash, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions)
+FROM outputs;
+
+CREATE TABLE inputs_new (
+    txid BLOB NOT NULL,
+    vin INTEGER NOT NULL,
+    -- NULL for coinbase inputs
+    prev_txid BLOB,
+    prev_vout INTEGER,
+    script_sig BLOB NOT NULL,
+    -- JSON array of hex stack items
+    witness TEXT NOT NULL DEFAULT '[]',
+    sequence INTEGER NOT NULL,
+    -- NULL when the prevout predates the indexer start height
+    value INTEGER,
+    address TEXT,
+    PRIMARY KEY (txid, vin)
+);
+
+INSERT INTO inputs_new
+SELECT (SELECT unhex(group_concat(substr(txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       vin,
+       (SELECT unhex(group_concat(substr(prev_txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       prev_vout,
+       unhex(script_sig),
+       witness,
+       sequence,
+       value,
+       address
+FROM inputs;
+
+CREATE TABLE runes_transactions_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    txid BLOB NOT NULL REFERENCES transactions_new(txid),
+    block_height INTEGER NOT NULL,
+    rune_id TEXT,
+    operation TEXT NOT NULL,
+    amount TEXT,
+    from_address TEXT,
+    to_address TEXT,
+    metadata TEXT,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+INSERT INTO runes_transactions_new
+SELECT id,
+       (SELECT unhex(group_concat(substr(txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       block_height,
+       rune_id,
+       operation,
+       amount,
+       from_address,
+       to_address,
+       metadata,
+       timestamp,
+       indexed_at
+FROM runes_transactions;
+
+CREATE TABLE inscriptions_new (
+    inscription_id TEXT PRIMARY KEY,
+    number INTEGER NOT NULL UNIQUE,
+    txid BLOB NOT NULL REFERENCES transactions_new(txid),
+    input_index INTEGER NOT NULL,
+    block_height INTEGER NOT NULL,
+    content_type TEXT,
+    content_encoding TEXT,
+    metaprotocol TEXT,
+    content_length INTEGER NOT NULL,
+    content_hash TEXT NOT NULL,
+    content BLOB,
+    location_txid BLOB,
+    location_vout INTEGER,
+    location_offset INTEGER,
+    address TEXT,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+INSERT INTO inscriptions_new
+SELECT inscription_id,
+       number,
+       (SELECT unhex(group_concat(substr(txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       input_index,
+       block_height,
+       content_type,
+       content_encoding,
+       metaprotocol,
+       content_length,
+       content_hash,
+       content,
+       (SELECT unhex(group_concat(substr(location_txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       location_vout,
+       location_offset,
+       address,
+       timestamp,
+       indexed_at
+FROM inscriptions;
+
+CREATE TABLE inscription_transfers_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    inscription_id TEXT NOT NULL REFERENCES inscriptions_new(inscription_id),
+    txid BLOB NOT NULL,
+    block_height INTEGER NOT NULL,
+    from_txid BLOB,
+    from_vout INTEGER,
+    from_offset INTEGER,
+    to_txid BLOB,
+    to_vout INTEGER,
+    to_offset INTEGER,
+    to_address TEXT,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+INSERT INTO inscription_transfers_new
+SELECT id,
+       inscription_id,
+       (SELECT unhex(group_concat(substr(txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       block_height,
+       (SELECT unhex(group_concat(substr(from_txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       from_vout,
+       from_offset,
+       (SELECT unhex(group_concat(substr(to_txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       to_vout,
+       to_offset,
+       to_address,
+       timestamp,
+       indexed_at
+FROM inscription_transfers;
+
+CREATE TABLE brc20_tokens_new (
+    tick TEXT PRIMARY KEY,
+    inscription_id TEXT NOT NULL REFERENCES inscriptions_new(inscription_id),
+    max_supply TEXT NOT NULL,
+    mint_limit TEXT NOT NULL,
+    decimals INTEGER NOT NULL,
+    minted TEXT NOT NULL DEFAULT '0',
+    deployer TEXT,
+    block_height INTEGER NOT NULL,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+INSERT INTO brc20_tokens_new SELECT * FROM brc20_tokens;
+
+CREATE TABLE brc20_balances_new (
+    address TEXT NOT NULL,
+    tick TEXT NOT NULL REFERENCES brc20_tokens_new(tick),
+    available TEXT NOT NULL DEFAULT '0',
+    transferable TEXT NOT NULL DEFAULT '0',
+    PRIMARY KEY (address, tick)
+);
+
+INSERT INTO brc20_balances_new SELECT * FROM brc20_balances;
+
+CREATE TABLE brc20_transfers_new (
+    inscription_id TEXT PRIMARY KEY REFERENCES inscriptions_new(inscription_id),
+    tick TEXT NOT NULL REFERENCES brc20_tokens_new(tick),
+    amount TEXT NOT NULL,
+    from_address TEXT NOT NULL,
+    to_address TEXT,
+    used BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+INSERT INTO brc20_transfers_new SELECT * FROM brc20_transfers;
+
+CREATE TABLE brc20_events_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    inscription_id TEXT NOT NULL,
+    operation TEXT NOT NULL,
+    tick TEXT NOT NULL,
+    amount TEXT,
+    from_address TEXT,
+    to_address TEXT,
+    txid BLOB NOT NULL,
+    block_height INTEGER NOT NULL,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+INSERT INTO brc20_events_new
+SELECT id,
+       inscription_id,
+       operation,
+       tick,
+       amount,
+       from_address,
+       to_address,
+       (SELECT unhex(group_concat(substr(txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       block_height,
+       timestamp,
+       indexed_at
+FROM brc20_events;
+
+CREATE TABLE bridge_events_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_type TEXT NOT NULL,
+    txid BLOB,
+    vout INTEGER,
+    block_height INTEGER,
+    starknet_tx_hash TEXT,
+    starknet_event_index INTEGER,
+    bitcoin_address TEXT,
+    starknet_address TEXT,
+    amount INTEGER,
+    asset TEXT,
+    status TEXT DEFAULT 'pending',
+    metadata TEXT,
+    timestamp TEXT NOT NULL,
+    indexed_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+INSERT INTO bridge_events_new
+SELECT id,
+       event_type,
+       (SELECT unhex(group_concat(substr(txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       vout,
+       block_height,
+       starknet_tx_hash,
+       starknet_event_index,
+       bitcoin_address,
+       starknet_address,
+       amount,
+       asset,
+       status,
+       metadata,
+       timestamp,
+       indexed_at
+FROM bridge_events;
+
+CREATE TABLE relayed_headers_new (
+    height INTEGER PRIMARY KEY,
+    hash BLOB NOT NULL,
+    submission_id INTEGER NOT NULL REFERENCES header_relay_submissions(id)
+);
+
+INSERT INTO relayed_headers_new
+SELECT height,
+       (SELECT unhex(group_concat(substr(hash, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       submission_id
+FROM relayed_headers;
+
+CREATE TABLE address_transactions_new (
+    address TEXT NOT NULL,
+    txid BLOB NOT NULL,
+    block_height INTEGER NOT NULL,
+    tx_index INTEGER NOT NULL DEFAULT 0,
+    received INTEGER NOT NULL DEFAULT 0,
+    sent INTEGER NOT NULL DEFAULT 0,
+    funded_outputs INTEGER NOT NULL DEFAULT 0,
+    spent_outputs INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (address, txid)
+);
+
+INSERT INTO address_transactions_new
+SELECT address,
+       (SELECT unhex(group_concat(substr(txid, 2 * i - 1, 2), '' ORDER BY i DESC)) FROM hash_positions),
+       block_height,
+       tx_index,
+       received,
+       sent,
+       funded_outputs,
+       spent_outputs
+FROM address_transactions;
+
+-- Children before parents, so no row is left referencing a dropped table
+DROP TABLE address_transactions;
+DROP TABLE relayed_headers;
+DROP TABLE bridge_events;
+DROP TABLE brc20_events;
+DROP TABLE brc20_transfers;
+DROP TABLE brc20_balances;
+DROP TABLE brc20_tokens;
+DROP TABLE inscription_transfers;
+DROP TABLE inscriptions;
+DROP TABLE runes_transactions;
+DROP TABLE inputs;
+DROP TABLE outputs;
+DROP TABLE transactions;
+DROP TABLE blocks;
+
+ALTER TABLE blocks_new RENAME TO blocks;
+ALTER TABLE transactions_new RENAME TO transactions;
+ALTER TABLE outputs_new RENAME TO outputs;
+ALTER TABLE inputs_new RENAME TO inputs;
+ALTER TABLE runes_transactions_new RENAME TO runes_transactions;
+ALTER TABLE inscriptions_new RENAME TO inscriptions;
+ALTER TABLE inscription_transfers_new RENAME TO inscription_transfers;
+ALTER TABLE brc20_tokens_new RENAME TO brc20_tokens;
+ALTER TABLE brc20_balances_new RENAME TO brc20_balances;
+ALTER TABLE brc20_transfers_new RENAME TO brc20_transfers;
+ALTER TABLE brc20_events_new RENAME TO brc20_events;
+ALTER TABLE bridge_events_new RENAME TO bridge_events;
+ALTER TABLE relayed_headers_new RENAME TO relayed_headers;
+ALTER TABLE address_transactions_new RENAME TO address_transactions;
+
+CREATE INDEX idx_blocks_timestamp ON blocks(timestamp DESC);
+
+CREATE INDEX idx_transactions_block_height_tx_index ON transactions(block_height, tx_index);
+
+CREATE INDEX idx_outputs_address ON outputs(address);
+CREATE INDEX idx_outputs_address_unspent ON outputs(address) WHERE NOT spent;
+CREATE INDEX idx_outputs_spending_txid ON outputs(spending_txid);
+CREATE INDEX idx_outputs_scripthash ON outputs(scripthash);
+
+CREATE INDEX idx_runes_transactions_txid ON runes_transactions(txid);
+CREATE INDEX idx_runes_transactions_height_id ON runes_transactions(block_height DESC, id DESC);
+
+CREATE INDEX idx_inscriptions_block_height ON inscriptions(block_height);
+CREATE INDEX idx_inscriptions_content_type ON inscriptions(content_type);
+CREATE INDEX idx_inscriptions_location ON inscriptions(location_txid, location_vout);
+CREATE INDEX idx_inscriptions_address_number ON inscriptions(address, number DESC);
+
+CREATE INDEX idx_inscription_transfers_inscription_id ON inscription_transfers(inscription_id);
+CREATE INDEX idx_inscription_transfers_block_height ON inscription_transfers(block_height);
+
+CREATE INDEX idx_brc20_tokens_height_tick ON brc20_tokens(block_height, tick);
+
+CREATE INDEX idx_brc20_balances_tick ON brc20_balances(tick);
+
+CREATE INDEX idx_brc20_events_tick ON brc20_events(tick);
+CREATE INDEX idx_brc20_events_block_height ON brc20_events(block_height);
+
+CREATE INDEX idx_bridge_events_status ON bridge_events(status);
+CREATE INDEX idx_bridge_events_txid ON bridge_events(txid, vout);
+CREATE INDEX idx_bridge_events_block_height ON bridge_events(block_height);
+CREATE INDEX idx_bridge_events_starknet_tx_hash ON bridge_events(starknet_tx_hash);
+
+CREATE INDEX idx_relayed_headers_submission_id ON relayed_headers(submission_id);
+
+CREATE INDEX idx_address_transactions_history
+    ON address_transactions(address, block_height DESC, tx_index DESC);
+CREATE INDEX idx_address_transactions_block_height ON address_transactions(block_height);
+
+DROP TABLE hash_positions;
@@ -0,0 +1,8 @@
+-- Retention policies prune transaction detail in the background. Pruned
+-- transactions keep their row, so blocks still list every txid.
+ALTER TABLE transactions ADD COLUMN pruned BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Pruning finds protocol data by txid
+CREATE INDEX idx_inscriptions_txid ON inscriptions(txid);
+CREATE INDEX idx_inscription_transfers_txid ON inscription_transfers(txid);
+CREATE INDEX idx_brc20_events_txid ON brc20_events(txid);
@@ -0,0 +1,35 @@
+-- Hourly and daily chain activity for /analytics/timeseries, maintained per
+-- block alongside chain_stats.
+
+-- Fees claimed by the coinbase above the block subsidy
+ALTER TABLE block_stats ADD COLUMN fees INTEGER NOT NULL DEFAULT 0;
+
+UPDATE block_stats
+SET fees = MAX(COALESCE((
+    SELECT SUM(o.value) FROM transactions t JOIN outputs o ON o.txid = t.txid
+    WHERE t.block_height = block_stats.height AND t.tx_index = 0
+), 0) - (5000000000 >> MIN(height / 210000, 63)), 0);
+
+CREATE TABLE timeseries_buckets (
+    -- `1h` or `1d`
+    resolution TEXT NOT NULL,
+    -- Start of the bucket in UTC, by block timestamp
+    bucket TEXT NOT NULL,
+    blocks INTEGER NOT NULL,
+    tx_count INTEGER NOT NULL,
+    fees INTEGER NOT NULL,
+    runes_mints INTEGER NOT NULL,
+    -- Total size of the bucket's blocks in bytes
+    block_size INTEGER NOT NULL,
+    PRIMARY KEY (resolution, bucket)
+);
+
+INSERT INTO timeseries_buckets
+    (resolution, bucket, blocks, tx_count, fees, runes_mints, block_size)
+SELECT r.resolution, strftime(r.format, b.timestamp) AS bucket,
+       COUNT(*), SUM(s.tx_count), SUM(s.fees), SUM(s.runes_minted), COALESCE(SUM(b.size), 0)
+FROM block_stats s
+JOIN blocks b ON b.height = s.height
+CROSS JOIN (SELECT '1h' AS resolution, '%Y-%m-%dT%H:00:00Z' AS format
+            UNION ALL SELECT '1d', '%Y-%m-%dT00:00:00Z') AS r
+GROUP BY r.resolution, bucket;
@@ -0,0 +1,8 @@
+-- Outputs each transaction created, so readers can tell which of them the
+-- spent outputs retention policy dropped. Transactions flagged pruned
+-- before it was recorded may have lost outputs already and are left NULL.
+ALTER TABLE transactions ADD COLUMN output_count INT;
+
+UPDATE transactions
+SET output_count = (SELECT COUNT(*) FROM outputs o WHERE o.txid = transactions.txid)
+WHERE NOT pruned;
@@ -0,0 +1,6 @@
+-- Retry state of failed submissions: which attempt at the batch starting at
+-- from_height each one was, and when the batch may be sent again
+ALTER TABLE header_relay_submissions ADD COLUMN attempt INTEGER;
+ALTER TABLE header_relay_submissions ADD COLUMN retry_after TEXT;
+
+CREATE INDEX idx_header_relay_submissions_from_height ON header_relay_submissions(from_height);
@@ -0,0 +1,319 @@
+//! Time-bucketed chain activity shared by `/analytics/timeseries` and the
+//! GraphQL `timeseries` query. Buckets are maintained per block, so a request
+//! reads at most `MAX_BUCKETS` rows.
+
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::Serialize;
+use std::{fmt, str::FromStr};
+
+use crate::{
+    db::{models::TimeseriesBucketModel, Storage},
+    utils::error::{IndexerError, Result},
+};
+
+/// Buckets returned when `from` is not given
+const DEFAULT_BUCKETS: i32 = 168;
+
+/// Most buckets one request may span
+const MAX_BUCKETS: i64 = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, async_graphql::Enum)]
+pub enum Metric {
+    /// Transactions confirmed
+    TxCount,
+    /// Fees claimed by miners, in sats
+    Fees,
+    /// Runes mint operations
+    RunesMints,
+    /// Average block size in bytes
+    BlockSize,
+}
+
+impl FromStr for Metric {
+    type Err = IndexerError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "tx_count" => Ok(Metric::TxCount),
+            "fees" => Ok(Metric::Fees),
+            "runes_mints" => Ok(Metric::RunesMints),
+            "block_size" => Ok(Metric::BlockSize),
+            _ => Err(IndexerError::invalid(
+                "metric",
+                "expected `tx_count`, `fees`, `runes_mints` or `block_size`",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Metric::TxCount => "tx_count",
+            Metric::Fees => "fees",
+            Metric::RunesMints => "runes_mints",
+            Metric::BlockSize => "block_size",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, async_graphql::Enum)]
+pub enum Interval {
+    Hour,
+    Day,
+}
+
+impl Interval {
+    fn duration(&self) -> TimeDelta {
+        match self {
+            Interval::Hour => TimeDelta::hours(1),
+            Interval::Day => TimeDelta::days(1),
+        }
+    }
+}
+
+impl FromStr for Interval {
+    type Err = IndexerError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "1h" => Ok(Interval::Hour),
+            "1d" => Ok(Interval::Day),
+            _ => Err(IndexerError::invalid("interval", "expected `1h` or `1d`")),
+        }
+    }
+}
+
+/// Also the `resolution` the buckets are stored under
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Interval::Hour => "1h",
+            Interval::Day => "1d",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Point {
+    /// Start of the bucket
+    pub timestamp: DateTime<Utc>,
+    /// Blocks timestamped within the bucket
+    pub blocks: i64,
+    /// `null` for averages over a bucket without blocks
+    pub value: Option<i64>,
+}
+
+impl Point {
+    fn new(metric: Metric, bucket: &TimeseriesBucketModel) -> Self {
+        let value = match metric {
+            Metric::TxCount => Some(bucket.tx_count),
+            Metric::Fees => Some(bucket.fees),
+            Metric::RunesMints => Some(bucket.runes_mints),
+            Metric::BlockSize => bucket.block_size.checked_div(bucket.blocks),
+        };
+
+        Self {
+            timestamp: bucket.bucket,
+            blocks: bucket.blocks,
+            value,
+        }
+    }
+}
+
+/// The buckets starting within `from..=to`, with empty ones filled in. `to`
+/// defaults to now and `from` to `DEFAULT_BUCKETS` buckets before it.
+pub async fn timeseries(
+    db: &dyn Storage,
+    metric: Metric,
+    interval: Interval,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<Point>> {
+    let step = interval.duration();
+    let to = to.unwrap_or_else(Utc::now);
+    // Blame whichever parameter `from` came from
+    let (field, from) = match from {
+        Some(from) => ("from", Some(from)),
+        None => ("to", to.checked_sub_signed(step * (DEFAULT_BUCKETS - 1))),
+    };
+    let from = from
+        .and_then(|from| from.duration_trunc(step).ok())
+        .ok_or_else(|| IndexerError::invalid(field, "out of range"))?;
+    if from > to {
+        return Err(IndexerError::invalid("from", "must not be after `to`"));
+    }
+    if (to - from).num_seconds() / step.num_seconds() >= MAX_BUCKETS {
+        return Err(IndexerError::invalid(
+            "from",
+            format!("range spans more than {} buckets", MAX_BUCKETS),
+        ));
+    }
+
+    let stored = db
+        .get_timeseries(&interval.to_string(), from, to)
+        .await?
+        .into_iter()
+        .map(|bucket| (bucket.bucket, bucket));
+    let mut stored = stored.peekable();
+
+    let mut points = Vec::new();
+    let mut next = Some(from);
+    while let Some(timestamp) = next.filter(|timestamp| *timestamp <= to) {
+        let point = match stored.next_if(|(bucket, _)| *bucket == timestamp) {
+            Some((_, bucket)) => Point::new(metric, &bucket),
+            None => Point::new(
+                metric,
+                &TimeseriesBucketModel {
+                    bucket: timestamp,
+                    blocks: 0,
+                    tx_count: 0,
+                    fees: 0,
+                    runes_mints: 0,
+                    block_size: 0,
+                },
+            ),
+        };
+        points.push(point);
+        next = timestamp.checked_add_signed(step);
+    }
+
+    Ok(points)
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::SqliteDatabase;
+    use bitcoin::{absolute::LockTime, blockdata::constants::genesis_block, Amount, Network};
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    /// Indexes a block at `time` whose coinbase claims `fees` above the subsidy
+    async fn mine(db: &SqliteDatabase, height: u64, time: &str, fees: u64) {
+        let mut block = genesis_block(Network::Regtest);
+        block.header.time = at(time).timestamp() as u32;
+        let coinbase = &mut block.txdata[0];
+        coinbase.lock_time = LockTime::from_height(height as u32).unwrap();
+        coinbase.output[0].value = Amount::from_sat(5_000_000_000 + fees);
+        db.insert_block(&block, height, &[0; 32], None)
+            .await
+            .unwrap();
+        db.insert_transaction(
+            &block.txdata[0],
+            0,
+            height,
+            &block.header,
+            None,
+            &[],
+            Network::Regtest,
+        )
+        .await
+        .unwrap();
+        db.insert_block_stats(height).await.unwrap();
+    }
+
+    fn invalid(result: Result<Vec<Point>>) -> &'static str {
+        match result {
+            Err(IndexerError::InvalidInput { field, .. }) => field,
+            other => panic!("expected invalid input, got {:?}", other.map(|p| p.len())),
+        }
+    }
+
+    #[tokio::test]
+    async fn fills_buckets_without_blocks() {
+        let db = SqliteDatabase::new("sqlite::memory:", 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+        mine(&db, 1, "2024-01-01T00:10:00Z", 100).await;
+        mine(&db, 2, "2024-01-01T00:50:00Z", 200).await;
+        mine(&db, 3, "2024-01-01T03:30:00Z", 50).await;
+
+        // `from` is rounded down to the start of its bucket
+        let from = Some(at("2024-01-01T00:20:00Z"));
+        let end = at("2024-01-01T04:00:00Z");
+        let to = Some(end);
+        let points = timeseries(&db, Metric::Fees, Interval::Hour, from, to)
+            .await
+            .unwrap();
+        let points: Vec<_> = points
+            .iter()
+            .map(|p| (p.timestamp.to_rfc3339(), p.blocks, p.value))
+            .collect();
+        assert_eq!(
+            points,
+            [
+                ("2024-01-01T00:00:00+00:00".into(), 2, Some(300)),
+                ("2024-01-01T01:00:00+00:00".into(), 0, Some(0)),
+                ("2024-01-01T02:00:00+00:00".into(), 0, Some(0)),
+                ("2024-01-01T03:00:00+00:00".into(), 1, Some(50)),
+                ("2024-01-01T04:00:00+00:00".into(), 0, Some(0)),
+            ]
+        );
+
+        // Averages have no value without blocks
+        let sizes = timeseries(&db, Metric::BlockSize, Interval::Hour, from, to)
+            .await
+            .unwrap();
+        let block_size = genesis_block(Network::Regtest).total_size() as i64;
+        assert_eq!(sizes[0].value, Some(block_size));
+        assert_eq!(sizes[1].value, None);
+
+        let days = timeseries(&db, Metric::Fees, Interval::Day, from, to)
+            .await
+            .unwrap();
+        assert_eq!(days.len(), 1);
+        assert_eq!((days[0].blocks, days[0].value), (3, Some(350)));
+
+        // Without `from`, the default span ends at `to`
+        let points = timeseries(&db, Metric::TxCount, Interval::Hour, None, to)
+            .await
+            .unwrap();
+        assert_eq!(points.len(), DEFAULT_BUCKETS as usize);
+        assert_eq!(points.last().unwrap().timestamp, end);
+    }
+
+    #[tokio::test]
+    async fn rejects_reversed_and_oversized_ranges() {
+        let db = SqliteDatabase::new("sqlite::memory:", 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+        let to = at("2024-01-01T00:00:00Z");
+        let hours_before = |hours: i64| Some(to - TimeDelta::hours(hours));
+        let series = |from, to| timeseries(&db, Metric::TxCount, Interval::Hour, from, to);
+
+        assert_eq!(
+            invalid(series(Some(to + TimeDelta::hours(1)), Some(to)).await),
+            "from"
+        );
+        assert_eq!(series(Some(to), Some(to)).await.unwrap().len(), 1);
+
+        let widest = series(hours_before(MAX_BUCKETS - 1), Some(to)).await;
+        assert_eq!(widest.unwrap().len(), MAX_BUCKETS as usize);
+        assert_eq!(
+            invalid(series(hours_before(MAX_BUCKETS), Some(to)).await),
+            "from"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_ranges_out_of_bounds_instead_of_overflowing() {
+        let db = SqliteDatabase::new("sqlite::memory:", 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+        let series = |from, to| timeseries(&db, Metric::TxCount, Interval::Day, from, to);
+
+        // The default `from` would precede the earliest representable time
+        assert_eq!(
+            invalid(series(None, Some(DateTime::<Utc>::MIN_UTC)).await),
+            "to"
+        );
+        // Buckets can't be aligned this far out
+        let end = Some(DateTime::<Utc>::MAX_UTC);
+        assert_eq!(invalid(series(end, end).await), "from");
+        assert_eq!(
+            invalid(series(Some(DateTime::<Utc>::MIN_UTC), None).await),
+            "from"
+        );
+    }
+}
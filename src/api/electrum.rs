@@ -0,0 +1,731 @@
+//! Electrum protocol server (TCP, newline-delimited JSON-RPC) over the indexed
+//! chain. Only confirmed transactions are known, so mempool queries are empty.
+
+use anyhow::{anyhow, Result};
+use bitcoin::{
+    consensus::encode::serialize_hex,
+    hashes::{sha256, Hash},
+    Transaction,
+};
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::Arc};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+    time::{interval, Duration},
+};
+use tokio_util::codec::{FramedRead, LinesCodec, LinesCodecError};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::Config,
+    db::{models::Hash256, Storage},
+    utils::{
+        merkle::{self, MerkleTree},
+        transaction::rebuild_transaction,
+    },
+};
+
+const PROTOCOL_VERSION: &str = "1.4";
+
+/// Most headers returned by one `blockchain.block.headers` call
+const MAX_HEADERS: u64 = 2016;
+
+/// Longest request line, batches included, a client may send
+const MAX_REQUEST_BYTES: usize = 1024 * 1024;
+
+/// Minimum relay fee reported to wallets, in BTC/kvB
+const RELAY_FEE: f64 = 0.00001;
+
+/// JSON-RPC error codes as used by ElectrumX
+const BAD_REQUEST: i64 = 1;
+const DAEMON_ERROR: i64 = 2;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            code: BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self {
+            code: DAEMON_ERROR,
+            message: e.to_string(),
+        }
+    }
+}
+
+pub struct ElectrumServer {
+    db: Arc<dyn Storage>,
+    config: Arc<Config>,
+    bind: String,
+    /// Merkle tree of the block hashes up to the last checkpoint asked for.
+    /// Wallets ship a fixed checkpoint, so one tree serves most requests.
+    checkpoint: Mutex<Option<Checkpoint>>,
+}
+
+struct Checkpoint {
+    height: u64,
+    hash: Hash256,
+    tree: MerkleTree,
+}
+
+impl ElectrumServer {
+    /// Returns `None` unless `electrum_bind` is configured.
+    pub fn new(db: Arc<dyn Storage>, config: Arc<Config>) -> Option<Self> {
+        let bind = config.electrum_bind.clone()?;
+        Some(Self {
+            db,
+            config,
+            bind,
+            checkpoint: Mutex::new(None),
+        })
+    }
+
+    pub async fn start(self) -> Result<()> {
+        let listener = TcpListener::bind(&self.bind).await?;
+        info!("Electrum server listening on {}", self.bind);
+
+        let server = Arc::new(self);
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let server = server.clone();
+            tokio::spawn(async move {
+                debug!("Electrum client {} connected", peer);
+                if let Err(e) = server.handle_connection(stream).await {
+                    debug!("Electrum client {} disconnected: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, stream: TcpStream) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = FramedRead::new(reader, LinesCodec::new_with_max_length(MAX_REQUEST_BYTES));
+        let mut session = Session::default();
+        let mut ticker = interval(Duration::from_secs(self.config.indexer_poll_interval_secs));
+
+        loop {
+            tokio::select! {
+                line = lines.next() => {
+                    let line = match line {
+                        None => return Ok(()),
+                        Some(Ok(line)) => line,
+                        Some(Err(LinesCodecError::MaxLineLengthExceeded)) => {
+                            let response = json!({
+                                "jsonrpc": "2.0",
+                                "id": null,
+                                "error": { "code": BAD_REQUEST, "message": "request too large" },
+                            });
+                            writer.write_all(format!("{}\n", response).as_bytes()).await?;
+                            return Err(anyhow!("request over {} bytes", MAX_REQUEST_BYTES));
+                        }
+                        Some(Err(LinesCodecError::Io(e))) => return Err(e.into()),
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let response = self.handle_line(&mut session, &line).await;
+                    writer.write_all(format!("{}\n", response).as_bytes()).await?;
+                }
+                _ = ticker.tick() => {
+                    // A failed check is retried in full on the next tick
+                    let notifications = match self.notifications(&mut session).await {
+                        Ok(notifications) => notifications,
+                        Err(e) => {
+                            warn!("Error checking Electrum subscriptions: {}", e);
+                            continue;
+                        }
+                    };
+                    for notification in notifications {
+                        writer.write_all(format!("{}\n", notification).as_bytes()).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles a single request or a batch.
+    async fn handle_line(&self, session: &mut Session, line: &str) -> Value {
+        match serde_json::from_str::<Value>(line) {
+            Ok(Value::Array(requests)) => {
+                let mut responses = Vec::with_capacity(requests.len());
+                for request in &requests {
+                    responses.push(self.handle_request(session, request).await);
+                }
+                Value::Array(responses)
+            }
+            Ok(request) => self.handle_request(session, &request).await,
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": BAD_REQUEST, "message": format!("invalid JSON: {}", e) },
+            }),
+        }
+    }
+
+    async fn handle_request(&self, session: &mut Session, request: &Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = match request.get("params") {
+            Some(Value::Array(params)) => params.clone(),
+            _ => Vec::new(),
+        };
+
+        match self.dispatch(session, method, &params).await {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": e.code, "message": e.message },
+            }),
+        }
+    }
+
+    async fn dispatch(
+        &self,
+        session: &mut Session,
+        method: &str,
+        params: &[Value],
+    ) -> Result<Value, RpcError> {
+        let str_param = |index: usize| {
+            params
+                .get(index)
+                .and_then(Value::as_str)
+                .ok_or_else(|| RpcError::bad_request(format!("missing parameter {}", index)))
+        };
+        let hash_param = |index: usize| {
+            str_param(index)?
+                .parse::<Hash256>()
+                .map_err(|_| RpcError::bad_request(format!("invalid hash parameter {}", index)))
+        };
+        let u64_param = |index: usize| {
+            params
+                .get(index)
+                .and_then(Value::as_u64)
+                .ok_or_else(|| RpcError::bad_request(format!("missing parameter {}", index)))
+        };
+
+        match method {
+            "server.version" => {
+                negotiate_version(params.get(1))?;
+                Ok(json!([
+                    format!("starknet-btc-indexer {}", env!("CARGO_PKG_VERSION")),
+                    PROTOCOL_VERSION
+                ]))
+            }
+            "server.banner" => Ok(json!("starknet-btc-indexer Electrum server")),
+            "server.donation_address" => Ok(json!("")),
+            "server.peers.subscribe" => Ok(json!([])),
+            "server.ping" => Ok(Value::Null),
+            "blockchain.relayfee" => Ok(json!(RELAY_FEE)),
+            "blockchain.estimatefee" => self.estimate_fee(u64_param(0)?).await,
+            "mempool.get_fee_histogram" => Ok(json!([])),
+            "blockchain.headers.subscribe" => {
+                let tip = self.tip_header().await?;
+                session.last_tip = tip.get("height").and_then(Value::as_i64);
+                session.headers_subscribed = true;
+                Ok(tip)
+            }
+            "blockchain.block.header" => {
+                let height = u64_param(0)?;
+                let cp_height = params.get(1).and_then(Value::as_u64).unwrap_or_default();
+                let block = self.db.get_block_by_height(height).await?.ok_or_else(|| {
+                    RpcError::bad_request(format!("height {} out of range", height))
+                })?;
+                let header = block
+                    .header
+                    .ok_or_else(|| anyhow!("header of block {} not stored", height))?
+                    .to_string();
+                if cp_height == 0 {
+                    return Ok(Value::String(header));
+                }
+
+                let mut result = self.checkpoint_proof(height, cp_height).await?;
+                result["header"] = Value::String(header);
+                Ok(result)
+            }
+            "blockchain.block.headers" => {
+                let start = u64_param(0)?;
+                let count = u64_param(1)?.min(MAX_HEADERS);
+                let cp_height = params.get(2).and_then(Value::as_u64).unwrap_or_default();
+                let blocks = if count == 0 {
+                    Vec::new()
+                } else {
+                    self.db
+                        .get_blocks_range(start, start.saturating_add(count - 1))
+                        .await?
+                };
+
+                // Stop at the first gap so the headers stay contiguous
+                let mut hex = String::new();
+                let mut returned = 0;
+                for block in blocks {
+                    if block.height as u64 != start + returned {
+                        break;
+                    }
+                    let Some(header) = block.header else {
+                        break;
+                    };
+                    hex.push_str(&header.to_string());
+                    returned += 1;
+                }
+
+                let mut result = json!({ "count": returned, "hex": hex, "max": MAX_HEADERS });
+                if cp_height != 0 && returned > 0 {
+                    let proof = self
+                        .checkpoint_proof(start + returned - 1, cp_height)
+                        .await?;
+                    result["branch"] = proof["branch"].clone();
+                    result["root"] = proof["root"].clone();
+                }
+                Ok(result)
+            }
+            "blockchain.scripthash.get_balance" => {
+                let balance = self.db.get_scripthash_balance(hash_param(0)?).await?;
+                Ok(json!({ "confirmed": balance, "unconfirmed": 0 }))
+            }
+            "blockchain.scripthash.get_history" => {
+                let history = self.db.get_scripthash_history(hash_param(0)?).await?;
+                Ok(Value::Array(
+                    history
+                        .into_iter()
+                        .map(|tx| json!({ "tx_hash": tx.txid, "height": tx.block_height }))
+                        .collect(),
+                ))
+            }
+            "blockchain.scripthash.get_mempool" => Ok(json!([])),
+            "blockchain.scripthash.listunspent" => {
+                let utxos = self.db.get_scripthash_utxos(hash_param(0)?).await?;
+                Ok(Value::Array(
+                    utxos
+                        .into_iter()
+                        .map(|utxo| {
+                            json!({
+                                "tx_hash": utxo.txid,
+                                "tx_pos": utxo.vout,
+                                "height": utxo.block_height,
+                                "value": utxo.value,
+                            })
+                        })
+                        .collect(),
+                ))
+            }
+            "blockchain.scripthash.subscribe" => {
+                let scripthash = hash_param(0)?;
+                let status = self.scripthash_status(scripthash).await?;
+                session.scripthashes.insert(scripthash, status.clone());
+                Ok(status.map(Value::String).unwrap_or(Value::Null))
+            }
+            "blockchain.scripthash.unsubscribe" => Ok(json!(session
+                .scripthashes
+                .remove(&hash_param(0)?)
+                .is_some())),
+            "blockchain.transaction.get" => {
+                if params.get(1).and_then(Value::as_bool).unwrap_or_default() {
+                    return Err(RpcError::bad_request(
+                        "verbose transactions are not supported",
+                    ));
+                }
+                let tx = self.transaction(hash_param(0)?).await?;
+                Ok(json!(serialize_hex(&tx)))
+            }
+            "blockchain.transaction.get_merkle" => {
+                let txid = hash_param(0)?;
+                let proof = merkle::transaction_proof(self.db.as_ref(), txid)
+                    .await?
+                    .ok_or_else(|| {
+                        RpcError::bad_request(format!("unknown transaction {}", txid))
+                    })?;
+                Ok(json!({
+                    "block_height": proof.block_height,
+                    "merkle": proof.branch,
+                    "pos": proof.index,
+                }))
+            }
+            "blockchain.transaction.broadcast" => self.broadcast(str_param(0)?).await,
+            _ => Err(RpcError::bad_request(format!("unknown method {}", method))),
+        }
+    }
+
+    /// Merkle branch from block `height` to the root of the block hashes up
+    /// to `cp_height`, as `{"branch", "root"}` in display byte order.
+    async fn checkpoint_proof(&self, height: u64, cp_height: u64) -> Result<Value, RpcError> {
+        let tip = self.db.get_last_block_height().await?.unwrap_or_default();
+        if height > cp_height || cp_height > tip {
+            return Err(RpcError::bad_request(format!(
+                "header height {} must be <= cp_height {} which must be <= chain height {}",
+                height, cp_height, tip
+            )));
+        }
+        let cp_block = self
+            .db
+            .get_block_by_height(cp_height)
+            .await?
+            .ok_or_else(|| anyhow!("block {} not found", cp_height))?;
+
+        let mut checkpoint = self.checkpoint.lock().await;
+        // A reorg below the checkpoint changes its hash
+        let stale = !checkpoint
+            .as_ref()
+            .is_some_and(|cp| cp.height == cp_height && cp.hash == cp_block.hash);
+        if stale {
+            let hashes = self.db.get_block_hashes(0, cp_height).await?;
+            if hashes.len() as u64 != cp_height + 1 {
+                return Err(
+                    anyhow!("blocks below checkpoint {} are not all indexed", cp_height).into(),
+                );
+            }
+            *checkpoint = Some(Checkpoint {
+                height: cp_height,
+                hash: cp_block.hash,
+                tree: MerkleTree::new(hashes.into_iter().map(Hash256::to_byte_array).collect()),
+            });
+        }
+
+        let tree = &checkpoint
+            .as_ref()
+            .expect("checkpoint tree was just built")
+            .tree;
+        let display = |node: [u8; 32]| Hash256::from_byte_array(node).to_string();
+        let root = tree.root().map(display).unwrap_or_default();
+        Ok(json!({
+            "branch": tree.branch(height as usize).into_iter().map(display).collect::<Vec<_>>(),
+            "root": root,
+        }))
+    }
+
+    async fn tip_header(&self) -> Result<Value> {
+        let block = self
+            .db
+            .get_tip_block()
+            .await?
+            .ok_or_else(|| anyhow!("no blocks indexed"))?;
+        let header = block
+            .header
+            .ok_or_else(|| anyhow!("header of block {} not stored", block.height))?;
+        Ok(json!({ "hex": header, "height": block.height }))
+    }
+
+    /// Electrum status: sha256 of the concatenated `tx_hash:height:` history entries.
+    async fn scripthash_status(&self, scripthash: Hash256) -> Result<Option<String>> {
+        let history = self.db.get_scripthash_history(scripthash).await?;
+        if history.is_empty() {
+            return Ok(None);
+        }
+
+        let status: String = history
+            .iter()
+            .map(|tx| format!("{}:{}:", tx.txid, tx.block_height))
+            .collect();
+        Ok(Some(hex::encode(
+            sha256::Hash::hash(status.as_bytes()).to_byte_array(),
+        )))
+    }
+
+    async fn transaction(&self, txid: Hash256) -> Result<Transaction, RpcError> {
+        let detail = self
+            .db
+            .get_transaction_detail(txid)
+            .await?
+            .ok_or_else(|| RpcError::bad_request(format!("unknown transaction {}", txid)))?;
+        if !detail.is_complete() {
+            return Err(RpcError::bad_request(format!(
+                "transaction {} has been pruned",
+                txid
+            )));
+        }
+        Ok(rebuild_transaction(&detail)?)
+    }
+
+    /// Pushes header and scripthash notifications once the indexed tip moves.
+    async fn notifications(&self, session: &mut Session) -> Result<Vec<Value>> {
+        if !session.headers_subscribed && session.scripthashes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tip = self.db.get_last_block_height().await?.map(|h| h as i64);
+        if tip == session.last_tip {
+            return Ok(Vec::new());
+        }
+
+        let mut notifications = Vec::new();
+        if session.headers_subscribed {
+            notifications.push(json!({
+                "jsonrpc": "2.0",
+                "method": "blockchain.headers.subscribe",
+                "params": [self.tip_header().await?],
+            }));
+        }
+
+        let mut changed = Vec::new();
+        for (scripthash, status) in &session.scripthashes {
+            let current = self.scripthash_status(*scripthash).await?;
+            if current != *status {
+                changed.push((*scripthash, current));
+            }
+        }
+
+        // The session only moves on once every query succeeded
+        session.last_tip = tip;
+        for (scripthash, current) in changed {
+            notifications.push(json!({
+                "jsonrpc": "2.0",
+                "method": "blockchain.scripthash.subscribe",
+                "params": [scripthash, current],
+            }));
+            session.scripthashes.insert(scripthash, current);
+        }
+
+        Ok(notifications)
+    }
+
+    async fn estimate_fee(&self, target: u64) -> Result<Value, RpcError> {
+        let target = u16::try_from(target).map_err(|_| RpcError::bad_request("invalid target"))?;
+        let fee_rate = self
+            .node(move |client| Ok(client.estimate_smart_fee(target, None)?.fee_rate))
+            .await?;
+
+        // -1 tells the wallet the node has no estimate
+        Ok(fee_rate.map_or(json!(-1), |rate| json!(rate.to_btc())))
+    }
+
+    async fn broadcast(&self, raw_tx: &str) -> Result<Value, RpcError> {
+        let raw_tx = raw_tx.to_string();
+        let txid = self
+            .node(move |client| Ok(client.send_raw_transaction(raw_tx.as_str())?))
+            .await?;
+        Ok(json!(txid.to_string()))
+    }
+
+    /// Runs a blocking call against the Bitcoin node.
+    async fn node<T, F>(&self, call: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Client) -> Result<T> + Send + 'static,
+    {
+        let config = self.config.clone();
+        tokio::task::spawn_blocking(move || {
+            let auth = Auth::UserPass(
+                config.bitcoin_rpc_user.clone(),
+                config.bitcoin_rpc_pass.clone(),
+            );
+            let client = Client::new(&config.bitcoin_rpc_url, auth)?;
+            call(&client)
+        })
+        .await
+        .map_err(|e| {
+            error!("Electrum node call panicked: {}", e);
+            anyhow!("node call failed")
+        })?
+    }
+}
+
+/// Checks that the client's `protocol_version`, a version or a `[min, max]`
+/// range, admits the version this server speaks.
+fn negotiate_version(requested: Option<&Value>) -> Result<(), RpcError> {
+    let (min, max) = match requested {
+        None => (PROTOCOL_VERSION, PROTOCOL_VERSION),
+        Some(Value::String(version)) => (version.as_str(), version.as_str()),
+        Some(Value::Array(range)) => match range.as_slice() {
+            [Value::String(min), Value::String(max)] => (min.as_str(), max.as_str()),
+            _ => return Err(RpcError::bad_request("invalid protocol version range")),
+        },
+        Some(_) => return Err(RpcError::bad_request("invalid protocol version")),
+    };
+
+    let parse = |version: &str| {
+        version
+            .split('.')
+            .map(str::parse::<u32>)
+            .collect::<Result<Vec<_>, _>>()
+            .map(|mut parts| {
+                // "1.4.0" is "1.4"
+                while parts.last() == Some(&0) {
+                    parts.pop();
+                }
+                parts
+            })
+            .map_err(|_| RpcError::bad_request(format!("invalid protocol version {}", version)))
+    };
+    let ours = parse(PROTOCOL_VERSION)?;
+    if parse(min)? <= ours && ours <= parse(max)? {
+        Ok(())
+    } else {
+        Err(RpcError::bad_request(format!(
+            "unsupported protocol version {}-{}, this server speaks {}",
+            min, max, PROTOCOL_VERSION
+        )))
+    }
+}
+
+/// Per-connection subscription state
+#[derive(Default)]
+struct Session {
+    headers_subscribed: bool,
+    last_tip: Option<i64>,
+    /// Subscribed scripthashes with the last status sent
+    scripthashes: HashMap<Hash256, Option<String>>,
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::SqliteDatabase;
+    use bitcoin::{blockdata::constants::genesis_block, merkle_tree, Network, TxMerkleNode};
+
+    /// A server over blocks `0..blocks`, with their hashes
+    async fn server(blocks: u64) -> (ElectrumServer, Vec<Hash256>) {
+        let db = SqliteDatabase::new("sqlite::memory:", 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+        let mut hashes = Vec::new();
+        for height in 0..blocks {
+            let mut block = genesis_block(Network::Regtest);
+            block.header.nonce = height as u32;
+            db.insert_block(&block, height, &[0; 32], None)
+                .await
+                .unwrap();
+            hashes.push(Hash256::from(block.block_hash()));
+        }
+
+        let config = Config::for_tests(json!({ "electrum_bind": "127.0.0.1:0" }));
+        let server = ElectrumServer::new(Arc::new(db), Arc::new(config)).unwrap();
+        (server, hashes)
+    }
+
+    async fn call(server: &ElectrumServer, method: &str, params: Value) -> Value {
+        let request = json!({ "id": 1, "method": method, "params": params });
+        server
+            .handle_request(&mut Session::default(), &request)
+            .await
+    }
+
+    /// Folds `branch` from the hash of block `height` up to the root it commits to
+    fn fold(hashes: &[Hash256], height: u64, branch: &Value) -> String {
+        let mut node = hashes[height as usize].to_byte_array().to_vec();
+        for (depth, sibling) in branch.as_array().unwrap().iter().enumerate() {
+            let sibling = sibling.as_str().unwrap().parse::<Hash256>().unwrap();
+            let pair = if (height >> depth) & 1 == 0 {
+                [node.as_slice(), sibling.as_bytes()].concat()
+            } else {
+                [sibling.as_bytes(), node.as_slice()].concat()
+            };
+            node = bitcoin::hashes::sha256d::Hash::hash(&pair)
+                .to_byte_array()
+                .to_vec();
+        }
+        Hash256::try_from(node).unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn proves_headers_against_a_checkpoint() {
+        let (server, hashes) = server(7).await;
+        let leaves = hashes[..=5]
+            .iter()
+            .map(|hash| TxMerkleNode::from_byte_array(hash.to_byte_array()));
+        let root = Hash256::from(merkle_tree::calculate_root(leaves).unwrap()).to_string();
+
+        for height in 0..=5 {
+            let response = call(&server, "blockchain.block.header", json!([height, 5])).await;
+            let result = &response["result"];
+            assert_eq!(result["root"], root);
+            assert_eq!(fold(&hashes, height, &result["branch"]), root);
+            assert_eq!(result["header"].as_str().unwrap().len(), 160);
+        }
+
+        // Without a checkpoint the header comes alone
+        let response = call(&server, "blockchain.block.header", json!([2])).await;
+        assert_eq!(response["result"].as_str().unwrap().len(), 160);
+
+        // The checkpoint must lie between the header and the tip
+        let response = call(&server, "blockchain.block.header", json!([5, 4])).await;
+        assert_eq!(response["error"]["code"], BAD_REQUEST);
+        let response = call(&server, "blockchain.block.header", json!([2, 7])).await;
+        assert_eq!(response["error"]["code"], BAD_REQUEST);
+
+        let response = call(&server, "blockchain.block.headers", json!([2, 3, 5])).await;
+        let result = &response["result"];
+        assert_eq!(result["count"], 3);
+        assert_eq!(result["hex"].as_str().unwrap().len(), 3 * 160);
+        assert_eq!(result["max"], MAX_HEADERS);
+        assert_eq!(result["root"], root);
+        assert_eq!(fold(&hashes, 4, &result["branch"]), root);
+
+        // Past the tip fewer headers come back
+        let response = call(&server, "blockchain.block.headers", json!([5, 10])).await;
+        assert_eq!(response["result"]["count"], 2);
+        assert!(response["result"].get("root").is_none());
+    }
+
+    #[tokio::test]
+    async fn negotiates_the_protocol_version() {
+        let (server, _) = server(1).await;
+        for requested in [
+            json!(["wallet"]),
+            json!(["wallet", "1.4"]),
+            json!(["wallet", ["1.2", "1.4.2"]]),
+            json!(["wallet", ["1.4.0", "1.5"]]),
+        ] {
+            let response = call(&server, "server.version", requested).await;
+            assert_eq!(response["result"][1], PROTOCOL_VERSION);
+        }
+        for requested in [
+            json!(["wallet", "1.2"]),
+            json!(["wallet", ["1.5", "1.6"]]),
+            json!(["wallet", "one"]),
+        ] {
+            let response = call(&server, "server.version", requested).await;
+            assert_eq!(response["error"]["code"], BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn closes_connections_sending_oversized_requests() {
+        use tokio::io::{AsyncBufReadExt, BufReader};
+
+        let (server, _) = server(1).await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = async {
+            let stream = TcpStream::connect(address).await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+
+            let request = json!({ "id": 1, "method": "server.ping", "params": [] });
+            writer
+                .write_all(format!("{}\n", request).as_bytes())
+                .await
+                .unwrap();
+            let response: Value =
+                serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            assert_eq!(response["id"], 1);
+
+            let oversized = format!("[{}]\n", "0,".repeat(MAX_REQUEST_BYTES / 2));
+            writer.write_all(oversized.as_bytes()).await.unwrap();
+            let response: Value =
+                serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            assert_eq!(response["error"]["message"], "request too large");
+            assert!(lines.next_line().await.unwrap().is_none());
+        };
+        let connection = async {
+            let (stream, _) = listener.accept().await.unwrap();
+            server.handle_connection(stream).await
+        };
+
+        let ((), result) = tokio::join!(client, connection);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,403 @@
+//! Subset of the Blockstream Esplora HTTP API, with Esplora's JSON shapes, so
+//! wallets and tools built against it can use this indexer directly.
+
+use anyhow::Result as AnyResult;
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use bitcoin::{consensus::encode::serialize_hex, Address, Network, ScriptBuf};
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use serde_json::{json, Map, Value};
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+use tokio::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{error, warn};
+
+use crate::{
+    api::{validation, ApiState},
+    db::models::{Hash256, OutputModel, TransactionDetail},
+    utils::{script::esplora_script_type, transaction::rebuild_transaction},
+};
+
+/// Confirmed transactions per `/address/:address/txs` page, as in Esplora
+const ADDRESS_TXS_PAGE: i64 = 25;
+
+/// Most UTXOs returned for one address
+const MAX_ADDRESS_UTXOS: i64 = 5000;
+
+/// Confirmation targets Esplora reports in `/fee-estimates`
+const FEE_TARGETS: [u16; 28] = [
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 144,
+    504, 1008,
+];
+
+/// How long `/fee-estimates` is served from cache before asking the node again
+const FEE_ESTIMATES_TTL: Duration = Duration::from_secs(30);
+
+/// The last `/fee-estimates` answer and when it was fetched
+#[derive(Default)]
+struct FeeEstimates(Mutex<Option<(Instant, Map<String, Value>)>>);
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/tx/:txid", get(get_tx))
+        .route("/tx/:txid/status", get(get_tx_status))
+        .route("/tx/:txid/hex", get(get_tx_hex))
+        .route("/address/:address/utxo", get(get_address_utxo))
+        .route("/address/:address/txs", get(get_address_txs))
+        .route(
+            "/address/:address/txs/chain/:last_seen_txid",
+            get(get_address_txs_chain),
+        )
+        .route("/block/:hash", get(get_block))
+        .route("/blocks/tip/height", get(get_tip_height))
+        .route("/blocks/tip/hash", get(get_tip_hash))
+        .route("/fee-estimates", get(get_fee_estimates))
+        .layer(Extension(Arc::new(FeeEstimates::default())))
+}
+
+fn internal_error(e: impl std::fmt::Display) -> StatusCode {
+    error!("Database error: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Addresses in the path, which must be for the configured network
+fn parse_address(state: &ApiState, value: &str) -> Result<(), StatusCode> {
+    let network = state.network().map_err(internal_error)?;
+    validation::address(value, network).map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+/// Txids and block hashes in the path, hex in display order
+fn parse_hash(value: &str) -> Result<Hash256, StatusCode> {
+    Hash256::from_str(value).map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+fn script_json(script: &ScriptBuf, value: u64, network: Network) -> Value {
+    let mut object = Map::new();
+    object.insert("scriptpubkey".into(), json!(script.to_hex_string()));
+    object.insert("scriptpubkey_asm".into(), json!(script.to_asm_string()));
+    object.insert(
+        "scriptpubkey_type".into(),
+        json!(esplora_script_type(script)),
+    );
+    if let Ok(address) = Address::from_script(script, network) {
+        object.insert("scriptpubkey_address".into(), json!(address.to_string()));
+    }
+    object.insert("value".into(), json!(value));
+    Value::Object(object)
+}
+
+fn status_json(detail: &TransactionDetail) -> Value {
+    json!({
+        "confirmed": true,
+        "block_height": detail.transaction.block_height,
+        "block_hash": detail.transaction.block_hash,
+        "block_time": detail.transaction.timestamp.timestamp(),
+    })
+}
+
+/// Loads a transaction that can still be rebuilt; pruned ones are gone rather
+/// than unknown.
+async fn load_detail(state: &ApiState, txid: Hash256) -> Result<TransactionDetail, StatusCode> {
+    match state.db.get_transaction_detail(txid).await {
+        Ok(Some(detail)) if !detail.is_complete() => Err(StatusCode::GONE),
+        Ok(Some(detail)) => Ok(detail),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+/// Renders a transaction in Esplora's `/tx/:txid` shape.
+async fn tx_json(state: &ApiState, detail: &TransactionDetail) -> AnyResult<Value> {
+    let txid = detail.transaction.txid;
+    let tx = rebuild_transaction(detail)?;
+    let network = state.network()?;
+
+    let prevouts: HashMap<(Hash256, i32), OutputModel> = state
+        .db
+        .get_transaction_prevouts(txid)
+        .await?
+        .into_iter()
+        .map(|output| ((output.txid, output.vout), output))
+        .collect();
+
+    let vin: Vec<Value> = tx
+        .input
+        .iter()
+        .map(|input| {
+            let is_coinbase = input.previous_output.is_null();
+            let prevout = prevouts
+                .get(&(
+                    input.previous_output.txid.into(),
+                    input.previous_output.vout as i32,
+                ))
+                .map(|output| {
+                    let script = ScriptBuf::from(&output.script_pubkey);
+                    script_json(&script, output.value as u64, network)
+                });
+
+            let mut object = Map::new();
+            object.insert("txid".into(), json!(input.previous_output.txid.to_string()));
+            object.insert("vout".into(), json!(input.previous_output.vout));
+            object.insert("prevout".into(), prevout.unwrap_or(Value::Null));
+            object.insert("scriptsig".into(), json!(input.script_sig.to_hex_string()));
+            object.insert(
+                "scriptsig_asm".into(),
+                json!(input.script_sig.to_asm_string()),
+            );
+            if !input.witness.is_empty() {
+                let witness: Vec<String> = input.witness.iter().map(hex::encode).collect();
+                object.insert("witness".into(), json!(witness));
+            }
+            object.insert("is_coinbase".into(), json!(is_coinbase));
+            object.insert("sequence".into(), json!(input.sequence.0));
+            Value::Object(object)
+        })
+        .collect();
+
+    let vout: Vec<Value> = tx
+        .output
+        .iter()
+        .map(|output| script_json(&output.script_pubkey, output.value.to_sat(), network))
+        .collect();
+
+    // Esplora gives coinbases a zero fee. Other fees are only unknown when a
+    // prevout isn't indexed, and are then null rather than a made-up 0
+    let fee = match tx.is_coinbase() {
+        true => Some(0),
+        false => detail.transaction.fee,
+    };
+
+    Ok(json!({
+        "txid": detail.transaction.txid,
+        "version": tx.version.0,
+        "locktime": tx.lock_time.to_consensus_u32(),
+        "vin": vin,
+        "vout": vout,
+        "size": tx.total_size(),
+        "weight": tx.weight().to_wu(),
+        "fee": fee,
+        "status": status_json(detail),
+    }))
+}
+
+async fn get_tx(
+    Path(txid): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<Value>, StatusCode> {
+    let detail = load_detail(&state, parse_hash(&txid)?).await?;
+    tx_json(&state, &detail)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+async fn get_tx_status(
+    Path(txid): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<Value>, StatusCode> {
+    match state.db.get_transaction_detail(parse_hash(&txid)?).await {
+        Ok(Some(detail)) => Ok(Json(status_json(&detail))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+async fn get_tx_hex(
+    Path(txid): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<String, StatusCode> {
+    let detail = load_detail(&state, parse_hash(&txid)?).await?;
+    rebuild_transaction(&detail)
+        .map(|tx| serialize_hex(&tx))
+        .map_err(internal_error)
+}
+
+async fn get_address_utxo(
+    Path(address): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<Value>, StatusCode> {
+    parse_address(&state, &address)?;
+
+    let utxos = state
+        .db
+        .get_address_utxos(&address, None, MAX_ADDRESS_UTXOS)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(Value::Array(
+        utxos
+            .into_iter()
+            .map(|utxo| {
+                json!({
+                    "txid": utxo.txid,
+                    "vout": utxo.vout,
+                    "status": {
+                        "confirmed": true,
+                        "block_height": utxo.block_height,
+                        "block_hash": utxo.block_hash,
+                        "block_time": utxo.block_time.timestamp(),
+                    },
+                    "value": utxo.value,
+                })
+            })
+            .collect(),
+    )))
+}
+
+async fn address_txs(
+    state: &ApiState,
+    address: &str,
+    after: Option<(i64, i32)>,
+) -> Result<Json<Value>, StatusCode> {
+    let history = state
+        .db
+        .get_address_transactions(address, after, ADDRESS_TXS_PAGE)
+        .await
+        .map_err(internal_error)?;
+
+    let mut txs = Vec::with_capacity(history.len());
+    for entry in history {
+        // Pruned transactions can't be shown in full, so they are left out
+        match load_detail(state, entry.txid).await {
+            Ok(detail) => txs.push(tx_json(state, &detail).await.map_err(internal_error)?),
+            Err(StatusCode::NOT_FOUND | StatusCode::GONE) => {}
+            Err(status) => return Err(status),
+        }
+    }
+    Ok(Json(Value::Array(txs)))
+}
+
+async fn get_address_txs(
+    Path(address): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<Value>, StatusCode> {
+    parse_address(&state, &address)?;
+    // Only confirmed transactions are indexed, so there is no mempool section
+    address_txs(&state, &address, None).await
+}
+
+async fn get_address_txs_chain(
+    Path((address, last_seen_txid)): Path<(String, String)>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<Value>, StatusCode> {
+    parse_address(&state, &address)?;
+
+    let last_seen = state
+        .db
+        .get_transaction(parse_hash(&last_seen_txid)?)
+        .await
+        .map_err(internal_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let after = (
+        last_seen.block_height,
+        last_seen.tx_index.unwrap_or_default(),
+    );
+
+    address_txs(&state, &address, Some(after)).await
+}
+
+async fn get_block(
+    Path(hash): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<Value>, StatusCode> {
+    let block = state
+        .db
+        .get_block_by_hash(parse_hash(&hash)?)
+        .await
+        .map_err(internal_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({
+        "id": block.hash,
+        "height": block.height,
+        "version": block.version,
+        "timestamp": block.timestamp.timestamp(),
+        "tx_count": block.tx_count,
+        "size": block.size,
+        "weight": block.weight,
+        "merkle_root": block.merkle_root,
+        "previousblockhash": block.prev_hash,
+        "mediantime": block.median_time.map(|t| t.timestamp()),
+        "nonce": block.nonce,
+        "bits": block.bits,
+        "difficulty": block.difficulty,
+    })))
+}
+
+async fn get_tip_height(Extension(state): Extension<ApiState>) -> Result<String, StatusCode> {
+    match state.db.get_tip_block().await {
+        Ok(Some(block)) => Ok(block.height.to_string()),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+async fn get_tip_hash(Extension(state): Extension<ApiState>) -> Result<String, StatusCode> {
+    match state.db.get_tip_block().await {
+        Ok(Some(block)) => Ok(block.hash.to_string()),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+/// Fee rates in sat/vB by confirmation target, from the node's `estimatesmartfee`,
+/// cached for `FEE_ESTIMATES_TTL`. Targets the node fails to estimate are left out.
+async fn get_fee_estimates(
+    Extension(state): Extension<ApiState>,
+    Extension(cache): Extension<Arc<FeeEstimates>>,
+) -> Result<Json<Value>, StatusCode> {
+    // Held while fetching, so concurrent requests share one round of calls
+    let mut cached = cache.0.lock().await;
+    if let Some((fetched_at, estimates)) = cached.as_ref() {
+        if fetched_at.elapsed() < FEE_ESTIMATES_TTL {
+            return Ok(Json(Value::Object(estimates.clone())));
+        }
+    }
+
+    let config = state.config.clone();
+    let estimates = tokio::task::spawn_blocking(move || -> AnyResult<Map<String, Value>> {
+        let auth = Auth::UserPass(
+            config.bitcoin_rpc_user.clone(),
+            config.bitcoin_rpc_pass.clone(),
+        );
+        let client = Client::new(&config.bitcoin_rpc_url, auth)?;
+
+        let mut estimates = Map::new();
+        let mut last_error = None;
+        for target in FEE_TARGETS {
+            match client.estimate_smart_fee(target, None) {
+                // Targets without enough data are left out, as Esplora does
+                Ok(estimate) => {
+                    if let Some(fee_rate) = estimate.fee_rate {
+                        let sat_per_vb = fee_rate.to_sat() as f64 / 1000.0;
+                        estimates.insert(target.to_string(), json!(sat_per_vb));
+                    }
+                }
+                Err(e) => {
+                    warn!("Fee estimation for target {} failed: {}", target, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) if estimates.is_empty() => Err(e.into()),
+            _ => Ok(estimates),
+        }
+    })
+    .await
+    .map_err(internal_error)?
+    .map_err(|e| {
+        error!("Fee estimation error: {}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    *cached = Some((Instant::now(), estimates.clone()));
+    Ok(Json(Value::Object(estimates)))
+}
@@ -1,5 +1,8 @@
 use async_graphql::{
-    http::GraphiQLSource, Context, EmptyMutation, EmptySubscription, Object, Schema, SimpleObject,
+    connection::{Connection, Edge},
+    http::GraphiQLSource,
+    Context, EmptyMutation, EmptySubscription, ErrorExtensions, Object, OutputType, Schema,
+    SimpleObject,
 };
 use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
 use axum::{
@@ -7,9 +10,23 @@ use axum::{
     response::{Html, IntoResponse},
 };
 
+use serde::{de::DeserializeOwned, Serialize};
+
 use crate::{
-    api::ApiState,
-    db::models::{BlockModel, RunesTransactionModel, TransactionModel},
+    api::{
+        analytics::{self, Interval, Metric, Point},
+        pagination::{decode_cursor, page_size, Cursor, Page},
+        request_id::RequestId,
+        validation, ApiState,
+    },
+    db::models::{
+        BlockModel, Brc20BalanceModel, Brc20TokenModel, BridgeEventModel, ChainStatsModel,
+        InputModel, OutputDetail, RunesTransactionModel, TransactionDetail,
+    },
+    utils::{
+        error::IndexerError,
+        merkle::{self, CairoMerkleProof, MerkleProof},
+    },
 };
 
 pub type AppSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
@@ -20,46 +37,248 @@ pub struct QueryRoot;
 impl QueryRoot {
     async fn block(&self, ctx: &Context<'_>, height: i64) -> async_graphql::Result<Option<Block>> {
         let state = ctx.data::<ApiState>()?;
-        let block = state.db.get_block_by_height(height as u64).await?;
+        let height = validation::signed_height("height", height).map_err(api_error)?;
+        let block = state
+            .db
+            .get_block_by_height(height)
+            .await
+            .map_err(api_error)?;
+        Ok(block.map(Block::from))
+    }
+
+    async fn block_by_hash(
+        &self,
+        ctx: &Context<'_>,
+        hash: String,
+    ) -> async_graphql::Result<Option<Block>> {
+        let state = ctx.data::<ApiState>()?;
+        let hash = validation::hash("hash", &hash).map_err(api_error)?;
+        let block = state.db.get_block_by_hash(hash).await.map_err(api_error)?;
+        Ok(block.map(Block::from))
+    }
+
+    /// Highest indexed block
+    async fn tip(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Block>> {
+        let state = ctx.data::<ApiState>()?;
+        let block = state.db.get_tip_block().await.map_err(api_error)?;
         Ok(block.map(Block::from))
     }
 
+    /// Blocks within optional height bounds, newest first unless `ascending`
+    async fn blocks(
+        &self,
+        ctx: &Context<'_>,
+        from: Option<i64>,
+        to: Option<i64>,
+        ascending: Option<bool>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<Connection<Cursor<u64>, Block>> {
+        let state = ctx.data::<ApiState>()?;
+        let from = from
+            .map(|h| validation::signed_height("from", h))
+            .transpose()
+            .map_err(api_error)?;
+        let to = to
+            .map(|h| validation::signed_height("to", h))
+            .transpose()
+            .map_err(api_error)?;
+        validation::height_range(from, to).map_err(api_error)?;
+        let limit = page_size(first.map(i64::from));
+        let after = decode_cursor(after.as_deref()).map_err(api_error)?;
+
+        let blocks = state
+            .db
+            .get_blocks(from, to, after, !ascending.unwrap_or(false), limit + 1)
+            .await
+            .map_err(api_error)?;
+        Ok(connection(
+            Page::new(blocks, limit),
+            after.is_some(),
+            |block| block.height as u64,
+        ))
+    }
+
+    /// Txids of a block in block order
+    async fn block_txids(
+        &self,
+        ctx: &Context<'_>,
+        height: i64,
+    ) -> async_graphql::Result<Vec<String>> {
+        let state = ctx.data::<ApiState>()?;
+        let height = validation::signed_height("height", height).map_err(api_error)?;
+        let txids = state.db.get_block_txids(height).await.map_err(api_error)?;
+        Ok(txids.iter().map(ToString::to_string).collect())
+    }
+
     async fn transaction(
         &self,
         ctx: &Context<'_>,
         txid: String,
     ) -> async_graphql::Result<Option<Transaction>> {
         let state = ctx.data::<ApiState>()?;
-        let tx = state.db.get_transaction(&txid).await?;
+        let txid = validation::txid(&txid).map_err(api_error)?;
+        let tx = state
+            .db
+            .get_transaction_detail(txid)
+            .await
+            .map_err(api_error)?;
+        if tx.as_ref().is_some_and(|tx| tx.transaction.pruned) {
+            return Err(api_error(IndexerError::Pruned(format!(
+                "Transaction {}",
+                txid
+            ))));
+        }
         Ok(tx.map(Transaction::from))
     }
 
+    /// SPV inclusion proof for an indexed transaction
+    async fn transaction_proof(
+        &self,
+        ctx: &Context<'_>,
+        txid: String,
+    ) -> async_graphql::Result<Option<TransactionProof>> {
+        let state = ctx.data::<ApiState>()?;
+        let txid = validation::txid(&txid).map_err(api_error)?;
+        let proof = merkle::transaction_proof(state.db.as_ref(), txid)
+            .await
+            .map_err(api_error)?;
+        Ok(proof.map(TransactionProof::from))
+    }
+
+    /// Runes operations, newest first
     async fn runes_transactions(
         &self,
         ctx: &Context<'_>,
-        limit: Option<i32>,
-        offset: Option<i32>,
-    ) -> async_graphql::Result<Vec<RunesTransaction>> {
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<Connection<Cursor<(i64, i32)>, RunesTransaction>> {
         let state = ctx.data::<ApiState>()?;
-        let limit = limit.unwrap_or(50) as i64;
-        let offset = offset.unwrap_or(0) as i64;
+        let limit = page_size(first.map(i64::from));
+        let after = decode_cursor(after.as_deref()).map_err(api_error)?;
 
-        let txs = state.db.get_runes_transactions(limit, offset).await?;
-        Ok(txs.into_iter().map(RunesTransaction::from).collect())
+        let txs = state
+            .db
+            .get_runes_transactions(after, limit + 1)
+            .await
+            .map_err(api_error)?;
+        Ok(connection(Page::new(txs, limit), after.is_some(), |tx| {
+            (tx.block_height, tx.id)
+        }))
+    }
+
+    async fn brc20_token(
+        &self,
+        ctx: &Context<'_>,
+        tick: String,
+    ) -> async_graphql::Result<Option<Brc20Token>> {
+        let state = ctx.data::<ApiState>()?;
+        let token = state
+            .db
+            .get_brc20_token(&tick.to_lowercase())
+            .await
+            .map_err(api_error)?;
+        Ok(token.map(Brc20Token::from))
+    }
+
+    /// BRC-20 tokens in deploy order
+    async fn brc20_tokens(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<Connection<Cursor<(i64, String)>, Brc20Token>> {
+        let state = ctx.data::<ApiState>()?;
+        let limit = page_size(first.map(i64::from));
+        let after = decode_cursor(after.as_deref()).map_err(api_error)?;
+        let has_previous = after.is_some();
+
+        let tokens = state
+            .db
+            .get_brc20_tokens(after, limit + 1)
+            .await
+            .map_err(api_error)?;
+        Ok(connection(
+            Page::new(tokens, limit),
+            has_previous,
+            |token| (token.block_height, token.tick.clone()),
+        ))
+    }
+
+    async fn brc20_balances(
+        &self,
+        ctx: &Context<'_>,
+        address: String,
+    ) -> async_graphql::Result<Vec<Brc20Balance>> {
+        let state = ctx.data::<ApiState>()?;
+        let network = state.network().map_err(api_error)?;
+        validation::address(&address, network).map_err(api_error)?;
+        let balances = state
+            .db
+            .get_brc20_balances(&address)
+            .await
+            .map_err(api_error)?;
+        Ok(balances.into_iter().map(Brc20Balance::from).collect())
     }
 
     async fn stats(&self, ctx: &Context<'_>) -> async_graphql::Result<Stats> {
         let state = ctx.data::<ApiState>()?;
-        let last_block = state.db.get_last_block_height().await?;
+        let stats = state.db.get_chain_stats().await.map_err(api_error)?;
+        Ok(Stats::from(stats))
+    }
+
+    /// Chain activity per hour or day, oldest first. `from` and `to` take unix
+    /// seconds or RFC 3339 and default to the week up to now.
+    async fn timeseries(
+        &self,
+        ctx: &Context<'_>,
+        metric: Metric,
+        #[graphql(default_with = "Interval::Hour")] interval: Interval,
+        from: Option<String>,
+        to: Option<String>,
+    ) -> async_graphql::Result<Vec<TimeseriesPoint>> {
+        let state = ctx.data::<ApiState>()?;
+        let from = from
+            .map(|from| validation::timestamp("from", &from))
+            .transpose()
+            .map_err(api_error)?;
+        let to = to
+            .map(|to| validation::timestamp("to", &to))
+            .transpose()
+            .map_err(api_error)?;
 
-        Ok(Stats {
-            last_indexed_block: last_block.map(|h| h as i64),
-            total_transactions: 0,
-            total_runes_transactions: 0,
-        })
+        let points = analytics::timeseries(state.db.as_ref(), metric, interval, from, to)
+            .await
+            .map_err(api_error)?;
+        Ok(points.into_iter().map(TimeseriesPoint::from).collect())
     }
 }
 
+/// Reports a failure with the same `code` (and `details`) as the REST API, in
+/// the error's extensions.
+fn api_error(err: impl Into<IndexerError>) -> async_graphql::Error {
+    err.into().extend()
+}
+
+/// Wraps a keyset page as a Relay connection, each edge keyed by its cursor.
+fn connection<T, K, N>(
+    page: Page<T>,
+    has_previous: bool,
+    key: impl Fn(&T) -> K,
+) -> Connection<Cursor<K>, N>
+where
+    K: Serialize + DeserializeOwned + Send + Sync,
+    N: From<T> + OutputType,
+{
+    let mut connection = Connection::new(has_previous, page.has_next);
+    connection.edges.extend(
+        page.items
+            .into_iter()
+            .map(|item| Edge::new(Cursor(key(&item)), N::from(item))),
+    );
+    connection
+}
+
 #[derive(SimpleObject)]
 struct Block {
     height: i64,
@@ -67,16 +286,84 @@ struct Block {
     prev_hash: String,
     timestamp: String,
     merkle_root: String,
+    header: Option<String>,
+    version: Option<i32>,
+    bits: Option<i64>,
+    nonce: Option<i64>,
+    difficulty: Option<f64>,
+    chainwork: Option<String>,
+    median_time: Option<String>,
+    tx_count: Option<i32>,
+    size: Option<i32>,
+    stripped_size: Option<i32>,
+    weight: Option<i32>,
 }
 
 impl From<BlockModel> for Block {
     fn from(model: BlockModel) -> Self {
         Self {
             height: model.height,
-            hash: model.hash,
-            prev_hash: model.prev_hash,
+            hash: model.hash.to_string(),
+            prev_hash: model.prev_hash.to_string(),
             timestamp: model.timestamp.to_rfc3339(),
-            merkle_root: model.merkle_root,
+            merkle_root: model.merkle_root.to_string(),
+            header: model.header.map(|header| header.to_string()),
+            version: model.version,
+            bits: model.bits,
+            nonce: model.nonce,
+            difficulty: model.difficulty,
+            chainwork: model.chainwork,
+            median_time: model.median_time.map(|t| t.to_rfc3339()),
+            tx_count: model.tx_count,
+            size: model.size,
+            stripped_size: model.stripped_size,
+            weight: model.weight,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct TransactionProof {
+    txid: String,
+    block_height: i64,
+    block_hash: String,
+    header: String,
+    index: u32,
+    branch: Vec<String>,
+    cairo: CairoProof,
+}
+
+#[derive(SimpleObject)]
+struct CairoProof {
+    header: Vec<u32>,
+    txid: Vec<u32>,
+    branch: Vec<Vec<u32>>,
+    index: u32,
+    calldata: Vec<String>,
+}
+
+impl From<MerkleProof> for TransactionProof {
+    fn from(proof: MerkleProof) -> Self {
+        Self {
+            txid: proof.txid,
+            block_height: proof.block_height,
+            block_hash: proof.block_hash,
+            header: proof.header,
+            index: proof.index,
+            branch: proof.branch,
+            cairo: CairoProof::from(proof.cairo),
+        }
+    }
+}
+
+impl From<CairoMerkleProof> for CairoProof {
+    fn from(proof: CairoMerkleProof) -> Self {
+        Self {
+            header: proof.header,
+            txid: proof.txid,
+            branch: proof.branch,
+            index: proof.index,
+            calldata: proof.calldata,
         }
     }
 }
@@ -86,25 +373,143 @@ struct Transaction {
     txid: String,
     block_height: i64,
     block_hash: String,
+    tx_index: Option<i32>,
     version: i32,
     locktime: i64,
     size: i32,
     weight: i32,
+    vsize: i64,
     fee: Option<i64>,
+    feerate: Option<f64>,
+    confirmations: i64,
     timestamp: String,
+    inputs: Vec<Input>,
+    outputs: Vec<Output>,
+    /// Vouts of spent outputs dropped by a retention policy
+    pruned_outputs: Vec<i32>,
+    runes: Vec<RunesTransaction>,
+    bridge_events: Vec<BridgeEvent>,
 }
 
-impl From<TransactionModel> for Transaction {
-    fn from(model: TransactionModel) -> Self {
+impl From<TransactionDetail> for Transaction {
+    fn from(detail: TransactionDetail) -> Self {
+        let model = detail.transaction;
         Self {
-            txid: model.txid,
+            txid: model.txid.to_string(),
             block_height: model.block_height,
-            block_hash: model.block_hash,
+            block_hash: model.block_hash.to_string(),
+            tx_index: model.tx_index,
             version: model.version,
             locktime: model.locktime,
             size: model.size,
             weight: model.weight,
+            vsize: detail.vsize,
             fee: model.fee,
+            feerate: detail.feerate,
+            confirmations: detail.confirmations,
+            timestamp: model.timestamp.to_rfc3339(),
+            inputs: detail.inputs.into_iter().map(Input::from).collect(),
+            outputs: detail.outputs.into_iter().map(Output::from).collect(),
+            pruned_outputs: detail.pruned_outputs,
+            runes: detail
+                .runes
+                .into_iter()
+                .map(RunesTransaction::from)
+                .collect(),
+            bridge_events: detail
+                .bridge_events
+                .into_iter()
+                .map(BridgeEvent::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct Input {
+    vin: i32,
+    prev_txid: Option<String>,
+    prev_vout: Option<i32>,
+    script_sig: String,
+    witness: Vec<String>,
+    sequence: i64,
+    value: Option<i64>,
+    address: Option<String>,
+}
+
+impl From<InputModel> for Input {
+    fn from(model: InputModel) -> Self {
+        Self {
+            vin: model.vin,
+            prev_txid: model.prev_txid.map(|txid| txid.to_string()),
+            prev_vout: model.prev_vout,
+            script_sig: model.script_sig.to_string(),
+            witness: model.witness.iter().map(ToString::to_string).collect(),
+            sequence: model.sequence,
+            value: model.value,
+            address: model.address,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct Output {
+    vout: i32,
+    value: i64,
+    script_pubkey: String,
+    script_type: String,
+    address: Option<String>,
+    spent: bool,
+    spending_txid: Option<String>,
+    spending_vin: Option<i32>,
+}
+
+impl From<OutputDetail> for Output {
+    fn from(detail: OutputDetail) -> Self {
+        let model = detail.output;
+        Self {
+            vout: model.vout,
+            value: model.value,
+            script_pubkey: model.script_pubkey.to_string(),
+            script_type: detail.script_type,
+            address: model.address,
+            spent: model.spent,
+            spending_txid: model.spending_txid.map(|txid| txid.to_string()),
+            spending_vin: model.spending_vin,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct BridgeEvent {
+    id: i32,
+    event_type: String,
+    txid: Option<String>,
+    vout: Option<i32>,
+    block_height: Option<i64>,
+    starknet_tx_hash: Option<String>,
+    bitcoin_address: Option<String>,
+    starknet_address: Option<String>,
+    amount: Option<i64>,
+    asset: Option<String>,
+    status: Option<String>,
+    timestamp: String,
+}
+
+impl From<BridgeEventModel> for BridgeEvent {
+    fn from(model: BridgeEventModel) -> Self {
+        Self {
+            id: model.id,
+            event_type: model.event_type,
+            txid: model.txid.map(|txid| txid.to_string()),
+            vout: model.vout,
+            block_height: model.block_height,
+            starknet_tx_hash: model.starknet_tx_hash,
+            bitcoin_address: model.bitcoin_address,
+            starknet_address: model.starknet_address,
+            amount: model.amount,
+            asset: model.asset,
+            status: model.status,
             timestamp: model.timestamp.to_rfc3339(),
         }
     }
@@ -128,11 +533,11 @@ impl From<RunesTransactionModel> for RunesTransaction {
     fn from(model: RunesTransactionModel) -> Self {
         Self {
             id: model.id,
-            txid: model.txid,
+            txid: model.txid.to_string(),
             block_height: model.block_height,
             rune_id: model.rune_id,
             operation: model.operation,
-            amount: model.amount.map(|a| a.to_string()),
+            amount: model.amount,
             from_address: model.from_address,
             to_address: model.to_address,
             metadata: model.metadata,
@@ -141,22 +546,131 @@ impl From<RunesTransactionModel> for RunesTransaction {
     }
 }
 
+#[derive(SimpleObject)]
+struct Brc20Token {
+    tick: String,
+    inscription_id: String,
+    max_supply: String,
+    mint_limit: String,
+    decimals: i32,
+    minted: String,
+    deployer: Option<String>,
+    block_height: i64,
+    timestamp: String,
+}
+
+impl From<Brc20TokenModel> for Brc20Token {
+    fn from(model: Brc20TokenModel) -> Self {
+        Self {
+            tick: model.tick,
+            inscription_id: model.inscription_id,
+            max_supply: model.max_supply,
+            mint_limit: model.mint_limit,
+            decimals: model.decimals,
+            minted: model.minted,
+            deployer: model.deployer,
+            block_height: model.block_height,
+            timestamp: model.timestamp.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct Brc20Balance {
+    address: String,
+    tick: String,
+    available: String,
+    transferable: String,
+}
+
+impl From<Brc20BalanceModel> for Brc20Balance {
+    fn from(model: Brc20BalanceModel) -> Self {
+        Self {
+            address: model.address,
+            tick: model.tick,
+            available: model.available,
+            transferable: model.transferable,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct TimeseriesPoint {
+    timestamp: String,
+    blocks: i64,
+    value: Option<i64>,
+}
+
+impl From<Point> for TimeseriesPoint {
+    fn from(point: Point) -> Self {
+        Self {
+            timestamp: point.timestamp.to_rfc3339(),
+            blocks: point.blocks,
+            value: point.value,
+        }
+    }
+}
+
 #[derive(SimpleObject)]
 struct Stats {
     last_indexed_block: Option<i64>,
+    node_height: Option<i64>,
+    lag: Option<i64>,
+    sync_eta_secs: Option<i64>,
+    total_blocks: i64,
     total_transactions: i64,
+    total_outputs: i64,
+    utxo_count: i64,
+    utxo_value: i64,
     total_runes_transactions: i64,
+    runes_etched: i64,
+    runes_minted: i64,
+    runes_burned: i64,
+    bridge_deposit_volume: i64,
+    bridge_withdrawal_volume: i64,
+}
+
+impl From<ChainStatsModel> for Stats {
+    fn from(model: ChainStatsModel) -> Self {
+        Self {
+            last_indexed_block: model.tip_height,
+            node_height: model.node_height,
+            lag: model.lag(),
+            sync_eta_secs: model.sync_eta_secs(),
+            total_blocks: model.blocks,
+            total_transactions: model.transactions,
+            total_outputs: model.outputs,
+            utxo_count: model.utxo_count,
+            utxo_value: model.utxo_value,
+            total_runes_transactions: model.runes_transactions,
+            runes_etched: model.runes_etched,
+            runes_minted: model.runes_minted,
+            runes_burned: model.runes_burned,
+            bridge_deposit_volume: model.bridge_deposit_volume,
+            bridge_withdrawal_volume: model.bridge_withdrawal_volume,
+        }
+    }
 }
 
 pub async fn graphql_handler(
     Extension(state): Extension<ApiState>,
+    Extension(request_id): Extension<RequestId>,
     req: GraphQLRequest,
 ) -> GraphQLResponse {
     let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
         .data(state)
         .finish();
 
-    schema.execute(req.into_inner()).await.into()
+    let mut response = schema.execute(req.into_inner()).await;
+    for error in &mut response.errors {
+        let extensions = error.extensions.get_or_insert_with(Default::default);
+        // Errors raised by the engine itself are problems with the query
+        if extensions.get("code").is_none() {
+            extensions.set("code", "bad_request");
+        }
+        extensions.set("request_id", request_id.0.as_str());
+    }
+    response.into()
 }
 
 pub async fn graphql_playground() -> impl IntoResponse {
@@ -1,16 +1,47 @@
+pub mod analytics;
+pub mod electrum;
+pub mod esplora;
 pub mod graphql;
+pub mod pagination;
+pub mod request_id;
 pub mod rest;
+pub mod validation;
 
-use axum::{routing::get, Extension, Router};
+use axum::{middleware, routing::get, Extension, Router};
+use bitcoin::Network;
 use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
 
-use crate::{config::Config, db::Database};
+use crate::{
+    config::Config,
+    db::{models::PoolStats, ContentStore, Storage},
+    utils::error::IndexerError,
+};
 
 #[derive(Clone)]
 pub struct ApiState {
-    pub db: Arc<Database>,
+    /// Handle for queries, on the read pool
+    pub db: Arc<dyn Storage>,
+    /// The indexer's handle, only used to report its pool in `/health`
+    pub writer: Arc<dyn Storage>,
     pub config: Arc<Config>,
+    pub content_store: Option<ContentStore>,
+}
+
+impl ApiState {
+    /// Usage of every pool behind the API and the indexer
+    pub fn pool_stats(&self) -> Vec<PoolStats> {
+        let mut stats = self.db.pool_stats();
+        if !Arc::ptr_eq(&self.db, &self.writer) {
+            stats.extend(self.writer.pool_stats());
+        }
+        stats
+    }
+
+    /// Network addresses must belong to
+    pub fn network(&self) -> Result<Network, IndexerError> {
+        Ok(self.config.network()?)
+    }
 }
 
 pub fn create_api_router(state: ApiState) -> Router {
@@ -19,18 +50,70 @@ pub fn create_api_router(state: ApiState) -> Router {
         .allow_methods(Any)
         .allow_headers(Any);
 
-    Router::new()
+    let router = Router::new()
         // REST endpoints
         .route("/health", get(rest::health))
+        .route("/blocks", get(rest::get_blocks))
+        .route("/blocks/tip", get(rest::get_tip))
+        .route("/blocks/hash/:hash", get(rest::get_block_by_hash))
         .route("/blocks/:height", get(rest::get_block))
+        .route(
+            "/blocks/:height/transactions",
+            get(rest::get_block_transactions),
+        )
+        .route("/blocks/:height/header", get(rest::get_block_header))
+        .route("/headers", get(rest::get_headers))
         .route("/transactions/:txid", get(rest::get_transaction))
+        .route(
+            "/transactions/:txid/proof",
+            get(rest::get_transaction_proof),
+        )
         .route("/runes/transactions", get(rest::get_runes_transactions))
+        .route("/inscriptions", get(rest::get_inscriptions))
+        .route("/inscriptions/:id", get(rest::get_inscription))
+        .route(
+            "/inscriptions/:id/content",
+            get(rest::get_inscription_content),
+        )
+        .route(
+            "/inscriptions/:id/transfers",
+            get(rest::get_inscription_transfers),
+        )
+        .route("/addresses/:address", get(rest::get_address))
+        .route("/addresses/:address/utxos", get(rest::get_address_utxos))
+        .route(
+            "/addresses/:address/transactions",
+            get(rest::get_address_transactions),
+        )
+        .route(
+            "/addresses/:address/inscriptions",
+            get(rest::get_address_inscriptions),
+        )
+        .route("/brc20/tokens", get(rest::get_brc20_tokens))
+        .route("/brc20/tokens/:tick", get(rest::get_brc20_token))
+        .route(
+            "/addresses/:address/brc20",
+            get(rest::get_address_brc20_balances),
+        )
+        .route("/bridge/events", get(rest::get_bridge_events))
+        .route("/bridge/events/:id", get(rest::get_bridge_event))
+        .route("/relay/submissions", get(rest::get_relay_submissions))
         .route("/stats", get(rest::get_stats))
+        .route("/analytics/timeseries", get(rest::get_timeseries))
         // GraphQL endpoint
         .route(
             "/graphql",
             get(graphql::graphql_playground).post(graphql::graphql_handler),
         )
+        .layer(middleware::from_fn(request_id::request_id));
+
+    // Esplora-compatible API, which keeps Esplora's plain-text errors
+    let router = match state.config.esplora_enabled {
+        true => router.nest("/esplora", esplora::router()),
+        false => router,
+    };
+
+    router
         // Add state
         .layer(Extension(state))
         .layer(cors)
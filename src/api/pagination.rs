@@ -0,0 +1,165 @@
+//! Keyset pagination shared by the REST and GraphQL list endpoints.
+//!
+//! A cursor is the sort key of the last item of a page, JSON-encoded and
+//! base64url'd. Clients treat it as opaque and hand it back to continue, so
+//! the key layout of any list can change without breaking them.
+
+use async_graphql::connection::CursorType;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::utils::error::IndexerError;
+
+/// Page size when the client does not ask for one
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+
+/// Largest page any list endpoint returns
+pub const MAX_PAGE_SIZE: i64 = 500;
+
+/// Clamps a requested page size to `1..=MAX_PAGE_SIZE`.
+pub fn page_size(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+pub struct Cursor<K>(pub K);
+
+impl<K: Serialize + DeserializeOwned> Cursor<K> {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(&self.0).expect("cursor keys are plain values");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(cursor: &str) -> anyhow::Result<Self> {
+        let json = URL_SAFE_NO_PAD.decode(cursor)?;
+        Ok(Self(serde_json::from_slice(&json)?))
+    }
+}
+
+impl<K: Serialize + DeserializeOwned + Send + Sync> CursorType for Cursor<K> {
+    type Error = anyhow::Error;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        Self::decode(s)
+    }
+
+    fn encode_cursor(&self) -> String {
+        self.encode()
+    }
+}
+
+/// Decodes an optional `cursor` parameter; a malformed one is invalid input.
+pub fn decode_cursor<K: Serialize + DeserializeOwned>(
+    cursor: Option<&str>,
+) -> Result<Option<K>, IndexerError> {
+    cursor
+        .map(|cursor| {
+            Cursor::decode(cursor)
+                .map(|cursor| cursor.0)
+                .map_err(|_| IndexerError::invalid("cursor", "not a cursor from this API"))
+        })
+        .transpose()
+}
+
+/// One page of a keyset query. Queries fetch `limit + 1` rows so the extra
+/// row tells whether another page follows.
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub has_next: bool,
+}
+
+impl<T> Page<T> {
+    pub fn new(mut items: Vec<T>, limit: i64) -> Self {
+        let has_next = items.len() as i64 > limit;
+        items.truncate(limit as usize);
+        Self { items, has_next }
+    }
+
+    /// Cursor continuing after this page, if there is more.
+    pub fn next_cursor<K: Serialize + DeserializeOwned>(
+        &self,
+        key: impl Fn(&T) -> K,
+    ) -> Option<String> {
+        self.items
+            .last()
+            .filter(|_| self.has_next)
+            .map(|item| Cursor(key(item)).encode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Key = (i64, i32);
+
+    fn rejected(cursor: &str) -> bool {
+        matches!(
+            decode_cursor::<Key>(Some(cursor)),
+            Err(IndexerError::InvalidInput {
+                field: "cursor",
+                ..
+            })
+        )
+    }
+
+    #[test]
+    fn round_trips_cursors() {
+        let cursor = Cursor((840_000i64, 17i32)).encode();
+        assert!(cursor
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(
+            decode_cursor::<Key>(Some(&cursor)).unwrap(),
+            Some((840_000, 17))
+        );
+        assert_eq!(decode_cursor::<Key>(None).unwrap(), None);
+
+        let cursor = Cursor(("rune/ä?".to_string(), -1i64)).encode();
+        assert_eq!(
+            Cursor::<(String, i64)>::decode(&cursor).unwrap().0,
+            ("rune/ä?".to_string(), -1)
+        );
+    }
+
+    #[test]
+    fn rejects_garbage_and_tampered_cursors() {
+        let cursor = Cursor((840_000i64, 17i32)).encode();
+        assert!(rejected(""));
+        assert!(rejected("not a cursor"));
+        assert!(rejected(&format!("{}=", cursor)));
+        assert!(rejected(&cursor[..cursor.len() - 2]));
+        assert!(rejected(&URL_SAFE_NO_PAD.encode("garbage")));
+        // Valid JSON, but not this list's key
+        assert!(rejected(&URL_SAFE_NO_PAD.encode(r#"{"height":840000}"#)));
+        assert!(rejected(&Cursor(("840000".to_string(), 17)).encode()));
+        assert!(rejected(&Cursor((840_000i64, i64::MAX)).encode()));
+    }
+
+    #[test]
+    fn clamps_page_sizes() {
+        assert_eq!(page_size(None), DEFAULT_PAGE_SIZE);
+        for (requested, size) in [
+            (i64::MIN, 1),
+            (-5, 1),
+            (0, 1),
+            (1, 1),
+            (MAX_PAGE_SIZE, MAX_PAGE_SIZE),
+            (MAX_PAGE_SIZE + 1, MAX_PAGE_SIZE),
+            (i64::MAX, MAX_PAGE_SIZE),
+        ] {
+            assert_eq!(page_size(Some(requested)), size, "{}", requested);
+        }
+    }
+
+    #[test]
+    fn continues_only_past_full_pages() {
+        let page = Page::new(vec![(3i64, 0i32), (2, 0), (1, 0)], 2);
+        assert_eq!(page.items, [(3, 0), (2, 0)]);
+        let cursor = page.next_cursor(|item| *item).unwrap();
+        assert_eq!(decode_cursor::<Key>(Some(&cursor)).unwrap(), Some((2, 0)));
+
+        let page = Page::new(vec![(1i64, 0i32)], 2);
+        assert!(!page.has_next);
+        assert_eq!(page.next_cursor(|item| *item), None);
+    }
+}
@@ -0,0 +1,72 @@
+//! Request ids and the JSON error envelope.
+//!
+//! Every request gets an id, the caller's `x-request-id` if it sent a usable
+//! one, echoed back in the response header and in error bodies so a failure
+//! can be matched to the server logs.
+
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+use crate::utils::error::ErrorBody;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest caller-supplied request id that is kept
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Largest plain-text error body rewritten into the envelope
+const MAX_REJECTION_LEN: usize = 4096;
+
+/// The current request's id, available to handlers as a request extension
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+pub async fn request_id<B>(mut req: Request<B>, next: Next<B>) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = tracing::info_span!("request", request_id = %id);
+    let response = next.run(req).instrument(span).await;
+
+    let mut response = with_error_envelope(response, &id).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Stamps the request id on handler errors, and wraps errors that did not come
+/// from a handler (extractor rejections, unknown routes) in the same envelope.
+async fn with_error_envelope(response: Response, id: &str) -> Response {
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return response;
+    }
+
+    let body = match response.extensions().get::<ErrorBody>() {
+        Some(body) => body.clone(),
+        None => {
+            let bytes = hyper::body::to_bytes(response.into_body())
+                .await
+                .unwrap_or_default();
+            let message = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_REJECTION_LEN)]);
+            ErrorBody::from_status(status, message.trim())
+        }
+    };
+
+    ErrorBody {
+        request_id: Some(id.to_string()),
+        ..body
+    }
+    .into_response(status)
+}
@@ -1,108 +1,767 @@
 use axum::{
     extract::{Extension, Path, Query},
-    http::StatusCode,
-    response::Json,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
-use tracing::error;
+use std::collections::BTreeMap;
 
-use crate::api::ApiState;
+use crate::{
+    api::{
+        analytics::{self, Interval, Metric},
+        pagination::{decode_cursor, page_size, Page},
+        validation, ApiState,
+    },
+    db::{
+        models::{AddressStatsModel, PoolStats},
+        InscriptionFilter, RetentionPolicy,
+    },
+    utils::{error::IndexerError, merkle},
+};
+
+/// Inscriptions may only load resources they themselves embed
+const INSCRIPTION_CSP: &str =
+    "default-src 'self' 'unsafe-eval' 'unsafe-inline' data: blob:; connect-src 'none'";
 
 #[derive(Serialize)]
 pub struct HealthResponse {
     status: String,
     version: String,
+    /// Connection pool usage; a pool at saturation 1.0 makes queries wait
+    database_pools: Vec<PoolStats>,
 }
 
-pub async fn health() -> Json<HealthResponse> {
+pub async fn health(Extension(state): Extension<ApiState>) -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "ok".to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
+        database_pools: state.pool_stats(),
     })
 }
 
 pub async fn get_block(
     Path(height): Path<u64>,
     Extension(state): Extension<ApiState>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    match state.db.get_block_by_height(height).await {
-        Ok(Some(block)) => Ok(Json(serde_json::to_value(block).unwrap())),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            error!("Database error: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::height("height", height)?;
+
+    match state.db.get_block_by_height(height).await? {
+        Some(block) => Ok(Json(serde_json::to_value(block)?)),
+        None => Err(IndexerError::not_found(format!("Block {}", height))),
+    }
+}
+
+pub async fn get_block_by_hash(
+    Path(hash): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    let hash = validation::hash("hash", &hash)?;
+
+    match state.db.get_block_by_hash(hash).await? {
+        Some(block) => Ok(Json(serde_json::to_value(block)?)),
+        None => Err(IndexerError::not_found(format!("Block {}", hash))),
+    }
+}
+
+pub async fn get_tip(
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    match state.db.get_tip_block().await? {
+        Some(block) => Ok(Json(serde_json::to_value(block)?)),
+        None => Err(IndexerError::not_found("Tip block")),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BlockListParams {
+    from: Option<u64>,
+    to: Option<u64>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+    /// `asc` or `desc` (default)
+    order: Option<String>,
+}
+
+pub async fn get_blocks(
+    Query(params): Query<BlockListParams>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::height_range(params.from, params.to)?;
+    let descending = match params.order.as_deref() {
+        None | Some("desc") => true,
+        Some("asc") => false,
+        Some(_) => return Err(IndexerError::invalid("order", "expected `asc` or `desc`")),
+    };
+    let limit = page_size(params.limit);
+    let after = decode_cursor::<u64>(params.cursor.as_deref())?;
+
+    let blocks = state
+        .db
+        .get_blocks(params.from, params.to, after, descending, limit + 1)
+        .await?;
+    let page = Page::new(blocks, limit);
+    let next_cursor = page.next_cursor(|block| block.height as u64);
+
+    Ok(Json(serde_json::json!({
+        "blocks": page.items,
+        "pagination": {
+            "limit": limit,
+            "count": page.items.len(),
+            "next_cursor": next_cursor,
         }
+    })))
+}
+
+pub async fn get_block_transactions(
+    Path(height): Path<u64>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::height("height", height)?;
+
+    let block = state
+        .db
+        .get_block_by_height(height)
+        .await?
+        .ok_or_else(|| IndexerError::not_found(format!("Block {}", height)))?;
+    let txids = state.db.get_block_txids(height).await?;
+
+    Ok(Json(serde_json::json!({
+        "height": block.height,
+        "hash": block.hash,
+        "txids": txids,
+    })))
+}
+
+/// Most headers returned by one `/headers` request, as with the P2P `getheaders`
+const MAX_HEADERS: u64 = 2000;
+
+pub async fn get_block_header(
+    Path(height): Path<u64>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::height("height", height)?;
+
+    let block = state
+        .db
+        .get_block_by_height(height)
+        .await?
+        .ok_or_else(|| IndexerError::not_found(format!("Block {}", height)))?;
+
+    match block.header {
+        Some(header) => Ok(Json(serde_json::json!({
+            "height": block.height,
+            "hash": block.hash,
+            "header": header,
+        }))),
+        // Indexed before raw headers were stored
+        None => Err(IndexerError::not_found(format!(
+            "Header of block {}",
+            height
+        ))),
     }
 }
 
+#[derive(Deserialize)]
+pub struct HeadersParams {
+    from: u64,
+    #[serde(default = "default_header_count")]
+    count: u64,
+}
+
+fn default_header_count() -> u64 {
+    MAX_HEADERS
+}
+
+pub async fn get_headers(
+    Query(params): Query<HeadersParams>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::height("from", params.from)?;
+    let count = params.count.clamp(1, MAX_HEADERS);
+
+    let blocks = state
+        .db
+        .get_blocks_range(params.from, params.from.saturating_add(count - 1))
+        .await?;
+
+    // Stop at the first gap so clients always receive a contiguous chain
+    let headers: Vec<_> = blocks
+        .into_iter()
+        .zip(params.from..)
+        .take_while(|(block, height)| block.height as u64 == *height)
+        .map_while(|(block, _)| {
+            Some(serde_json::json!({
+                "height": block.height,
+                "hash": block.hash,
+                "header": block.header?,
+            }))
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "from": params.from,
+        "count": headers.len(),
+        "headers": headers,
+    })))
+}
+
 pub async fn get_transaction(
     Path(txid): Path<String>,
     Extension(state): Extension<ApiState>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    match state.db.get_transaction(&txid).await {
-        Ok(Some(tx)) => Ok(Json(serde_json::to_value(tx).unwrap())),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            error!("Database error: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    let txid = validation::txid(&txid)?;
+
+    match state.db.get_transaction_detail(txid).await? {
+        Some(tx) if tx.transaction.pruned => {
+            Err(IndexerError::Pruned(format!("Transaction {}", txid)))
         }
+        Some(tx) => Ok(Json(serde_json::to_value(tx)?)),
+        None => Err(IndexerError::not_found(format!("Transaction {}", txid))),
     }
 }
 
-#[derive(Deserialize)]
-pub struct PaginationParams {
-    #[serde(default = "default_limit")]
-    limit: i64,
-    #[serde(default)]
-    offset: i64,
+pub async fn get_transaction_proof(
+    Path(txid): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    let txid = validation::txid(&txid)?;
+
+    match merkle::transaction_proof(state.db.as_ref(), txid).await? {
+        Some(proof) => Ok(Json(serde_json::to_value(proof)?)),
+        None => Err(IndexerError::not_found(format!("Transaction {}", txid))),
+    }
 }
 
-fn default_limit() -> i64 {
-    50
+/// `cursor` is the opaque `next_cursor` of the previous page
+#[derive(Deserialize)]
+pub struct PageParams {
+    cursor: Option<String>,
+    limit: Option<i64>,
 }
 
 pub async fn get_runes_transactions(
-    Query(params): Query<PaginationParams>,
+    Query(params): Query<PageParams>,
     Extension(state): Extension<ApiState>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    match state
-        .db
-        .get_runes_transactions(params.limit, params.offset)
-        .await
-    {
-        Ok(txs) => Ok(Json(serde_json::json!({
-            "transactions": txs,
-            "pagination": {
-                "limit": params.limit,
-                "offset": params.offset,
-                "count": txs.len(),
-            }
-        }))),
-        Err(e) => {
-            error!("Database error: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    let limit = page_size(params.limit);
+    let after = decode_cursor(params.cursor.as_deref())?;
+
+    let txs = state.db.get_runes_transactions(after, limit + 1).await?;
+    let page = Page::new(txs, limit);
+    let next_cursor = page.next_cursor(|tx| (tx.block_height, tx.id));
+
+    Ok(Json(serde_json::json!({
+        "transactions": page.items,
+        "pagination": {
+            "limit": limit,
+            "count": page.items.len(),
+            "next_cursor": next_cursor,
         }
-    }
+    })))
 }
 
 #[derive(Serialize)]
 pub struct Stats {
-    pub last_indexed_block: Option<u64>,
+    pub last_indexed_block: Option<i64>,
+    pub node_height: Option<i64>,
+    /// Blocks the node has that are not indexed yet
+    pub lag: Option<i64>,
+    pub sync_eta_secs: Option<i64>,
+    pub total_blocks: i64,
     pub total_transactions: i64,
+    pub total_outputs: i64,
+    pub utxo_count: i64,
+    pub utxo_value: i64,
     pub total_runes_transactions: i64,
+    pub runes_etched: i64,
+    pub runes_minted: i64,
+    pub runes_burned: i64,
+    pub bridge_deposit_volume: i64,
+    pub bridge_withdrawal_volume: i64,
+    /// Height each retention policy has pruned up to
+    pub pruned_heights: BTreeMap<String, u64>,
 }
 
-pub async fn get_stats(Extension(state): Extension<ApiState>) -> Result<Json<Stats>, StatusCode> {
-    // In production, these would be actual queries
-    let last_block = state
-        .db
-        .get_last_block_height()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+pub async fn get_stats(Extension(state): Extension<ApiState>) -> Result<Json<Stats>, IndexerError> {
+    // Totals are maintained per block, so this is a single-row read
+    let stats = state.db.get_chain_stats().await?;
+
+    let mut pruned_heights = BTreeMap::new();
+    for policy in RetentionPolicy::ALL {
+        if let Some(height) = state.db.get_sync_cursor(&policy.cursor()).await? {
+            pruned_heights.insert(policy.to_string(), height);
+        }
+    }
 
     Ok(Json(Stats {
-        last_indexed_block: last_block,
-        total_transactions: 0,       // Implement count query
-        total_runes_transactions: 0, // Implement count query
+        last_indexed_block: stats.tip_height,
+        node_height: stats.node_height,
+        lag: stats.lag(),
+        sync_eta_secs: stats.sync_eta_secs(),
+        total_blocks: stats.blocks,
+        total_transactions: stats.transactions,
+        total_outputs: stats.outputs,
+        utxo_count: stats.utxo_count,
+        utxo_value: stats.utxo_value,
+        total_runes_transactions: stats.runes_transactions,
+        runes_etched: stats.runes_etched,
+        runes_minted: stats.runes_minted,
+        runes_burned: stats.runes_burned,
+        bridge_deposit_volume: stats.bridge_deposit_volume,
+        bridge_withdrawal_volume: stats.bridge_withdrawal_volume,
+        pruned_heights,
     }))
 }
+
+#[derive(Deserialize)]
+pub struct TimeseriesParams {
+    metric: String,
+    /// `1h` (default) or `1d`
+    interval: Option<String>,
+    /// Unix seconds or RFC 3339
+    from: Option<String>,
+    to: Option<String>,
+}
+
+pub async fn get_timeseries(
+    Query(params): Query<TimeseriesParams>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    let metric: Metric = params.metric.parse()?;
+    let interval: Interval = params.interval.as_deref().unwrap_or("1h").parse()?;
+    let from = params
+        .from
+        .as_deref()
+        .map(|from| validation::timestamp("from", from))
+        .transpose()?;
+    let to = params
+        .to
+        .as_deref()
+        .map(|to| validation::timestamp("to", to))
+        .transpose()?;
+
+    let buckets = analytics::timeseries(state.db.as_ref(), metric, interval, from, to).await?;
+
+    Ok(Json(serde_json::json!({
+        "metric": metric.to_string(),
+        "interval": interval.to_string(),
+        "buckets": buckets,
+    })))
+}
+
+pub async fn get_inscription(
+    Path(inscription_id): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::inscription_id(&inscription_id)?;
+
+    match state.db.get_inscription(&inscription_id).await? {
+        Some(inscription) => Ok(Json(serde_json::json!({
+            "content_url": format!("/inscriptions/{}/content", inscription.inscription_id),
+            "inscription": inscription,
+        }))),
+        None => Err(IndexerError::not_found(format!(
+            "Inscription {}",
+            inscription_id
+        ))),
+    }
+}
+
+pub async fn get_inscription_content(
+    Path(inscription_id): Path<String>,
+    headers: HeaderMap,
+    Extension(state): Extension<ApiState>,
+) -> Result<Response, IndexerError> {
+    validation::inscription_id(&inscription_id)?;
+    let not_found =
+        || IndexerError::not_found(format!("Content of inscription {}", inscription_id));
+
+    let inscription = state
+        .db
+        .get_inscription(&inscription_id)
+        .await?
+        .ok_or_else(not_found)?;
+
+    // Encoded bodies are served as-is, so the client must be able to decode them
+    if let Some(encoding) = &inscription.content_encoding {
+        let accepted = headers
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value
+                    .split(',')
+                    .filter_map(|part| part.split(';').next())
+                    .any(|part| part.trim().eq_ignore_ascii_case(encoding))
+            })
+            .unwrap_or(false);
+
+        if !accepted {
+            return Err(IndexerError::NotAcceptable(format!(
+                "content is {}-encoded",
+                encoding
+            )));
+        }
+    }
+
+    let content = match state.db.get_inscription_content(&inscription_id).await? {
+        Some(content) => content,
+        None => match &state.content_store {
+            Some(store) => store
+                .get(&inscription.content_hash)
+                .await?
+                .ok_or_else(not_found)?,
+            None => return Err(not_found()),
+        },
+    };
+
+    let content_type = inscription
+        .content_type
+        .as_deref()
+        .and_then(|value| HeaderValue::from_str(value).ok())
+        .unwrap_or_else(|| HeaderValue::from_static("application/octet-stream"));
+
+    let mut response = (StatusCode::OK, content).into_response();
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CONTENT_TYPE, content_type);
+    response_headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static(INSCRIPTION_CSP),
+    );
+    response_headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("public, max-age=1209600, immutable"),
+    );
+    response_headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    if let Some(encoding) = inscription
+        .content_encoding
+        .as_deref()
+        .and_then(|value| HeaderValue::from_str(value).ok())
+    {
+        response_headers.insert(header::CONTENT_ENCODING, encoding);
+    }
+
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+pub struct InscriptionListParams {
+    cursor: Option<String>,
+    limit: Option<i64>,
+    content_type: Option<String>,
+    from_height: Option<u64>,
+    to_height: Option<u64>,
+}
+
+pub async fn get_inscriptions(
+    Query(params): Query<InscriptionListParams>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::height_range(params.from_height, params.to_height)?;
+    let filter = InscriptionFilter {
+        content_type: params.content_type,
+        from_height: params.from_height,
+        to_height: params.to_height,
+    };
+    let limit = page_size(params.limit);
+    let after = decode_cursor(params.cursor.as_deref())?;
+
+    let inscriptions = state.db.get_inscriptions(&filter, after, limit + 1).await?;
+    let page = Page::new(inscriptions, limit);
+    let next_cursor = page.next_cursor(|inscription| inscription.number);
+
+    Ok(Json(serde_json::json!({
+        "inscriptions": page.items,
+        "pagination": {
+            "limit": limit,
+            "count": page.items.len(),
+            "next_cursor": next_cursor,
+        }
+    })))
+}
+
+pub async fn get_inscription_transfers(
+    Path(inscription_id): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::inscription_id(&inscription_id)?;
+
+    if state.db.get_inscription(&inscription_id).await?.is_none() {
+        return Err(IndexerError::not_found(format!(
+            "Inscription {}",
+            inscription_id
+        )));
+    }
+    let transfers = state.db.get_inscription_transfers(&inscription_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "inscription_id": inscription_id,
+        "transfers": transfers,
+    })))
+}
+
+pub async fn get_address(
+    Path(address): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::address(&address, state.network()?)?;
+
+    // An address never seen on chain simply has nothing
+    let stats = state
+        .db
+        .get_address_stats(&address)
+        .await?
+        .unwrap_or(AddressStatsModel {
+            address: address.clone(),
+            received: 0,
+            sent: 0,
+            tx_count: 0,
+            funded_txo_count: 0,
+            spent_txo_count: 0,
+            first_seen_height: None,
+            last_seen_height: None,
+        });
+
+    Ok(Json(serde_json::json!({
+        "address": stats.address,
+        "balance": stats.received - stats.sent,
+        "received": stats.received,
+        "sent": stats.sent,
+        "tx_count": stats.tx_count,
+        "funded_txo_count": stats.funded_txo_count,
+        "spent_txo_count": stats.spent_txo_count,
+        "first_seen_height": stats.first_seen_height,
+        "last_seen_height": stats.last_seen_height,
+    })))
+}
+
+pub async fn get_address_transactions(
+    Path(address): Path<String>,
+    Query(params): Query<PageParams>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::address(&address, state.network()?)?;
+    let limit = page_size(params.limit);
+    let after = decode_cursor(params.cursor.as_deref())?;
+
+    let transactions = state
+        .db
+        .get_address_transactions(&address, after, limit + 1)
+        .await?;
+    let page = Page::new(transactions, limit);
+    let next_cursor = page.next_cursor(|tx| (tx.block_height, tx.tx_index));
+    let pruned_height = state
+        .db
+        .get_sync_cursor(&RetentionPolicy::AddressHistory.cursor())
+        .await?;
+
+    Ok(Json(serde_json::json!({
+        "address": address,
+        "transactions": page.items,
+        // History at or below this height has been pruned
+        "pruned_height": pruned_height,
+        "pagination": {
+            "limit": limit,
+            "count": page.items.len(),
+            "next_cursor": next_cursor,
+        }
+    })))
+}
+
+pub async fn get_address_utxos(
+    Path(address): Path<String>,
+    Query(params): Query<PageParams>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::address(&address, state.network()?)?;
+    let limit = page_size(params.limit);
+    let after = decode_cursor(params.cursor.as_deref())?;
+
+    let utxos = state
+        .db
+        .get_address_utxos(&address, after, limit + 1)
+        .await?;
+    let page = Page::new(utxos, limit);
+    let next_cursor = page.next_cursor(|utxo| (utxo.block_height, utxo.txid, utxo.vout));
+
+    Ok(Json(serde_json::json!({
+        "address": address,
+        "utxos": page.items,
+        "pagination": {
+            "limit": limit,
+            "count": page.items.len(),
+            "next_cursor": next_cursor,
+        }
+    })))
+}
+
+pub async fn get_address_inscriptions(
+    Path(address): Path<String>,
+    Query(params): Query<PageParams>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::address(&address, state.network()?)?;
+    let limit = page_size(params.limit);
+    let after = decode_cursor(params.cursor.as_deref())?;
+
+    let inscriptions = state
+        .db
+        .get_inscriptions_by_address(&address, after, limit + 1)
+        .await?;
+    let page = Page::new(inscriptions, limit);
+    let next_cursor = page.next_cursor(|inscription| inscription.number);
+
+    Ok(Json(serde_json::json!({
+        "address": address,
+        "inscriptions": page.items,
+        "pagination": {
+            "limit": limit,
+            "count": page.items.len(),
+            "next_cursor": next_cursor,
+        }
+    })))
+}
+
+pub async fn get_brc20_tokens(
+    Query(params): Query<PageParams>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    let limit = page_size(params.limit);
+    let after = decode_cursor(params.cursor.as_deref())?;
+
+    let tokens = state.db.get_brc20_tokens(after, limit + 1).await?;
+    let page = Page::new(tokens, limit);
+    let next_cursor = page.next_cursor(|token| (token.block_height, token.tick.clone()));
+
+    Ok(Json(serde_json::json!({
+        "tokens": page.items,
+        "pagination": {
+            "limit": limit,
+            "count": page.items.len(),
+            "next_cursor": next_cursor,
+        }
+    })))
+}
+
+pub async fn get_brc20_token(
+    Path(tick): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    match state.db.get_brc20_token(&tick.to_lowercase()).await? {
+        Some(token) => Ok(Json(serde_json::to_value(token)?)),
+        None => Err(IndexerError::not_found(format!("BRC-20 token {}", tick))),
+    }
+}
+
+pub async fn get_address_brc20_balances(
+    Path(address): Path<String>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    validation::address(&address, state.network()?)?;
+
+    let balances = state.db.get_brc20_balances(&address).await?;
+
+    Ok(Json(serde_json::json!({
+        "address": address,
+        "balances": balances,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct BridgeEventListParams {
+    cursor: Option<String>,
+    limit: Option<i64>,
+    status: Option<String>,
+    event_type: Option<String>,
+}
+
+pub async fn get_bridge_events(
+    Query(params): Query<BridgeEventListParams>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    let limit = page_size(params.limit);
+    let after = decode_cursor(params.cursor.as_deref())?;
+
+    let events = state
+        .db
+        .get_bridge_events(
+            params.status.as_deref(),
+            params.event_type.as_deref(),
+            after,
+            limit + 1,
+        )
+        .await?;
+    let page = Page::new(events, limit);
+    let next_cursor = page.next_cursor(|event| event.id);
+
+    Ok(Json(serde_json::json!({
+        "events": page.items,
+        "pagination": {
+            "limit": limit,
+            "count": page.items.len(),
+            "next_cursor": next_cursor,
+        }
+    })))
+}
+
+pub async fn get_bridge_event(
+    Path(id): Path<i32>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    let event = state
+        .db
+        .get_bridge_event(id)
+        .await?
+        .ok_or_else(|| IndexerError::not_found(format!("Bridge event {}", id)))?;
+
+    let (transitions, tip) = tokio::try_join!(
+        state.db.get_bridge_event_transitions(id),
+        state.db.get_last_block_height()
+    )?;
+
+    let confirmations = match (tip, event.block_height) {
+        (Some(tip), Some(height)) if event.status.as_deref() != Some("orphaned") => {
+            (tip as i64 - height + 1).max(0)
+        }
+        _ => 0,
+    };
+
+    Ok(Json(serde_json::json!({
+        "event": event,
+        "confirmations": confirmations,
+        "transitions": transitions,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct RelaySubmissionListParams {
+    cursor: Option<String>,
+    limit: Option<i64>,
+    status: Option<String>,
+}
+
+pub async fn get_relay_submissions(
+    Query(params): Query<RelaySubmissionListParams>,
+    Extension(state): Extension<ApiState>,
+) -> Result<Json<serde_json::Value>, IndexerError> {
+    let limit = page_size(params.limit);
+    let after = decode_cursor(params.cursor.as_deref())?;
+
+    let submissions = state
+        .db
+        .get_header_submissions(params.status.as_deref(), after, limit + 1)
+        .await?;
+    let page = Page::new(submissions, limit);
+    let next_cursor = page.next_cursor(|submission| submission.id);
+
+    Ok(Json(serde_json::json!({
+        "submissions": page.items,
+        "pagination": {
+            "limit": limit,
+            "count": page.items.len(),
+            "next_cursor": next_cursor,
+        }
+    })))
+}
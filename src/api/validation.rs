@@ -0,0 +1,87 @@
+//! Request parameter checks shared by the REST and GraphQL APIs, so malformed
+//! input is reported as such instead of as a missing record.
+
+use bitcoin::{Address, Network};
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+use crate::{
+    db::models::Hash256,
+    utils::error::{IndexerError, Result},
+};
+
+/// Txids and block hashes: 32 bytes, hex in display order
+pub fn hash(field: &'static str, value: &str) -> Result<Hash256> {
+    if value.len() != 64 {
+        return Err(IndexerError::invalid(field, "expected 64 hex characters"));
+    }
+    Hash256::from_str(value).map_err(|_| IndexerError::invalid(field, "expected 64 hex characters"))
+}
+
+pub fn txid(value: &str) -> Result<Hash256> {
+    hash("txid", value)
+}
+
+/// Heights are stored as BIGINT
+pub fn height(field: &'static str, value: u64) -> Result<()> {
+    if value > i64::MAX as u64 {
+        return Err(IndexerError::invalid(field, "height out of range"));
+    }
+    Ok(())
+}
+
+/// Optional `from..=to` height bounds
+pub fn height_range(from: Option<u64>, to: Option<u64>) -> Result<()> {
+    if let Some(from) = from {
+        height("from", from)?;
+    }
+    if let Some(to) = to {
+        height("to", to)?;
+    }
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(IndexerError::invalid("from", "must not be above `to`"));
+        }
+    }
+    Ok(())
+}
+
+/// Addresses must parse and belong to the network being indexed
+pub fn address(value: &str, network: Network) -> Result<()> {
+    let address =
+        Address::from_str(value).map_err(|e| IndexerError::invalid("address", e.to_string()))?;
+    address
+        .require_network(network)
+        .map_err(|_| IndexerError::invalid("address", format!("not a {} address", network)))?;
+    Ok(())
+}
+
+/// Inscription ids are `<txid>i<index>`
+pub fn inscription_id(value: &str) -> Result<()> {
+    let valid = value.split_once('i').is_some_and(|(txid, index)| {
+        hash("inscription_id", txid).is_ok() && index.parse::<u32>().is_ok()
+    });
+    if !valid {
+        return Err(IndexerError::invalid(
+            "inscription_id",
+            "expected <txid>i<index>",
+        ));
+    }
+    Ok(())
+}
+
+/// Heights taken as GraphQL `Int`s, which are signed
+pub fn signed_height(field: &'static str, value: i64) -> Result<u64> {
+    u64::try_from(value).map_err(|_| IndexerError::invalid(field, "height must not be negative"))
+}
+
+/// Times as unix seconds or RFC 3339
+pub fn timestamp(field: &'static str, value: &str) -> Result<DateTime<Utc>> {
+    let parsed = match value.parse::<i64>() {
+        Ok(secs) => DateTime::from_timestamp(secs, 0),
+        Err(_) => DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|time| time.with_timezone(&Utc)),
+    };
+    parsed.ok_or_else(|| IndexerError::invalid(field, "expected unix seconds or RFC 3339"))
+}
@@ -1,5 +1,8 @@
+use anyhow::anyhow;
+use bitcoin::Network;
 use config::{Config as ConfigBuilder, ConfigError, Environment};
 use serde::Deserialize;
+use std::str::FromStr;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
@@ -7,10 +10,25 @@ pub struct Config {
     pub bitcoin_rpc_url: String,
     pub bitcoin_rpc_user: String,
     pub bitcoin_rpc_pass: String,
+    /// `mainnet` (or `bitcoin`), `testnet`, `signet` or `regtest`
     pub bitcoin_network: String,
 
     // Database
     pub database_url: String,
+    /// Replica for API queries; they use their own pool on `database_url` when unset
+    pub database_read_url: Option<String>,
+    /// Connections for the indexer and other writers
+    #[serde(default = "default_database_write_pool_size")]
+    pub database_write_pool_size: u32,
+    /// Connections for API queries
+    #[serde(default = "default_database_read_pool_size")]
+    pub database_read_pool_size: u32,
+    /// Cancels API queries running longer than this; 0 disables. PostgreSQL only
+    #[serde(default = "default_database_statement_timeout_ms")]
+    pub database_statement_timeout_ms: u64,
+    /// Convert the append-only event tables to TimescaleDB hypertables on startup
+    #[serde(default)]
+    pub timescaledb_enabled: bool,
 
     // API
     pub api_host: String,
@@ -20,6 +38,137 @@ pub struct Config {
     pub indexer_start_height: u64,
     pub indexer_batch_size: usize,
     pub indexer_poll_interval_secs: u64,
+    /// Directory for the embedded UTXO cache; prevouts are read from the database when unset
+    pub utxo_cache_dir: Option<String>,
+    /// Outputs the UTXO cache keeps in memory before spilling to disk
+    #[serde(default = "default_utxo_cache_capacity")]
+    pub utxo_cache_capacity: usize,
+
+    // Retention; everything is kept forever when unset
+    /// Blocks spent outputs are kept after being spent
+    pub retention_spent_outputs_blocks: Option<u64>,
+    /// Blocks transaction inputs are kept
+    pub retention_transactions_blocks: Option<u64>,
+    /// Blocks per-address history is kept; address totals are never pruned
+    pub retention_address_history_blocks: Option<u64>,
+    #[serde(default = "default_retention_interval_secs")]
+    pub retention_interval_secs: u64,
+
+    /// Serve the Esplora-compatible API under `/esplora`
+    #[serde(default)]
+    pub esplora_enabled: bool,
+
+    /// Address for the Electrum protocol server, e.g. `0.0.0.0:50001`; disabled when unset
+    pub electrum_bind: Option<String>,
+
+    // Inscriptions
+    /// Directory for content-addressed inscription bodies; stored in the database when unset
+    pub inscription_content_dir: Option<String>,
+
+    // Starknet bridge
+    /// Comma-separated Bitcoin addresses that receive bridge deposits
+    pub bridge_deposit_addresses: Option<String>,
+    /// Comma-separated hex scriptPubKeys that receive bridge deposits
+    pub bridge_deposit_scripts: Option<String>,
+    /// Confirmations before a bridge event moves from `pending` to `confirmed`
+    #[serde(default = "default_bridge_confirmations")]
+    pub bridge_confirmations: u64,
+    /// Confirmations before a bridge event is `finalized`
+    #[serde(default = "default_bridge_finality_confirmations")]
+    pub bridge_finality_confirmations: u64,
+    /// Seconds before a withdrawal without a Bitcoin payout is flagged `unmatched`
+    #[serde(default = "default_bridge_withdrawal_timeout_secs")]
+    pub bridge_withdrawal_timeout_secs: u64,
+
+    // Starknet
+    pub starknet_rpc_url: Option<String>,
+    /// Bridge contract emitting withdrawal events. A Bitcoin payout is linked to
+    /// its withdrawal by an `OP_RETURN` output pushing `"SNWD"` followed by the
+    /// 32-byte big-endian hash of the Starknet transaction that requested it.
+    pub starknet_bridge_contract: Option<String>,
+    /// Selector of the bridge's withdrawal event
+    pub starknet_withdrawal_event_key: Option<String>,
+    #[serde(default)]
+    pub starknet_start_block: u64,
+    /// Blocks a withdrawal event must be buried under before it is ingested,
+    /// so that a Starknet reorg cannot leave phantom withdrawals behind
+    #[serde(default = "default_starknet_withdrawal_confirmations")]
+    pub starknet_withdrawal_confirmations: u64,
+    #[serde(default = "default_starknet_poll_interval_secs")]
+    pub starknet_poll_interval_secs: u64,
+
+    // Header relay
+    /// Light-client contract that receives Bitcoin headers
+    pub starknet_relay_contract: Option<String>,
+    /// Entrypoint taking `Array<[u32; 20]>` of consecutive serialized headers
+    #[serde(default = "default_starknet_relay_entrypoint")]
+    pub starknet_relay_entrypoint: String,
+    pub starknet_account_address: Option<String>,
+    pub starknet_private_key: Option<String>,
+    /// First height to relay; defaults to `indexer_start_height`
+    pub starknet_relay_start_height: Option<u64>,
+    #[serde(default = "default_starknet_relay_batch_size")]
+    pub starknet_relay_batch_size: u64,
+    /// Max fee in fri for each relay transaction, as hex; caps the resource
+    /// bounds derived from the fee estimate
+    #[serde(default = "default_starknet_relay_max_fee")]
+    pub starknet_relay_max_fee: String,
+    /// Tip in fri per unit of L2 gas for each relay transaction
+    #[serde(default)]
+    pub starknet_relay_tip: u64,
+}
+
+fn default_database_write_pool_size() -> u32 {
+    10
+}
+
+fn default_database_read_pool_size() -> u32 {
+    20
+}
+
+fn default_database_statement_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_utxo_cache_capacity() -> usize {
+    1_000_000
+}
+
+fn default_retention_interval_secs() -> u64 {
+    10 * 60
+}
+
+fn default_bridge_confirmations() -> u64 {
+    6
+}
+
+fn default_bridge_finality_confirmations() -> u64 {
+    100
+}
+
+fn default_bridge_withdrawal_timeout_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_starknet_withdrawal_confirmations() -> u64 {
+    10
+}
+
+fn default_starknet_poll_interval_secs() -> u64 {
+    30
+}
+
+fn default_starknet_relay_entrypoint() -> String {
+    "submit_headers".to_string()
+}
+
+fn default_starknet_relay_batch_size() -> u64 {
+    10
+}
+
+fn default_starknet_relay_max_fee() -> String {
+    // 10 STRK
+    "0x8ac7230489e80000".to_string()
 }
 
 impl Config {
@@ -32,4 +181,40 @@ impl Config {
 
         config.try_deserialize()
     }
+
+    /// The configured `bitcoin_network`, accepting `mainnet` for `bitcoin`.
+    pub fn network(&self) -> anyhow::Result<Network> {
+        match self.bitcoin_network.as_str() {
+            "mainnet" => Ok(Network::Bitcoin),
+            name => Network::from_str(name).map_err(|_| {
+                anyhow!(
+                    "Invalid BITCOIN_NETWORK {:?}: expected mainnet, testnet, signet or regtest",
+                    name
+                )
+            }),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+impl Config {
+    /// Regtest settings for tests, with `overrides` (in field names) applied.
+    pub(crate) fn for_tests(overrides: serde_json::Value) -> Self {
+        let mut config = serde_json::json!({
+            "bitcoin_rpc_url": "http://127.0.0.1:18443",
+            "bitcoin_rpc_user": "user",
+            "bitcoin_rpc_pass": "pass",
+            "bitcoin_network": "regtest",
+            "database_url": "sqlite::memory:",
+            "api_host": "127.0.0.1",
+            "api_port": 0,
+            "indexer_start_height": 0,
+            "indexer_batch_size": 10,
+            "indexer_poll_interval_secs": 1,
+        });
+        if let serde_json::Value::Object(overrides) = overrides {
+            config.as_object_mut().unwrap().extend(overrides);
+        }
+        serde_json::from_value(config).unwrap()
+    }
 }
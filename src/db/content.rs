@@ -0,0 +1,43 @@
+use anyhow::Result;
+use std::path::PathBuf;
+use tokio::fs;
+
+/// Content-addressed blob directory for inscription bodies, keyed by sha256.
+#[derive(Debug, Clone)]
+pub struct ContentStore {
+    dir: PathBuf,
+}
+
+impl ContentStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub async fn put(&self, hash: &str, content: &[u8]) -> Result<()> {
+        let path = self.path(hash);
+        if fs::try_exists(&path).await? {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.dir).await?;
+
+        // Write to a temporary file first so readers never see partial blobs
+        let tmp = self.dir.join(format!("{}.tmp", hash));
+        fs::write(&tmp, content).await?;
+        fs::rename(&tmp, &path).await?;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.path(hash)).await {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn path(&self, hash: &str) -> PathBuf {
+        self.dir.join(hash)
+    }
+}
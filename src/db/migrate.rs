@@ -0,0 +1,350 @@
+//! PostgreSQL schema migrations embedded from `migrations/` at build time.
+//!
+//! Applied versions and their checksums live in sqlx's `_sqlx_migrations`
+//! table. Runs hold a Postgres advisory lock, so instances starting together
+//! apply each migration once, and refuse to start if an applied migration's
+//! file has since been edited.
+
+use anyhow::{bail, Result};
+use sqlx::database::HasArguments;
+use sqlx::migrate::{AppliedMigration, Migrate, Migrator};
+use sqlx::{Database, Encode, Executor, IntoArguments, PgPool, Postgres, Type};
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Append-only tables partitioned by `timestamp` when TimescaleDB is enabled.
+/// Their keys include the partition column and nothing references them.
+const HYPERTABLES: &[&str] = &[
+    "runes_transactions",
+    "inscription_transfers",
+    "brc20_events",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the embedded file no longer matches what was run
+    ChecksumMismatch,
+    /// Started and did not finish
+    Failed,
+    /// Applied by a newer build; not embedded in this one
+    Unknown,
+}
+
+impl std::fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::ChecksumMismatch => "checksum mismatch",
+            MigrationState::Failed => "failed",
+            MigrationState::Unknown => "unknown",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+/// Applies pending migrations, verifying the checksums of applied ones.
+pub async fn run(pool: &PgPool) -> Result<()> {
+    // Schemas set up by piping the files into psql have no version table yet
+    let tracked = table_exists(pool, "_sqlx_migrations").await?;
+    if !tracked && table_exists(pool, "blocks").await? {
+        bail!(
+            "Database schema predates migration tracking; run `migrate baseline <version>` \
+             with the last migration applied by hand"
+        );
+    }
+
+    MIGRATOR.run(pool).await?;
+    info!("Database migrations completed");
+    Ok(())
+}
+
+pub async fn status(pool: &PgPool) -> Result<Vec<MigrationStatus>> {
+    let mut conn = pool.acquire().await?;
+    let (applied, dirty) = match table_exists(pool, "_sqlx_migrations").await? {
+        true => (
+            conn.list_applied_migrations().await?,
+            conn.dirty_version().await?,
+        ),
+        false => (Vec::new(), None),
+    };
+
+    Ok(compare(&MIGRATOR, applied, dirty))
+}
+
+/// Matches the migrations embedded in `migrator` against those a database has
+/// recorded, including ones only a newer build knows about.
+pub fn compare(
+    migrator: &Migrator,
+    applied: Vec<AppliedMigration>,
+    dirty: Option<i64>,
+) -> Vec<MigrationStatus> {
+    let mut applied: HashMap<i64, Vec<u8>> = applied
+        .into_iter()
+        .map(|migration| (migration.version, migration.checksum.into_owned()))
+        .collect();
+
+    let mut statuses: Vec<MigrationStatus> = migrator
+        .iter()
+        .map(|migration| {
+            let state = match applied.remove(&migration.version) {
+                _ if dirty == Some(migration.version) => MigrationState::Failed,
+                None => MigrationState::Pending,
+                Some(checksum) if checksum == *migration.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::ChecksumMismatch,
+            };
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+            }
+        })
+        .collect();
+
+    statuses.extend(applied.into_keys().map(|version| MigrationStatus {
+        version,
+        description: String::new(),
+        state: MigrationState::Unknown,
+    }));
+    statuses.sort_by_key(|status| status.version);
+
+    statuses
+}
+
+/// Records migrations up to `version` as applied without running them, for
+/// databases whose schema was created by hand.
+pub async fn baseline(pool: &PgPool, version: i64) -> Result<u64> {
+    let mut conn = pool.acquire().await?;
+    conn.lock().await?;
+    let result = record_applied::<Postgres>(&mut conn, &MIGRATOR, version).await;
+    conn.unlock().await?;
+    result
+}
+
+/// Records the migrations of `migrator` up to `version` as applied, returning
+/// how many were not recorded already.
+async fn record_applied<DB>(
+    conn: &mut DB::Connection,
+    migrator: &Migrator,
+    version: i64,
+) -> Result<u64>
+where
+    DB: Database,
+    DB::Connection: Migrate,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'q> i64: Encode<'q, DB> + Type<DB>,
+    for<'q> &'q str: Encode<'q, DB> + Type<DB>,
+    for<'q> &'q [u8]: Encode<'q, DB> + Type<DB>,
+{
+    conn.ensure_migrations_table().await?;
+    let applied: HashSet<i64> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+
+    let mut recorded = 0;
+    for migration in migrator
+        .iter()
+        .filter(|m| m.version <= version && !applied.contains(&m.version))
+    {
+        sqlx::query::<DB>(
+            r#"
+            INSERT INTO _sqlx_migrations
+            (version, description, success, checksum, execution_time)
+            VALUES ($1, $2, TRUE, $3, 0)
+            "#,
+        )
+        .bind(migration.version)
+        .bind(&*migration.description)
+        .bind(&*migration.checksum)
+        .execute(&mut *conn)
+        .await?;
+        recorded += 1;
+    }
+
+    Ok(recorded)
+}
+
+/// Installs TimescaleDB and converts [`HYPERTABLES`]; tables that are already
+/// hypertables are left alone, so this is safe on every start.
+pub async fn enable_timescaledb(pool: &PgPool) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    conn.lock().await?;
+
+    let result = async {
+        sqlx::query("CREATE EXTENSION IF NOT EXISTS timescaledb")
+            .execute(&mut *conn)
+            .await?;
+
+        for table in HYPERTABLES {
+            sqlx::query(
+                r#"
+                SELECT create_hypertable($1::regclass, 'timestamp',
+                    chunk_time_interval => INTERVAL '1 day',
+                    if_not_exists => TRUE,
+                    migrate_data => TRUE)
+                "#,
+            )
+            .bind(table)
+            .execute(&mut *conn)
+            .await?;
+        }
+        anyhow::Ok(())
+    }
+    .await;
+
+    conn.unlock().await?;
+    result?;
+
+    info!("TimescaleDB hypertables enabled");
+    Ok(())
+}
+
+async fn table_exists(pool: &PgPool, table: &str) -> Result<bool> {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT to_regclass($1) IS NOT NULL")
+        .bind(table)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(exists)
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::{sqlite::MIGRATOR as SQLITE_MIGRATOR, SqliteDatabase, Storage};
+    use sqlx::{Connection, Sqlite, SqliteConnection};
+
+    /// A directory for a database file that outlives its connections, and
+    /// the file's URL
+    fn database(name: &str) -> (std::path::PathBuf, String) {
+        let dir = std::env::temp_dir().join(format!("migrate-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let url = format!("sqlite://{}", dir.join("index.db").display());
+        (dir, url)
+    }
+
+    fn states(statuses: &[MigrationStatus]) -> Vec<(i64, MigrationState)> {
+        statuses.iter().map(|s| (s.version, s.state)).collect()
+    }
+
+    fn embedded(state: MigrationState) -> Vec<(i64, MigrationState)> {
+        SQLITE_MIGRATOR.iter().map(|m| (m.version, state)).collect()
+    }
+
+    #[tokio::test]
+    async fn reruns_applied_migrations_as_no_ops() {
+        let (dir, url) = database("rerun");
+        let db = SqliteDatabase::new(&url, 1).await.unwrap();
+        assert_eq!(
+            states(&db.migration_status().await.unwrap()),
+            embedded(MigrationState::Pending)
+        );
+
+        db.run_migrations().await.unwrap();
+        db.run_migrations().await.unwrap();
+        // and again from a later start
+        let db = SqliteDatabase::new(&url, 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+
+        assert_eq!(
+            states(&db.migration_status().await.unwrap()),
+            embedded(MigrationState::Applied)
+        );
+        let mut conn = SqliteConnection::connect(&url).await.unwrap();
+        let recorded: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(recorded as usize, SQLITE_MIGRATOR.iter().count());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn detects_edited_failed_and_unknown_migrations() {
+        let (dir, url) = database("checksums");
+        let db = SqliteDatabase::new(&url, 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+
+        let mut conn = SqliteConnection::connect(&url).await.unwrap();
+        for statement in [
+            "UPDATE _sqlx_migrations SET checksum = x'00' WHERE version = 2",
+            "UPDATE _sqlx_migrations SET success = FALSE WHERE version = 3",
+            "INSERT INTO _sqlx_migrations \
+             (version, description, success, checksum, execution_time) \
+             VALUES (9999, 'from a newer build', TRUE, x'00', 0)",
+        ] {
+            sqlx::query(statement).execute(&mut conn).await.unwrap();
+        }
+
+        let mut expected = embedded(MigrationState::Applied);
+        expected[1].1 = MigrationState::ChecksumMismatch;
+        expected[2].1 = MigrationState::Failed;
+        expected.push((9999, MigrationState::Unknown));
+        assert_eq!(states(&db.migration_status().await.unwrap()), expected);
+
+        // The edit alone is enough to refuse to start
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version IN (3, 9999)")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        let error = db.run_migrations().await.unwrap_err();
+        assert!(error.to_string().contains("modified"), "{}", error);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn baselines_without_running_migrations() {
+        let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+        let count = SQLITE_MIGRATOR.iter().count() as u64;
+
+        assert_eq!(
+            record_applied::<Sqlite>(&mut conn, &SQLITE_MIGRATOR, 3)
+                .await
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            record_applied::<Sqlite>(&mut conn, &SQLITE_MIGRATOR, 3)
+                .await
+                .unwrap(),
+            0
+        );
+        let applied = conn.list_applied_migrations().await.unwrap();
+        let mut expected = embedded(MigrationState::Pending);
+        for state in &mut expected[..3] {
+            state.1 = MigrationState::Applied;
+        }
+        assert_eq!(states(&compare(&SQLITE_MIGRATOR, applied, None)), expected);
+
+        assert_eq!(
+            record_applied::<Sqlite>(&mut conn, &SQLITE_MIGRATOR, i64::MAX)
+                .await
+                .unwrap(),
+            count - 3
+        );
+        // Nothing is left to run, and nothing was run
+        SQLITE_MIGRATOR.run(&mut conn).await.unwrap();
+        let blocks: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'blocks')")
+                .fetch_one(&mut conn)
+                .await
+                .unwrap();
+        assert!(!blocks);
+    }
+}
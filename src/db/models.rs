@@ -1,22 +1,221 @@
+use bitcoin::{hashes::Hash, BlockHash, ScriptBuf, TxMerkleNode, Txid};
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::{
+    database::{HasArguments, HasValueRef},
+    encode::IsNull,
+    error::BoxDynError,
+    Database, Decode, Encode, FromRow, Type,
+};
+use std::{collections::HashSet, fmt, str::FromStr};
+
+use crate::utils::script::script_type;
+
+/// A txid, block hash or other 32-byte hash, stored as BYTEA in internal byte
+/// order and displayed byte-reversed the way Bitcoin Core prints it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hash256([u8; 32]);
+
+impl Hash256 {
+    pub fn from_byte_array(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn to_byte_array(self) -> [u8; 32] {
+        self.0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<u8>> for Hash256 {
+    type Error = std::array::TryFromSliceError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        bytes.as_slice().try_into().map(Self)
+    }
+}
+
+impl fmt::Display for Hash256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bytes = self.0;
+        bytes.reverse();
+        f.write_str(&hex::encode(bytes))
+    }
+}
+
+impl FromStr for Hash256 {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(s, &mut bytes)?;
+        bytes.reverse();
+        Ok(Self(bytes))
+    }
+}
+
+impl From<Txid> for Hash256 {
+    fn from(txid: Txid) -> Self {
+        Self(txid.to_byte_array())
+    }
+}
+
+impl From<BlockHash> for Hash256 {
+    fn from(hash: BlockHash) -> Self {
+        Self(hash.to_byte_array())
+    }
+}
+
+impl From<TxMerkleNode> for Hash256 {
+    fn from(hash: TxMerkleNode) -> Self {
+        Self(hash.to_byte_array())
+    }
+}
+
+impl From<Hash256> for Txid {
+    fn from(hash: Hash256) -> Self {
+        Txid::from_byte_array(hash.0)
+    }
+}
+
+impl From<Hash256> for BlockHash {
+    fn from(hash: Hash256) -> Self {
+        BlockHash::from_byte_array(hash.0)
+    }
+}
+
+/// Raw bytes such as scripts and headers, stored as BYTEA and displayed as hex.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct HexBytes(pub Vec<u8>);
+
+impl HexBytes {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for HexBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(&self.0))
+    }
+}
+
+impl FromStr for HexBytes {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        hex::decode(s).map(Self)
+    }
+}
+
+impl From<Vec<u8>> for HexBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<&bitcoin::Script> for HexBytes {
+    fn from(script: &bitcoin::Script) -> Self {
+        Self(script.to_bytes())
+    }
+}
+
+impl From<&HexBytes> for ScriptBuf {
+    fn from(bytes: &HexBytes) -> Self {
+        ScriptBuf::from_bytes(bytes.0.clone())
+    }
+}
+
+/// Both types are written to the API and caches as their display strings
+macro_rules! impl_bytes_traits {
+    ($ty:ty) => {
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer)?
+                    .parse()
+                    .map_err(de::Error::custom)
+            }
+        }
+
+        impl<DB: Database> Type<DB> for $ty
+        where
+            Vec<u8>: Type<DB>,
+        {
+            fn type_info() -> DB::TypeInfo {
+                <Vec<u8> as Type<DB>>::type_info()
+            }
+
+            fn compatible(ty: &DB::TypeInfo) -> bool {
+                <Vec<u8> as Type<DB>>::compatible(ty)
+            }
+        }
+
+        impl<'q, DB: Database> Encode<'q, DB> for $ty
+        where
+            Vec<u8>: Encode<'q, DB>,
+        {
+            fn encode_by_ref(&self, buf: &mut <DB as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+                self.as_bytes().to_vec().encode(buf)
+            }
+        }
+
+        impl<'r, DB: Database> Decode<'r, DB> for $ty
+        where
+            Vec<u8>: Decode<'r, DB>,
+        {
+            fn decode(value: <DB as HasValueRef<'r>>::ValueRef) -> Result<Self, BoxDynError> {
+                Ok(<Vec<u8> as Decode<DB>>::decode(value)?.try_into()?)
+            }
+        }
+
+        impl sqlx::postgres::PgHasArrayType for $ty {
+            fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+                <Vec<u8> as sqlx::postgres::PgHasArrayType>::array_type_info()
+            }
+        }
+    };
+}
+
+impl_bytes_traits!(Hash256);
+impl_bytes_traits!(HexBytes);
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct BlockModel {
     pub height: i64,
-    pub hash: String,
-    pub prev_hash: String,
+    pub hash: Hash256,
+    pub prev_hash: Hash256,
     pub timestamp: DateTime<Utc>,
-    pub merkle_root: String,
+    pub merkle_root: Hash256,
     pub indexed_at: Option<DateTime<Utc>>,
+    /// Raw 80-byte header
+    pub header: Option<HexBytes>,
+    pub version: Option<i32>,
+    pub bits: Option<i64>,
+    pub nonce: Option<i64>,
+    pub difficulty: Option<f64>,
+    /// Cumulative chain work up to and including this block, 64 hex digits
+    pub chainwork: Option<String>,
+    pub median_time: Option<DateTime<Utc>>,
+    pub tx_count: Option<i32>,
+    pub size: Option<i32>,
+    pub stripped_size: Option<i32>,
+    pub weight: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct TransactionModel {
-    pub txid: String,
+    pub txid: Hash256,
     pub block_height: i64,
-    pub block_hash: String,
+    pub block_hash: Hash256,
     pub version: i32,
     pub locktime: i64,
     pub size: i32,
@@ -24,28 +223,165 @@ pub struct TransactionModel {
     pub fee: Option<i64>,
     pub timestamp: DateTime<Utc>,
     pub indexed_at: Option<DateTime<Utc>>,
+    /// Position within the block
+    pub tx_index: Option<i32>,
+    /// Inputs or all outputs were dropped by a retention policy
+    pub pruned: bool,
+    /// Outputs created; unknown for transactions pruned before it was recorded
+    pub output_count: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct OutputModel {
-    pub txid: String,
+    pub txid: Hash256,
     pub vout: i32,
     pub value: i64,
-    pub script_pubkey: String,
+    pub script_pubkey: HexBytes,
     pub address: Option<String>,
     pub spent: bool,
-    pub spending_txid: Option<String>,
+    pub spending_txid: Option<Hash256>,
     pub spending_vin: Option<i32>,
+    /// Electrum scripthash of `script_pubkey`
+    pub scripthash: Option<Hash256>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct InputModel {
+    pub txid: Hash256,
+    pub vin: i32,
+    pub prev_txid: Option<Hash256>,
+    pub prev_vout: Option<i32>,
+    pub script_sig: HexBytes,
+    pub witness: Vec<HexBytes>,
+    pub sequence: i64,
+    pub value: Option<i64>,
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputDetail {
+    #[serde(flatten)]
+    pub output: OutputModel,
+    pub script_type: String,
+}
+
+/// A transaction with its inputs, outputs and attached protocol events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionDetail {
+    #[serde(flatten)]
+    pub transaction: TransactionModel,
+    pub vsize: i64,
+    /// Fee rate in sat/vB, when the fee is known
+    pub feerate: Option<f64>,
+    /// Confirmations relative to the indexed tip
+    pub confirmations: i64,
+    pub inputs: Vec<InputModel>,
+    pub outputs: Vec<OutputDetail>,
+    /// Vouts of spent outputs dropped by a retention policy
+    pub pruned_outputs: Vec<i32>,
+    pub runes: Vec<RunesTransactionModel>,
+    pub bridge_events: Vec<BridgeEventModel>,
+}
+
+impl TransactionDetail {
+    /// Derives sizes, fee rate and confirmations; `tip` is the indexed tip height.
+    pub fn new(
+        transaction: TransactionModel,
+        tip: Option<i64>,
+        inputs: Vec<InputModel>,
+        outputs: Vec<OutputModel>,
+        runes: Vec<RunesTransactionModel>,
+        bridge_events: Vec<BridgeEventModel>,
+    ) -> Self {
+        let vsize = (transaction.weight as i64 + 3) / 4;
+        let feerate = transaction
+            .fee
+            .map(|fee| (fee as f64 / vsize as f64 * 100.0).round() / 100.0);
+        let confirmations = (tip.unwrap_or_default() - transaction.block_height + 1).max(0);
+        let retained: HashSet<i32> = outputs.iter().map(|output| output.vout).collect();
+        let pruned_outputs = (0..transaction.output_count.unwrap_or_default())
+            .filter(|vout| !retained.contains(vout))
+            .collect();
+
+        Self {
+            transaction,
+            vsize,
+            feerate,
+            confirmations,
+            inputs,
+            outputs: outputs
+                .into_iter()
+                .map(|output| OutputDetail {
+                    script_type: script_type(&ScriptBuf::from(&output.script_pubkey)).to_string(),
+                    output,
+                })
+                .collect(),
+            pruned_outputs,
+            runes,
+            bridge_events,
+        }
+    }
+
+    /// Whether every input and output is still indexed, so the raw
+    /// transaction can be rebuilt.
+    pub fn is_complete(&self) -> bool {
+        !self.transaction.pruned && self.pruned_outputs.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AddressStatsModel {
+    pub address: String,
+    pub received: i64,
+    pub sent: i64,
+    pub tx_count: i32,
+    pub funded_txo_count: i32,
+    pub spent_txo_count: i32,
+    pub first_seen_height: Option<i64>,
+    pub last_seen_height: Option<i64>,
+}
+
+/// What one transaction paid to and spent from an address
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AddressTransactionModel {
+    pub address: String,
+    pub txid: Hash256,
+    pub block_height: i64,
+    pub tx_index: i32,
+    pub received: i64,
+    pub sent: i64,
+    pub funded_outputs: i32,
+    pub spent_outputs: i32,
+}
+
+/// A confirmed transaction touching a script, in Electrum history order
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ScriptHistoryModel {
+    pub txid: Hash256,
+    pub block_height: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UtxoModel {
+    pub txid: Hash256,
+    pub vout: i32,
+    pub value: i64,
+    pub script_pubkey: HexBytes,
+    pub address: Option<String>,
+    pub block_height: i64,
+    pub block_hash: Hash256,
+    pub block_time: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct RunesTransactionModel {
     pub id: i32,
-    pub txid: String,
+    pub txid: Hash256,
     pub block_height: i64,
     pub rune_id: Option<String>,
     pub operation: String,
-    pub amount: Option<sqlx::types::Decimal>,
+    /// Decimal string
+    pub amount: Option<String>,
     pub from_address: Option<String>,
     pub to_address: Option<String>,
     pub metadata: Option<serde_json::Value>,
@@ -61,6 +397,16 @@ pub struct RunesData {
     pub from_address: Option<String>,
     pub to_address: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    /// Explicit assignments of `amount` to outputs
+    pub edicts: Vec<RuneEdict>,
+}
+
+/// Runes a runestone assigns to one output; an amount of zero assigns all that
+/// is left, and an output equal to the output count splits across all outputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuneEdict {
+    pub output: u32,
+    pub amount: u128,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,13 +417,346 @@ pub enum RuneOperation {
     Etch,
 }
 
-impl ToString for RuneOperation {
-    fn to_string(&self) -> String {
-        match self {
-            RuneOperation::Mint => "mint".to_string(),
-            RuneOperation::Transfer => "transfer".to_string(),
-            RuneOperation::Burn => "burn".to_string(),
-            RuneOperation::Etch => "etch".to_string(),
+impl std::fmt::Display for RuneOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            RuneOperation::Mint => "mint",
+            RuneOperation::Transfer => "transfer",
+            RuneOperation::Burn => "burn",
+            RuneOperation::Etch => "etch",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct InscriptionModel {
+    pub inscription_id: String,
+    pub number: i64,
+    pub txid: Hash256,
+    pub input_index: i32,
+    pub block_height: i64,
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    pub metaprotocol: Option<String>,
+    pub content_length: i64,
+    pub content_hash: String,
+    pub location_txid: Option<Hash256>,
+    pub location_vout: Option<i32>,
+    pub location_offset: Option<i64>,
+    pub address: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub indexed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct InscriptionTransferModel {
+    pub id: i32,
+    pub inscription_id: String,
+    pub txid: Hash256,
+    pub block_height: i64,
+    pub from_txid: Option<Hash256>,
+    pub from_vout: Option<i32>,
+    pub from_offset: Option<i64>,
+    pub to_txid: Option<Hash256>,
+    pub to_vout: Option<i32>,
+    pub to_offset: Option<i64>,
+    pub to_address: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub indexed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InscriptionData {
+    pub inscription_id: String,
+    pub input_index: u32,
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    pub metaprotocol: Option<String>,
+    pub content: Vec<u8>,
+    /// Where the inscribed sat landed, `None` if it was lost to fees
+    pub location: Option<InscriptionLocation>,
+}
+
+/// A sat position within a transaction output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InscriptionLocation {
+    pub txid: Hash256,
+    pub vout: u32,
+    pub offset: u64,
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Brc20TokenModel {
+    pub tick: String,
+    pub inscription_id: String,
+    pub max_supply: String,
+    pub mint_limit: String,
+    pub decimals: i32,
+    pub minted: String,
+    pub deployer: Option<String>,
+    pub block_height: i64,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Brc20BalanceModel {
+    pub address: String,
+    pub tick: String,
+    pub available: String,
+    pub transferable: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Brc20TransferModel {
+    pub inscription_id: String,
+    pub tick: String,
+    pub amount: String,
+    pub from_address: String,
+    pub to_address: Option<String>,
+    pub used: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Brc20EventModel {
+    pub id: i32,
+    pub inscription_id: String,
+    pub operation: String,
+    pub tick: String,
+    pub amount: Option<String>,
+    pub from_address: Option<String>,
+    pub to_address: Option<String>,
+    pub txid: Hash256,
+    pub block_height: i64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A validated BRC-20 operation, amounts as decimal strings
+#[derive(Debug, Clone)]
+pub struct Brc20Event {
+    pub inscription_id: String,
+    pub operation: Brc20EventKind,
+    pub tick: String,
+    pub amount: Option<String>,
+    pub from_address: Option<String>,
+    pub to_address: Option<String>,
+    pub txid: Hash256,
+    pub block_height: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Brc20EventKind {
+    Deploy,
+    Mint,
+    InscribeTransfer,
+    Transfer,
+}
+
+impl std::fmt::Display for Brc20EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Brc20EventKind::Deploy => "deploy",
+            Brc20EventKind::Mint => "mint",
+            Brc20EventKind::InscribeTransfer => "inscribe_transfer",
+            Brc20EventKind::Transfer => "transfer",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BridgeEventModel {
+    pub id: i32,
+    pub event_type: String,
+    pub txid: Option<Hash256>,
+    pub vout: Option<i32>,
+    pub block_height: Option<i64>,
+    pub starknet_tx_hash: Option<String>,
+    pub starknet_event_index: Option<i32>,
+    pub bitcoin_address: Option<String>,
+    pub starknet_address: Option<String>,
+    pub amount: Option<i64>,
+    pub asset: Option<String>,
+    pub status: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub timestamp: DateTime<Utc>,
+    pub indexed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeEventData {
+    pub event_type: String,
+    pub txid: Option<Hash256>,
+    pub vout: Option<u32>,
+    pub starknet_tx_hash: Option<String>,
+    pub starknet_event_index: Option<u32>,
+    pub bitcoin_address: Option<String>,
+    pub starknet_address: Option<String>,
+    pub amount: Option<i64>,
+    pub asset: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BridgeEventTransitionModel {
+    pub id: i32,
+    pub event_id: i32,
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub block_height: Option<i64>,
+    pub reason: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Lifecycle of a bridge event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BridgeEventStatus {
+    Pending,
+    Confirmed,
+    Finalized,
+    Orphaned,
+    /// Withdrawal with no Bitcoin payout within the configured timeout
+    Unmatched,
+    /// Withdrawal whose payout does not pay the requested recipient and amount
+    Mismatched,
+}
+
+impl std::fmt::Display for BridgeEventStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            BridgeEventStatus::Pending => "pending",
+            BridgeEventStatus::Confirmed => "confirmed",
+            BridgeEventStatus::Finalized => "finalized",
+            BridgeEventStatus::Orphaned => "orphaned",
+            BridgeEventStatus::Unmatched => "unmatched",
+            BridgeEventStatus::Mismatched => "mismatched",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct HeaderRelaySubmissionModel {
+    pub id: i32,
+    pub from_height: i64,
+    pub to_height: i64,
+    pub starknet_tx_hash: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+    /// For a failed submission, which consecutive failure of its batch it was
+    pub attempt: Option<i32>,
+    /// For a failed submission, when its batch may be sent again
+    pub retry_after: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Lifecycle of a header relay submission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeaderRelayStatus {
+    /// Sent to the sequencer, awaiting a receipt
+    Submitted,
+    Accepted,
+    Reverted,
+    Rejected,
+    /// Could not be built or sent; retried on the next poll
+    Failed,
+    /// Covered headers were reorged out of the Bitcoin chain
+    Orphaned,
+}
+
+impl std::fmt::Display for HeaderRelayStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            HeaderRelayStatus::Submitted => "submitted",
+            HeaderRelayStatus::Accepted => "accepted",
+            HeaderRelayStatus::Reverted => "reverted",
+            HeaderRelayStatus::Rejected => "rejected",
+            HeaderRelayStatus::Failed => "failed",
+            HeaderRelayStatus::Orphaned => "orphaned",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Running chain totals with the indexer's position relative to the node
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ChainStatsModel {
+    pub blocks: i64,
+    pub transactions: i64,
+    pub outputs: i64,
+    pub utxo_count: i64,
+    pub utxo_value: i64,
+    pub runes_transactions: i64,
+    pub runes_etched: i64,
+    pub runes_minted: i64,
+    pub runes_burned: i64,
+    pub bridge_deposit_volume: i64,
+    pub bridge_withdrawal_volume: i64,
+    pub node_height: Option<i64>,
+    pub tip_height: Option<i64>,
+    /// Recent indexing speed, over the last 100 blocks
+    pub blocks_per_sec: Option<f64>,
+}
+
+impl ChainStatsModel {
+    /// Blocks the node has that are not indexed yet
+    pub fn lag(&self) -> Option<i64> {
+        let node_height = self.node_height?;
+        Some((node_height - self.tip_height.unwrap_or(-1)).max(0))
+    }
+
+    /// Seconds until caught up at the recent indexing speed
+    pub fn sync_eta_secs(&self) -> Option<i64> {
+        match self.lag()? {
+            0 => Some(0),
+            lag => self
+                .blocks_per_sec
+                .filter(|rate| *rate > 0.0)
+                .map(|rate| (lag as f64 / rate).ceil() as i64),
+        }
+    }
+}
+
+/// Chain activity within one hourly or daily bucket
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TimeseriesBucketModel {
+    /// Start of the bucket, by block timestamp
+    pub bucket: DateTime<Utc>,
+    pub blocks: i64,
+    pub tx_count: i64,
+    /// Fees claimed by the coinbases above the subsidy
+    pub fees: i64,
+    pub runes_mints: i64,
+    /// Total size of the blocks in bytes
+    pub block_size: i64,
+}
+
+/// Usage of one connection pool
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStats {
+    pub name: &'static str,
+    pub max_connections: u32,
+    /// Open connections, idle or in use
+    pub connections: u32,
+    pub idle: u32,
+    /// Share of `max_connections` in use; at 1.0 new queries wait for a connection
+    pub saturation: f64,
+}
+
+impl PoolStats {
+    pub fn of<DB: Database>(name: &'static str, pool: &sqlx::Pool<DB>) -> Self {
+        let max_connections = pool.options().get_max_connections();
+        let connections = pool.size();
+        let idle = pool.num_idle() as u32;
+        Self {
+            name,
+            max_connections,
+            connections,
+            idle,
+            saturation: connections.saturating_sub(idle) as f64 / max_connections.max(1) as f64,
         }
     }
 }
@@ -0,0 +1,2458 @@
+//! SQLite storage backend, for running the indexer and APIs without a
+//! PostgreSQL server. Schema lives in `migrations/sqlite/`.
+//!
+//! SQLite has no NUMERIC type, arrays or data-modifying CTEs, so BRC-20
+//! amounts are decimal strings added up in Rust, witnesses are JSON, and
+//! statements that Postgres chains through `RETURNING` run one after another
+//! in a transaction.
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use bitcoin::{consensus::encode::serialize, Block, Network, Transaction};
+use chrono::{DateTime, Utc};
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    FromRow, SqliteConnection, SqlitePool,
+};
+use std::{collections::HashMap, str::FromStr, time::Duration};
+use tracing::{debug, error, info, warn};
+
+use crate::db::migrate::{self, MigrationStatus};
+use crate::db::models::{
+    AddressStatsModel, AddressTransactionModel, BlockModel, Brc20BalanceModel, Brc20Event,
+    Brc20EventModel, Brc20TokenModel, Brc20TransferModel, BridgeEventData, BridgeEventModel,
+    BridgeEventStatus, BridgeEventTransitionModel, ChainStatsModel, Hash256, HeaderRelayStatus,
+    HeaderRelaySubmissionModel, HexBytes, InputModel, InscriptionData, InscriptionLocation,
+    InscriptionModel, InscriptionTransferModel, OutputModel, PoolStats, RunesData,
+    RunesTransactionModel, ScriptHistoryModel, TimeseriesBucketModel, TransactionDetail,
+    TransactionModel, UtxoModel,
+};
+use crate::db::store::{InscriptionFilter, RetentionPolicy, Storage, INDEXING_RATE_WINDOW};
+use crate::indexer::brc20::{format_amount, parse_amount};
+use crate::utils::script::electrum_scripthash;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+const INSCRIPTION_COLUMNS: &str = "inscription_id, number, txid, input_index, block_height, \
+     content_type, content_encoding, metaprotocol, content_length, content_hash, location_txid, \
+     location_vout, location_offset, address, timestamp, indexed_at";
+
+/// Whether transaction `t` carries data retention policies never prune
+const PROTOCOL_TRANSACTION: &str = "\
+     EXISTS (SELECT 1 FROM runes_transactions r WHERE r.txid = t.txid) \
+     OR EXISTS (SELECT 1 FROM inscriptions i WHERE i.txid = t.txid) \
+     OR EXISTS (SELECT 1 FROM inscription_transfers x WHERE x.txid = t.txid) \
+     OR EXISTS (SELECT 1 FROM brc20_events e WHERE e.txid = t.txid) \
+     OR EXISTS (SELECT 1 FROM bridge_events b WHERE b.txid = t.txid)";
+
+/// BRC-20 amounts have at most 18 decimals, so they are summed at that scale
+const AMOUNT_DECIMALS: u8 = 18;
+
+/// How long a statement waits for the database lock held by another process
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `inputs` row with the witness stack stored as a JSON array of hex items,
+/// SQLite having no array type
+#[derive(FromRow)]
+struct InputRow {
+    txid: Hash256,
+    vin: i32,
+    prev_txid: Option<Hash256>,
+    prev_vout: Option<i32>,
+    script_sig: HexBytes,
+    #[sqlx(json)]
+    witness: Vec<HexBytes>,
+    sequence: i64,
+    value: Option<i64>,
+    address: Option<String>,
+}
+
+impl From<InputRow> for InputModel {
+    fn from(row: InputRow) -> Self {
+        Self {
+            txid: row.txid,
+            vin: row.vin,
+            prev_txid: row.prev_txid,
+            prev_vout: row.prev_vout,
+            script_sig: row.script_sig,
+            witness: row.witness,
+            sequence: row.sequence,
+            value: row.value,
+            address: row.address,
+        }
+    }
+}
+
+pub struct SqliteDatabase {
+    /// Readers; in WAL mode they run alongside the writer
+    pool: SqlitePool,
+    /// A single connection, so concurrent writers queue instead of failing
+    /// with `SQLITE_BUSY`
+    writer: SqlitePool,
+    /// Whether both are the one connection of an in-memory database
+    shared: bool,
+}
+
+impl SqliteDatabase {
+    pub async fn new(database_url: &str, read_connections: u32) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(BUSY_TIMEOUT)
+            .foreign_keys(true);
+
+        // Each connection to `:memory:` opens its own empty database, so an
+        // in-memory database lives on one connection that is never recycled
+        if database_url.contains(":memory:") {
+            let pool = SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+                .connect_with(options)
+                .await?;
+
+            return Ok(Self {
+                writer: pool.clone(),
+                pool,
+                shared: true,
+            });
+        }
+
+        let writer = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options.clone())
+            .await?;
+        let pool = SqlitePoolOptions::new()
+            .max_connections(read_connections)
+            .connect_with(options.read_only(true))
+            .await?;
+
+        Ok(Self {
+            pool,
+            writer,
+            shared: false,
+        })
+    }
+}
+
+fn block_time(header: &bitcoin::block::Header) -> DateTime<Utc> {
+    DateTime::<Utc>::from_timestamp(header.time as i64, 0).unwrap_or_else(Utc::now)
+}
+
+#[async_trait]
+impl Storage for SqliteDatabase {
+    fn pool_stats(&self) -> Vec<PoolStats> {
+        if self.shared {
+            return vec![PoolStats::of("shared", &self.pool)];
+        }
+        vec![
+            PoolStats::of("write", &self.writer),
+            PoolStats::of("read", &self.pool),
+        ]
+    }
+
+    async fn run_migrations(&self) -> Result<()> {
+        MIGRATOR.run(&self.writer).await?;
+        info!("Database migrations completed");
+        Ok(())
+    }
+
+    async fn migration_status(&self) -> Result<Vec<MigrationStatus>> {
+        let mut conn = self.writer.acquire().await?;
+        let tracked = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master \
+             WHERE type = 'table' AND name = '_sqlx_migrations')",
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        let (applied, dirty) = match tracked {
+            true => (
+                conn.list_applied_migrations().await?,
+                conn.dirty_version().await?,
+            ),
+            false => (Vec::new(), None),
+        };
+
+        Ok(migrate::compare(&MIGRATOR, applied, dirty))
+    }
+
+    async fn baseline_migrations(&self, _version: i64) -> Result<u64> {
+        bail!("SQLite databases are only ever created by the embedded migrations")
+    }
+
+    async fn enable_timescaledb(&self) -> Result<()> {
+        bail!("TimescaleDB requires a PostgreSQL database")
+    }
+
+    async fn get_last_block_height(&self) -> Result<Option<u64>> {
+        let result = sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(height) FROM blocks")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(result.map(|h| h as u64))
+    }
+
+    async fn insert_block(
+        &self,
+        block: &Block,
+        height: u64,
+        chainwork: &[u8],
+        median_time: Option<u64>,
+    ) -> Result<()> {
+        let header = &block.header;
+        // weight = 3 * stripped size + total size
+        let stripped_size = (block.weight().to_wu() as usize - block.total_size()) / 3;
+        let median_time = median_time.and_then(|t| DateTime::<Utc>::from_timestamp(t as i64, 0));
+
+        sqlx::query(
+            r#"
+            INSERT INTO blocks
+            (height, hash, prev_hash, timestamp, merkle_root, header, version, bits, nonce,
+             difficulty, chainwork, median_time, tx_count, size, stripped_size, weight)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            ON CONFLICT (height) DO NOTHING
+            "#,
+        )
+        .bind(height as i64)
+        .bind(Hash256::from(header.block_hash()))
+        .bind(Hash256::from(header.prev_blockhash))
+        .bind(block_time(header))
+        .bind(Hash256::from(header.merkle_root))
+        .bind(serialize(header))
+        .bind(header.version.to_consensus())
+        .bind(header.bits.to_consensus() as i64)
+        .bind(header.nonce as i64)
+        .bind(header.difficulty_float())
+        .bind(format!("{:0>64}", hex::encode(chainwork)))
+        .bind(median_time)
+        .bind(block.txdata.len() as i32)
+        .bind(block.total_size() as i32)
+        .bind(stripped_size as i32)
+        .bind(block.weight().to_wu() as i32)
+        .execute(&self.writer)
+        .await?;
+
+        debug!("Inserted block at height {}", height);
+        Ok(())
+    }
+
+    async fn insert_transaction(
+        &self,
+        tx: &Transaction,
+        tx_index: usize,
+        block_height: u64,
+        block_header: &bitcoin::block::Header,
+        fee: Option<i64>,
+        prevouts: &[Option<OutputModel>],
+        network: Network,
+    ) -> Result<()> {
+        let txid = Hash256::from(tx.txid());
+        let mut db_tx = self.writer.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO transactions
+            (txid, block_height, block_hash, version, locktime, size, weight, fee, timestamp,
+             tx_index, output_count)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (txid) DO NOTHING
+            "#,
+        )
+        .bind(txid)
+        .bind(block_height as i64)
+        .bind(Hash256::from(block_header.block_hash()))
+        .bind(tx.version.0)
+        .bind(tx.lock_time.to_consensus_u32() as i64)
+        .bind(tx.total_size() as i32)
+        .bind(tx.weight().to_wu() as i32)
+        .bind(fee)
+        .bind(block_time(block_header))
+        .bind(tx_index as i32)
+        .bind(tx.output.len() as i32)
+        .execute(&mut *db_tx)
+        .await?;
+
+        for (vin, input) in tx.input.iter().enumerate() {
+            let prevout = prevouts.get(vin).and_then(Option::as_ref);
+            let (prev_txid, prev_vout) = match tx.is_coinbase() {
+                true => (None, None),
+                false => (
+                    Some(Hash256::from(input.previous_output.txid)),
+                    Some(input.previous_output.vout as i32),
+                ),
+            };
+            let witness: Vec<String> = input.witness.iter().map(hex::encode).collect();
+
+            sqlx::query(
+                r#"
+                INSERT INTO inputs
+                (txid, vin, prev_txid, prev_vout, script_sig, witness, sequence, value, address)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT (txid, vin) DO NOTHING
+                "#,
+            )
+            .bind(txid)
+            .bind(vin as i32)
+            .bind(prev_txid)
+            .bind(prev_vout)
+            .bind(input.script_sig.as_bytes())
+            .bind(sqlx::types::Json(witness))
+            .bind(input.sequence.0 as i64)
+            .bind(prevout.map(|o| o.value))
+            .bind(prevout.and_then(|o| o.address.clone()))
+            .execute(&mut *db_tx)
+            .await?;
+        }
+
+        // Per-address (received, sent, funded outputs, spent outputs)
+        let mut flows: HashMap<String, (i64, i64, i32, i32)> = HashMap::new();
+        for prevout in prevouts.iter().flatten() {
+            if let Some(address) = &prevout.address {
+                let flow = flows.entry(address.clone()).or_default();
+                flow.1 += prevout.value;
+                flow.3 += 1;
+            }
+        }
+
+        for (vout, output) in tx.output.iter().enumerate() {
+            let address = bitcoin::Address::from_script(&output.script_pubkey, network)
+                .ok()
+                .map(|a| a.to_string());
+            if let Some(address) = &address {
+                let flow = flows.entry(address.clone()).or_default();
+                flow.0 += output.value.to_sat() as i64;
+                flow.2 += 1;
+            }
+
+            sqlx::query(
+                r#"
+                INSERT INTO outputs
+                (txid, vout, value, script_pubkey, address, spent, scripthash)
+                VALUES ($1, $2, $3, $4, $5, FALSE, $6)
+                ON CONFLICT (txid, vout) DO NOTHING
+                "#,
+            )
+            .bind(txid)
+            .bind(vout as i32)
+            .bind(output.value.to_sat() as i64)
+            .bind(output.script_pubkey.as_bytes())
+            .bind(address)
+            .bind(electrum_scripthash(&output.script_pubkey))
+            .execute(&mut *db_tx)
+            .await?;
+        }
+
+        for (address, (received, sent, funded, spent)) in flows {
+            // Stats only move when the flow row is new, so re-indexing is harmless
+            let inserted = sqlx::query(
+                r#"
+                INSERT INTO address_transactions
+                (address, txid, block_height, tx_index, received, sent, funded_outputs,
+                 spent_outputs)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (address, txid) DO NOTHING
+                "#,
+            )
+            .bind(&address)
+            .bind(txid)
+            .bind(block_height as i64)
+            .bind(tx_index as i32)
+            .bind(received)
+            .bind(sent)
+            .bind(funded)
+            .bind(spent)
+            .execute(&mut *db_tx)
+            .await?
+            .rows_affected();
+            if inserted == 0 {
+                continue;
+            }
+
+            sqlx::query(
+                r#"
+                INSERT INTO address_stats
+                (address, received, sent, tx_count, funded_txo_count, spent_txo_count,
+                 first_seen_height, last_seen_height)
+                VALUES ($1, $2, $3, 1, $4, $5, $6, $6)
+                ON CONFLICT (address) DO UPDATE SET
+                    received = address_stats.received + excluded.received,
+                    sent = address_stats.sent + excluded.sent,
+                    tx_count = address_stats.tx_count + 1,
+                    funded_txo_count = address_stats.funded_txo_count
+                        + excluded.funded_txo_count,
+                    spent_txo_count = address_stats.spent_txo_count + excluded.spent_txo_count,
+                    first_seen_height = MIN(address_stats.first_seen_height,
+                                            excluded.first_seen_height),
+                    last_seen_height = MAX(address_stats.last_seen_height,
+                                           excluded.last_seen_height)
+                "#,
+            )
+            .bind(&address)
+            .bind(received)
+            .bind(sent)
+            .bind(funded)
+            .bind(spent)
+            .bind(block_height as i64)
+            .execute(&mut *db_tx)
+            .await?;
+        }
+
+        db_tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_output(&self, txid: Hash256, vout: u32) -> Result<Option<OutputModel>> {
+        let output =
+            sqlx::query_as::<_, OutputModel>("SELECT * FROM outputs WHERE txid = $1 AND vout = $2")
+                .bind(txid)
+                .bind(vout as i32)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(output)
+    }
+
+    async fn mark_output_spent(
+        &self,
+        txid: Hash256,
+        vout: u32,
+        spending_txid: Hash256,
+        spending_vin: u32,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE outputs
+            SET spent = TRUE, spending_txid = $3, spending_vin = $4
+            WHERE txid = $1 AND vout = $2
+            "#,
+        )
+        .bind(txid)
+        .bind(vout as i32)
+        .bind(spending_txid)
+        .bind(spending_vin as i32)
+        .execute(&self.writer)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_unspent_outputs(
+        &self,
+        after: Option<(Hash256, i32)>,
+        limit: i64,
+    ) -> Result<Vec<OutputModel>> {
+        let (txid, vout) = after.unzip();
+        let outputs = sqlx::query_as::<_, OutputModel>(
+            r#"
+            SELECT * FROM outputs
+            WHERE NOT spent AND ($1 IS NULL OR (txid, vout) > ($1, $2))
+            ORDER BY txid, vout
+            LIMIT $3
+            "#,
+        )
+        .bind(txid)
+        .bind(vout)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(outputs)
+    }
+
+    async fn insert_runes_transaction(
+        &self,
+        runes_data: &RunesData,
+        tx: &Transaction,
+        block_height: u64,
+    ) -> Result<()> {
+        let timestamp = Utc::now(); // Should get from block
+
+        sqlx::query(
+            r#"
+            INSERT INTO runes_transactions
+            (txid, block_height, rune_id, operation, amount, from_address, to_address, metadata,
+             timestamp)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(Hash256::from(tx.txid()))
+        .bind(block_height as i64)
+        .bind(&runes_data.rune_id)
+        .bind(runes_data.operation.to_string())
+        .bind(runes_data.amount.map(|a| a.to_string()))
+        .bind(&runes_data.from_address)
+        .bind(&runes_data.to_address)
+        .bind(&runes_data.metadata)
+        .bind(timestamp)
+        .execute(&self.writer)
+        .await?;
+
+        info!("Inserted Runes transaction: {}", tx.txid());
+        Ok(())
+    }
+
+    async fn insert_inscription(
+        &self,
+        inscription: &InscriptionData,
+        content_hash: &str,
+        store_content: bool,
+        tx: &Transaction,
+        block_height: u64,
+        block_header: &bitcoin::block::Header,
+    ) -> Result<()> {
+        // `WHERE TRUE` keeps SQLite from reading ON CONFLICT as a join constraint
+        sqlx::query(
+            r#"
+            INSERT INTO inscriptions
+            (inscription_id, number, txid, input_index, block_height, content_type,
+             content_encoding, metaprotocol, content_length, content_hash, content,
+             location_txid, location_vout, location_offset, address, timestamp)
+            SELECT $1, COALESCE(MAX(number), -1) + 1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+                   $11, $12, $13, $14, $15
+            FROM inscriptions WHERE TRUE
+            ON CONFLICT (inscription_id) DO NOTHING
+            "#,
+        )
+        .bind(&inscription.inscription_id)
+        .bind(Hash256::from(tx.txid()))
+        .bind(inscription.input_index as i32)
+        .bind(block_height as i64)
+        .bind(&inscription.content_type)
+        .bind(&inscription.content_encoding)
+        .bind(&inscription.metaprotocol)
+        .bind(inscription.content.len() as i64)
+        .bind(content_hash)
+        .bind(store_content.then_some(inscription.content.as_slice()))
+        .bind(inscription.location.as_ref().map(|l| l.txid))
+        .bind(inscription.location.as_ref().map(|l| l.vout as i32))
+        .bind(inscription.location.as_ref().map(|l| l.offset as i64))
+        .bind(
+            inscription
+                .location
+                .as_ref()
+                .and_then(|l| l.address.clone()),
+        )
+        .bind(block_time(block_header))
+        .execute(&self.writer)
+        .await?;
+
+        info!("Inserted inscription: {}", inscription.inscription_id);
+        Ok(())
+    }
+
+    async fn insert_inscription_transfer(
+        &self,
+        inscription_id: &str,
+        from: Option<&InscriptionLocation>,
+        to: Option<&InscriptionLocation>,
+        txid: Hash256,
+        block_height: u64,
+        block_header: &bitcoin::block::Header,
+    ) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO inscription_transfers
+            (inscription_id, txid, block_height, from_txid, from_vout, from_offset,
+             to_txid, to_vout, to_offset, to_address, timestamp)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            "#,
+        )
+        .bind(inscription_id)
+        .bind(txid)
+        .bind(block_height as i64)
+        .bind(from.map(|l| l.txid))
+        .bind(from.map(|l| l.vout as i32))
+        .bind(from.map(|l| l.offset as i64))
+        .bind(to.map(|l| l.txid))
+        .bind(to.map(|l| l.vout as i32))
+        .bind(to.map(|l| l.offset as i64))
+        .bind(to.and_then(|l| l.address.clone()))
+        .bind(block_time(block_header))
+        .execute(&mut *db_tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE inscriptions
+            SET location_txid = $2, location_vout = $3, location_offset = $4, address = $5
+            WHERE inscription_id = $1
+            "#,
+        )
+        .bind(inscription_id)
+        .bind(to.map(|l| l.txid))
+        .bind(to.map(|l| l.vout as i32))
+        .bind(to.map(|l| l.offset as i64))
+        .bind(to.and_then(|l| l.address.clone()))
+        .execute(&mut *db_tx)
+        .await?;
+
+        db_tx.commit().await?;
+
+        debug!("Transferred inscription {} in {}", inscription_id, txid);
+        Ok(())
+    }
+
+    async fn insert_brc20_token(&self, token: &Brc20TokenModel, event: &Brc20Event) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO brc20_tokens
+            (tick, inscription_id, max_supply, mint_limit, decimals, deployer, block_height,
+             timestamp)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (tick) DO NOTHING
+            "#,
+        )
+        .bind(&token.tick)
+        .bind(&token.inscription_id)
+        .bind(&token.max_supply)
+        .bind(&token.mint_limit)
+        .bind(token.decimals)
+        .bind(&token.deployer)
+        .bind(token.block_height)
+        .bind(token.timestamp)
+        .execute(&mut *db_tx)
+        .await?;
+
+        insert_brc20_event(&mut db_tx, event).await?;
+        db_tx.commit().await?;
+
+        info!("Deployed BRC-20 token: {}", token.tick);
+        Ok(())
+    }
+
+    async fn apply_brc20_mint(&self, event: &Brc20Event) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        adjust_brc20_minted(&mut db_tx, &event.tick, &event.amount, 1).await?;
+        credit_brc20_available(&mut db_tx, event.to_address.as_deref(), event).await?;
+        insert_brc20_event(&mut db_tx, event).await?;
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn apply_brc20_inscribe_transfer(&self, event: &Brc20Event) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        adjust_brc20_balance(
+            &mut db_tx,
+            event.from_address.as_deref(),
+            &event.tick,
+            &event.amount,
+            -1,
+            1,
+        )
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO brc20_transfers (inscription_id, tick, amount, from_address)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(&event.inscription_id)
+        .bind(&event.tick)
+        .bind(&event.amount)
+        .bind(&event.from_address)
+        .execute(&mut *db_tx)
+        .await?;
+
+        insert_brc20_event(&mut db_tx, event).await?;
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn apply_brc20_transfer(&self, event: &Brc20Event) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        adjust_brc20_balance(
+            &mut db_tx,
+            event.from_address.as_deref(),
+            &event.tick,
+            &event.amount,
+            0,
+            -1,
+        )
+        .await?;
+
+        // Nobody is credited for a transfer burned to an output without an address
+        let receiver = event.to_address.as_deref();
+        if receiver.is_some() {
+            credit_brc20_available(&mut db_tx, receiver, event).await?;
+        }
+
+        sqlx::query(
+            "UPDATE brc20_transfers SET used = TRUE, to_address = $2 WHERE inscription_id = $1",
+        )
+        .bind(&event.inscription_id)
+        .bind(receiver)
+        .execute(&mut *db_tx)
+        .await?;
+
+        insert_brc20_event(&mut db_tx, event).await?;
+        db_tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn insert_bridge_event(
+        &self,
+        event: &BridgeEventData,
+        block_height: u64,
+        block_header: &bitcoin::block::Header,
+    ) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        // Events orphaned by a reorg may be re-detected on the new chain
+        let id = sqlx::query_scalar::<_, i32>(
+            r#"
+            INSERT INTO bridge_events
+            (event_type, txid, vout, block_height, starknet_tx_hash, bitcoin_address,
+             starknet_address, amount, asset, status, metadata, timestamp)
+            SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, 'pending', $10, $11
+            WHERE NOT EXISTS (
+                SELECT 1 FROM bridge_events
+                WHERE event_type = $1 AND txid = $2 AND vout = $3 AND status <> 'orphaned'
+            )
+            RETURNING id
+            "#,
+        )
+        .bind(&event.event_type)
+        .bind(event.txid)
+        .bind(event.vout.map(|v| v as i32))
+        .bind(block_height as i64)
+        .bind(&event.starknet_tx_hash)
+        .bind(&event.bitcoin_address)
+        .bind(&event.starknet_address)
+        .bind(event.amount)
+        .bind(&event.asset)
+        .bind(&event.metadata)
+        .bind(block_time(block_header))
+        .fetch_optional(&mut *db_tx)
+        .await?;
+
+        if let Some(id) = id {
+            insert_bridge_transition(
+                &mut db_tx,
+                id,
+                None,
+                BridgeEventStatus::Pending,
+                Some(block_height as i64),
+                None,
+            )
+            .await?;
+        }
+        db_tx.commit().await?;
+
+        info!(
+            "Inserted bridge {} event: {:?}",
+            event.event_type, event.txid
+        );
+        Ok(())
+    }
+
+    async fn insert_bridge_withdrawal(&self, event: &BridgeEventData) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        let id = sqlx::query_scalar::<_, i32>(
+            r#"
+            INSERT INTO bridge_events
+            (event_type, starknet_tx_hash, starknet_event_index, bitcoin_address,
+             starknet_address, amount, asset, status, metadata, timestamp)
+            SELECT $1, $2, $3, $4, $5, $6, $7, 'pending', $8, $9
+            WHERE NOT EXISTS (
+                SELECT 1 FROM bridge_events
+                WHERE event_type = $1 AND starknet_tx_hash = $2 AND starknet_event_index = $3
+            )
+            RETURNING id
+            "#,
+        )
+        .bind(&event.event_type)
+        .bind(&event.starknet_tx_hash)
+        .bind(event.starknet_event_index.map(|i| i as i32))
+        .bind(&event.bitcoin_address)
+        .bind(&event.starknet_address)
+        .bind(event.amount)
+        .bind(&event.asset)
+        .bind(&event.metadata)
+        .bind(Utc::now())
+        .fetch_optional(&mut *db_tx)
+        .await?;
+
+        if let Some(id) = id {
+            insert_bridge_transition(&mut db_tx, id, None, BridgeEventStatus::Pending, None, None)
+                .await?;
+        }
+        db_tx.commit().await?;
+
+        if id.is_some() {
+            info!("Inserted bridge withdrawal: {:?}", event.starknet_tx_hash);
+        }
+        Ok(())
+    }
+
+    async fn get_bridge_withdrawals(
+        &self,
+        starknet_tx_hash: &str,
+    ) -> Result<Vec<BridgeEventModel>> {
+        let events = sqlx::query_as::<_, BridgeEventModel>(
+            r#"
+            SELECT * FROM bridge_events
+            WHERE event_type = 'withdrawal' AND starknet_tx_hash = $1
+            ORDER BY starknet_event_index
+            "#,
+        )
+        .bind(starknet_tx_hash)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    async fn match_bridge_withdrawal(
+        &self,
+        id: i32,
+        txid: Hash256,
+        vout: Option<u32>,
+        block_height: u64,
+        status: BridgeEventStatus,
+        reason: &str,
+    ) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        let prev = sqlx::query_scalar::<_, Option<String>>(
+            "SELECT status FROM bridge_events WHERE id = $1 AND txid IS NULL",
+        )
+        .bind(id)
+        .fetch_optional(&mut *db_tx)
+        .await?;
+
+        if let Some(prev) = prev {
+            sqlx::query(
+                r#"
+                UPDATE bridge_events SET txid = $2, vout = $3, block_height = $4, status = $5
+                WHERE id = $1
+                "#,
+            )
+            .bind(id)
+            .bind(txid)
+            .bind(vout.map(|v| v as i32))
+            .bind(block_height as i64)
+            .bind(status.to_string())
+            .execute(&mut *db_tx)
+            .await?;
+
+            insert_bridge_transition(
+                &mut db_tx,
+                id,
+                prev.as_deref(),
+                status,
+                Some(block_height as i64),
+                Some(reason),
+            )
+            .await?;
+
+            // A payout matched after its block's stats were recorded is added to them
+            let amount = sqlx::query_scalar::<_, Option<i64>>(
+                "SELECT amount FROM bridge_events WHERE id = $1 AND asset = 'BTC'",
+            )
+            .bind(id)
+            .fetch_optional(&mut *db_tx)
+            .await?
+            .flatten();
+            if let Some(amount) = amount {
+                let counted = sqlx::query(
+                    r#"
+                    UPDATE block_stats
+                    SET bridge_withdrawal_volume = bridge_withdrawal_volume + $2
+                    WHERE height = $1
+                    "#,
+                )
+                .bind(block_height as i64)
+                .bind(amount)
+                .execute(&mut *db_tx)
+                .await?
+                .rows_affected();
+                if counted > 0 {
+                    sqlx::query(
+                        "UPDATE chain_stats \
+                         SET bridge_withdrawal_volume = bridge_withdrawal_volume + $1",
+                    )
+                    .bind(amount)
+                    .execute(&mut *db_tx)
+                    .await?;
+                }
+            }
+        }
+        db_tx.commit().await?;
+
+        info!("Withdrawal {} {}: {}", id, status, reason);
+        Ok(())
+    }
+
+    async fn flag_unmatched_withdrawals(&self, timeout_secs: u64) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        let flagged = sqlx::query_scalar::<_, i32>(
+            r#"
+            UPDATE bridge_events SET status = 'unmatched'
+            WHERE event_type = 'withdrawal' AND status = 'pending' AND txid IS NULL
+              AND julianday(timestamp) < julianday('now') - $1 / 86400.0
+            RETURNING id
+            "#,
+        )
+        .bind(timeout_secs as f64)
+        .fetch_all(&mut *db_tx)
+        .await?;
+
+        for id in &flagged {
+            insert_bridge_transition(
+                &mut db_tx,
+                *id,
+                Some("pending"),
+                BridgeEventStatus::Unmatched,
+                None,
+                Some("no payout within timeout"),
+            )
+            .await?;
+        }
+        db_tx.commit().await?;
+
+        if !flagged.is_empty() {
+            warn!("Flagged {} unmatched withdrawal(s)", flagged.len());
+        }
+        Ok(())
+    }
+
+    async fn get_sync_cursor(&self, name: &str) -> Result<Option<u64>> {
+        let block =
+            sqlx::query_scalar::<_, i64>("SELECT block_number FROM sync_cursors WHERE name = $1")
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(block.map(|b| b as u64))
+    }
+
+    async fn set_sync_cursor(&self, name: &str, block_number: u64) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO sync_cursors (name, block_number, updated_at) VALUES ($1, $2, $3)
+            ON CONFLICT (name) DO UPDATE
+            SET block_number = excluded.block_number, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(name)
+        .bind(block_number as i64)
+        .bind(Utc::now())
+        .execute(&self.writer)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_blocks_range(&self, from: u64, to: u64) -> Result<Vec<BlockModel>> {
+        let blocks = sqlx::query_as::<_, BlockModel>(
+            "SELECT * FROM blocks WHERE height BETWEEN $1 AND $2 ORDER BY height",
+        )
+        .bind(from as i64)
+        .bind(to as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(blocks)
+    }
+
+    async fn get_block_hashes(&self, from: u64, to: u64) -> Result<Vec<Hash256>> {
+        let hashes = sqlx::query_scalar::<_, Hash256>(
+            "SELECT hash FROM blocks WHERE height BETWEEN $1 AND $2 ORDER BY height",
+        )
+        .bind(from as i64)
+        .bind(to as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(hashes)
+    }
+
+    async fn get_last_relayed_height(&self) -> Result<Option<u64>> {
+        let height =
+            sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(height) FROM relayed_headers")
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(height.map(|h| h as u64))
+    }
+
+    async fn get_in_flight_header_submission(&self) -> Result<Option<HeaderRelaySubmissionModel>> {
+        let submission = sqlx::query_as::<_, HeaderRelaySubmissionModel>(
+            "SELECT * FROM header_relay_submissions WHERE status = $1 ORDER BY id LIMIT 1",
+        )
+        .bind(HeaderRelayStatus::Submitted.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(submission)
+    }
+
+    async fn insert_header_submission(
+        &self,
+        headers: &[(u64, Hash256)],
+        starknet_tx_hash: &str,
+    ) -> Result<i32> {
+        let (Some((from, _)), Some((to, _))) = (headers.first(), headers.last()) else {
+            bail!("Empty header submission");
+        };
+
+        let mut db_tx = self.writer.begin().await?;
+
+        let id = sqlx::query_scalar::<_, i32>(
+            r#"
+            INSERT INTO header_relay_submissions (from_height, to_height, starknet_tx_hash, status)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(*from as i64)
+        .bind(*to as i64)
+        .bind(starknet_tx_hash)
+        .bind(HeaderRelayStatus::Submitted.to_string())
+        .fetch_one(&mut *db_tx)
+        .await?;
+
+        for (height, hash) in headers {
+            sqlx::query(
+                r#"
+                INSERT INTO relayed_headers (height, hash, submission_id) VALUES ($1, $2, $3)
+                ON CONFLICT (height) DO UPDATE
+                SET hash = excluded.hash, submission_id = excluded.submission_id
+                "#,
+            )
+            .bind(*height as i64)
+            .bind(hash)
+            .bind(id)
+            .execute(&mut *db_tx)
+            .await?;
+        }
+
+        db_tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn insert_failed_header_submission(
+        &self,
+        from: u64,
+        to: u64,
+        error: &str,
+    ) -> Result<i32> {
+        let id = sqlx::query_scalar::<_, i32>(
+            r#"
+            INSERT INTO header_relay_submissions (from_height, to_height, status, error)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(from as i64)
+        .bind(to as i64)
+        .bind(HeaderRelayStatus::Failed.to_string())
+        .bind(error)
+        .fetch_one(&self.writer)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn get_last_settled_header_submission(
+        &self,
+        from: u64,
+    ) -> Result<Option<HeaderRelaySubmissionModel>> {
+        let submission = sqlx::query_as::<_, HeaderRelaySubmissionModel>(
+            r#"
+            SELECT * FROM header_relay_submissions
+            WHERE from_height = $1 AND status <> $2
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(from as i64)
+        .bind(HeaderRelayStatus::Submitted.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(submission)
+    }
+
+    async fn schedule_header_relay_retry(
+        &self,
+        id: i32,
+        attempt: u32,
+        retry_after: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE header_relay_submissions
+            SET attempt = $2, retry_after = $3, updated_at = $4
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(attempt as i32)
+        .bind(retry_after)
+        .bind(Utc::now())
+        .execute(&self.writer)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_header_submission(
+        &self,
+        id: i32,
+        status: HeaderRelayStatus,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE header_relay_submissions
+            SET status = $2, error = COALESCE($3, error), updated_at = $4
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(status.to_string())
+        .bind(error)
+        .bind(Utc::now())
+        .execute(&mut *db_tx)
+        .await?;
+
+        if status != HeaderRelayStatus::Accepted {
+            sqlx::query("DELETE FROM relayed_headers WHERE submission_id = $1")
+                .bind(id)
+                .execute(&mut *db_tx)
+                .await?;
+        }
+
+        db_tx.commit().await?;
+        Ok(())
+    }
+
+    async fn rollback_header_relay(&self) -> Result<Option<u64>> {
+        let mut db_tx = self.writer.begin().await?;
+
+        // Heights above the indexed tip are compared once they are reindexed
+        let fork = sqlx::query_scalar::<_, Option<i64>>(
+            r#"
+            SELECT MIN(r.height)
+            FROM relayed_headers r
+            LEFT JOIN blocks b ON b.height = r.height
+            WHERE r.height <= (SELECT MAX(height) FROM blocks)
+              AND b.hash IS DISTINCT FROM r.hash
+            "#,
+        )
+        .fetch_one(&mut *db_tx)
+        .await?;
+
+        let Some(fork) = fork else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE header_relay_submissions
+            SET status = $2, updated_at = $3
+            WHERE id IN (SELECT DISTINCT submission_id FROM relayed_headers WHERE height >= $1)
+            "#,
+        )
+        .bind(fork)
+        .bind(HeaderRelayStatus::Orphaned.to_string())
+        .bind(Utc::now())
+        .execute(&mut *db_tx)
+        .await?;
+
+        sqlx::query("DELETE FROM relayed_headers WHERE height >= $1")
+            .bind(fork)
+            .execute(&mut *db_tx)
+            .await?;
+
+        db_tx.commit().await?;
+        Ok(Some(fork as u64))
+    }
+
+    async fn update_bridge_event_statuses(
+        &self,
+        tip_height: u64,
+        confirmations: u64,
+        finality_confirmations: u64,
+    ) -> Result<()> {
+        // An event mined at height h has tip - h + 1 confirmations
+        let steps = [
+            (
+                BridgeEventStatus::Pending,
+                BridgeEventStatus::Confirmed,
+                confirmations,
+            ),
+            (
+                BridgeEventStatus::Confirmed,
+                BridgeEventStatus::Finalized,
+                finality_confirmations,
+            ),
+        ];
+
+        for (from, to, depth) in steps {
+            let mut db_tx = self.writer.begin().await?;
+
+            let updated = sqlx::query_scalar::<_, i32>(
+                r#"
+                UPDATE bridge_events SET status = $2
+                WHERE status = $1 AND block_height IS NOT NULL AND $3 - block_height + 1 >= $4
+                RETURNING id
+                "#,
+            )
+            .bind(from.to_string())
+            .bind(to.to_string())
+            .bind(tip_height as i64)
+            .bind(depth as i64)
+            .fetch_all(&mut *db_tx)
+            .await?;
+
+            let reason = format!("{} confirmations", depth);
+            for id in &updated {
+                insert_bridge_transition(
+                    &mut db_tx,
+                    *id,
+                    Some(&from.to_string()),
+                    to,
+                    Some(tip_height as i64),
+                    Some(&reason),
+                )
+                .await?;
+            }
+            db_tx.commit().await?;
+
+            if !updated.is_empty() {
+                info!("Marked {} bridge event(s) {}", updated.len(), to);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn insert_block_stats(&self, height: u64) -> Result<()> {
+        let mut db_tx = self.writer.begin().await?;
+
+        let inserted = sqlx::query(
+            r#"
+            WITH block_txs AS (
+                SELECT txid FROM transactions WHERE block_height = $1
+            ),
+            created AS (
+                SELECT COUNT(*) AS n, COALESCE(SUM(o.value), 0) AS value
+                FROM outputs o JOIN block_txs t ON t.txid = o.txid
+            ),
+            spent AS (
+                SELECT COUNT(*) AS n, COALESCE(SUM(i.value), 0) AS value
+                FROM inputs i JOIN block_txs t ON t.txid = i.txid
+                WHERE i.value IS NOT NULL
+            ),
+            runes AS (
+                SELECT COUNT(*) AS n,
+                       COUNT(*) FILTER (WHERE operation = 'etch') AS etched,
+                       COUNT(*) FILTER (WHERE operation = 'mint') AS minted,
+                       COUNT(*) FILTER (WHERE operation = 'burn') AS burned
+                FROM runes_transactions WHERE block_height = $1
+            ),
+            bridge AS (
+                SELECT
+                    COALESCE(SUM(amount) FILTER (WHERE event_type = 'deposit'), 0) AS deposits,
+                    COALESCE(SUM(amount) FILTER (WHERE event_type = 'withdrawal'), 0)
+                        AS withdrawals
+                FROM bridge_events
+                WHERE block_height = $1 AND status <> 'orphaned' AND asset = 'BTC'
+            ),
+            -- Fees are what the coinbase claims above the subsidy
+            coinbase AS (
+                SELECT MAX(COALESCE(SUM(o.value), 0) - (5000000000 >> MIN($1 / 210000, 63)), 0)
+                           AS fees
+                FROM outputs o JOIN transactions t ON t.txid = o.txid
+                WHERE t.block_height = $1 AND t.tx_index = 0
+            )
+            INSERT INTO block_stats
+            (height, tx_count, output_count, output_value, spent_count, spent_value,
+             runes_transactions, runes_etched, runes_minted, runes_burned,
+             bridge_deposit_volume, bridge_withdrawal_volume, fees)
+            SELECT $1, (SELECT COUNT(*) FROM block_txs), created.n, created.value,
+                   spent.n, spent.value, runes.n, runes.etched, runes.minted,
+                   runes.burned, bridge.deposits, bridge.withdrawals, coinbase.fees
+            FROM created, spent, runes, bridge, coinbase WHERE TRUE
+            ON CONFLICT (height) DO NOTHING
+            "#,
+        )
+        .bind(height as i64)
+        .execute(&mut *db_tx)
+        .await?
+        .rows_affected();
+
+        if inserted > 0 {
+            sqlx::query(
+                r#"
+                UPDATE chain_stats AS c SET
+                    blocks = c.blocks + 1,
+                    transactions = c.transactions + s.tx_count,
+                    outputs = c.outputs + s.output_count,
+                    utxo_count = c.utxo_count + s.output_count - s.spent_count,
+                    utxo_value = c.utxo_value + s.output_value - s.spent_value,
+                    runes_transactions = c.runes_transactions + s.runes_transactions,
+                    runes_etched = c.runes_etched + s.runes_etched,
+                    runes_minted = c.runes_minted + s.runes_minted,
+                    runes_burned = c.runes_burned + s.runes_burned,
+                    bridge_deposit_volume = c.bridge_deposit_volume + s.bridge_deposit_volume,
+                    bridge_withdrawal_volume =
+                        c.bridge_withdrawal_volume + s.bridge_withdrawal_volume
+                FROM (SELECT * FROM block_stats WHERE height = $1) AS s
+                "#,
+            )
+            .bind(height as i64)
+            .execute(&mut *db_tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO timeseries_buckets AS t
+                (resolution, bucket, blocks, tx_count, fees, runes_mints, block_size)
+                SELECT r.resolution, strftime(r.format, b.timestamp), 1, s.tx_count, s.fees,
+                       s.runes_minted, COALESCE(b.size, 0)
+                FROM block_stats s
+                JOIN blocks b ON b.height = s.height
+                CROSS JOIN (SELECT '1h' AS resolution, '%Y-%m-%dT%H:00:00Z' AS format
+                            UNION ALL SELECT '1d', '%Y-%m-%dT00:00:00Z') AS r
+                WHERE s.height = $1
+                ON CONFLICT (resolution, bucket) DO UPDATE SET
+                    blocks = t.blocks + 1,
+                    tx_count = t.tx_count + excluded.tx_count,
+                    fees = t.fees + excluded.fees,
+                    runes_mints = t.runes_mints + excluded.runes_mints,
+                    block_size = t.block_size + excluded.block_size
+                "#,
+            )
+            .bind(height as i64)
+            .execute(&mut *db_tx)
+            .await?;
+        }
+
+        db_tx.commit().await?;
+        Ok(())
+    }
+
+    async fn has_block_stats(&self, height: u64) -> Result<bool> {
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM block_stats WHERE height = $1)",
+        )
+        .bind(height as i64)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(exists)
+    }
+
+    async fn set_node_height(&self, height: u64) -> Result<()> {
+        sqlx::query("UPDATE chain_stats SET node_height = $1")
+            .bind(height as i64)
+            .execute(&self.writer)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_chain_stats(&self) -> Result<ChainStatsModel> {
+        let stats = sqlx::query_as::<_, ChainStatsModel>(
+            r#"
+            WITH tip AS (
+                SELECT height, indexed_at FROM block_stats ORDER BY height DESC LIMIT 1
+            )
+            SELECT c.blocks, c.transactions, c.outputs, c.utxo_count, c.utxo_value,
+                   c.runes_transactions, c.runes_etched, c.runes_minted, c.runes_burned,
+                   c.bridge_deposit_volume, c.bridge_withdrawal_volume, c.node_height,
+                   (SELECT height FROM tip) AS tip_height,
+                   (SELECT (tip.height - w.height)
+                           / ((julianday(tip.indexed_at) - julianday(w.indexed_at)) * 86400.0)
+                    FROM tip JOIN block_stats w ON w.height = tip.height - $1) AS blocks_per_sec
+            FROM chain_stats c
+            "#,
+        )
+        .bind(INDEXING_RATE_WINDOW)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(stats)
+    }
+
+    async fn get_timeseries(
+        &self,
+        resolution: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<TimeseriesBucketModel>> {
+        let buckets = sqlx::query_as::<_, TimeseriesBucketModel>(
+            r#"
+            SELECT bucket, blocks, tx_count, fees, runes_mints, block_size
+            FROM timeseries_buckets
+            WHERE resolution = $1
+              AND bucket BETWEEN strftime('%Y-%m-%dT%H:%M:%SZ', $2)
+                             AND strftime('%Y-%m-%dT%H:%M:%SZ', $3)
+            ORDER BY bucket
+            "#,
+        )
+        .bind(resolution)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(buckets)
+    }
+
+    async fn rollback_from(&self, height: u64) -> Result<()> {
+        let height = height as i64;
+        let mut db_tx = self.writer.begin().await?;
+
+        // Undo BRC-20 operations newest first
+        let events = sqlx::query_as::<_, Brc20EventModel>(
+            r#"
+            SELECT id, inscription_id, operation, tick, amount, from_address, to_address, txid,
+                   block_height, timestamp
+            FROM brc20_events WHERE block_height >= $1 ORDER BY id DESC
+            "#,
+        )
+        .bind(height)
+        .fetch_all(&mut *db_tx)
+        .await?;
+
+        for event in events {
+            let Brc20EventModel {
+                operation,
+                tick,
+                amount,
+                from_address: from,
+                to_address: to,
+                inscription_id,
+                ..
+            } = event;
+            match operation.as_str() {
+                "deploy" => {
+                    for table in ["brc20_transfers", "brc20_balances", "brc20_tokens"] {
+                        sqlx::query(&format!("DELETE FROM {} WHERE tick = $1", table))
+                            .bind(&tick)
+                            .execute(&mut *db_tx)
+                            .await?;
+                    }
+                }
+                "mint" => {
+                    adjust_brc20_minted(&mut db_tx, &tick, &amount, -1).await?;
+                    adjust_brc20_balance(&mut db_tx, to.as_deref(), &tick, &amount, -1, 0).await?;
+                }
+                "inscribe_transfer" => {
+                    adjust_brc20_balance(&mut db_tx, from.as_deref(), &tick, &amount, 1, -1)
+                        .await?;
+                    sqlx::query("DELETE FROM brc20_transfers WHERE inscription_id = $1")
+                        .bind(&inscription_id)
+                        .execute(&mut *db_tx)
+                        .await?;
+                }
+                "transfer" => {
+                    adjust_brc20_balance(&mut db_tx, to.as_deref(), &tick, &amount, -1, 0).await?;
+                    adjust_brc20_balance(&mut db_tx, from.as_deref(), &tick, &amount, 0, 1).await?;
+                    sqlx::query(
+                        "UPDATE brc20_transfers SET used = FALSE, to_address = NULL \
+                         WHERE inscription_id = $1",
+                    )
+                    .bind(&inscription_id)
+                    .execute(&mut *db_tx)
+                    .await?;
+                }
+                _ => {}
+            }
+        }
+
+        // Finality should have ruled this out, and the deposits may already
+        // have been credited on Starknet
+        let finalized = sqlx::query_scalar::<_, i32>(
+            "SELECT id FROM bridge_events \
+             WHERE block_height >= $1 AND status = 'finalized' AND event_type <> 'withdrawal' \
+             ORDER BY id",
+        )
+        .bind(height)
+        .fetch_all(&mut *db_tx)
+        .await?;
+        if !finalized.is_empty() {
+            error!(
+                "Reorg from height {} orphans finalized bridge deposits {:?}",
+                height, finalized
+            );
+        }
+
+        let statements = [
+            "DELETE FROM brc20_events WHERE block_height >= $1",
+            r#"
+            UPDATE chain_stats AS c SET
+                blocks = c.blocks - s.blocks,
+                transactions = c.transactions - s.tx_count,
+                outputs = c.outputs - s.output_count,
+                utxo_count = c.utxo_count - s.output_count + s.spent_count,
+                utxo_value = c.utxo_value - s.output_value + s.spent_value,
+                runes_transactions = c.runes_transactions - s.runes_transactions,
+                runes_etched = c.runes_etched - s.runes_etched,
+                runes_minted = c.runes_minted - s.runes_minted,
+                runes_burned = c.runes_burned - s.runes_burned,
+                bridge_deposit_volume = c.bridge_deposit_volume - s.bridge_deposit_volume,
+                bridge_withdrawal_volume = c.bridge_withdrawal_volume - s.bridge_withdrawal_volume
+            FROM (
+                SELECT COUNT(*) AS blocks,
+                       COALESCE(SUM(tx_count), 0) AS tx_count,
+                       COALESCE(SUM(output_count), 0) AS output_count,
+                       COALESCE(SUM(output_value), 0) AS output_value,
+                       COALESCE(SUM(spent_count), 0) AS spent_count,
+                       COALESCE(SUM(spent_value), 0) AS spent_value,
+                       COALESCE(SUM(runes_transactions), 0) AS runes_transactions,
+                       COALESCE(SUM(runes_etched), 0) AS runes_etched,
+                       COALESCE(SUM(runes_minted), 0) AS runes_minted,
+                       COALESCE(SUM(runes_burned), 0) AS runes_burned,
+                       COALESCE(SUM(bridge_deposit_volume), 0) AS bridge_deposit_volume,
+                       COALESCE(SUM(bridge_withdrawal_volume), 0) AS bridge_withdrawal_volume
+                FROM block_stats WHERE height >= $1
+            ) AS s
+            "#,
+            r#"
+            UPDATE timeseries_buckets AS t SET
+                blocks = t.blocks - f.blocks,
+                tx_count = t.tx_count - f.tx_count,
+                fees = t.fees - f.fees,
+                runes_mints = t.runes_mints - f.runes_mints,
+                block_size = t.block_size - f.block_size
+            FROM (
+                SELECT r.resolution, strftime(r.format, b.timestamp) AS bucket,
+                       COUNT(*) AS blocks, SUM(s.tx_count) AS tx_count, SUM(s.fees) AS fees,
+                       SUM(s.runes_minted) AS runes_mints,
+                       COALESCE(SUM(b.size), 0) AS block_size
+                FROM block_stats s
+                JOIN blocks b ON b.height = s.height
+                CROSS JOIN (SELECT '1h' AS resolution, '%Y-%m-%dT%H:00:00Z' AS format
+                            UNION ALL SELECT '1d', '%Y-%m-%dT00:00:00Z') AS r
+                WHERE s.height >= $1
+                GROUP BY 1, 2
+            ) AS f
+            WHERE t.resolution = f.resolution AND t.bucket = f.bucket
+            "#,
+            "DELETE FROM timeseries_buckets WHERE blocks <= 0",
+            "DELETE FROM block_stats WHERE height >= $1",
+            // Return moved inscriptions to where they were before the first undone transfer
+            r#"
+            UPDATE inscriptions AS i
+            SET location_txid = t.from_txid, location_vout = t.from_vout,
+                location_offset = t.from_offset,
+                address = (SELECT address FROM outputs o
+                           WHERE o.txid = t.from_txid AND o.vout = t.from_vout)
+            FROM (
+                SELECT * FROM inscription_transfers
+                WHERE id IN (SELECT MIN(id) FROM inscription_transfers
+                             WHERE block_height >= $1 GROUP BY inscription_id)
+            ) AS t
+            WHERE i.inscription_id = t.inscription_id
+            "#,
+            "DELETE FROM inscription_transfers WHERE block_height >= $1",
+            "DELETE FROM inscriptions WHERE block_height >= $1",
+            r#"
+            UPDATE outputs SET spent = FALSE, spending_txid = NULL, spending_vin = NULL
+            WHERE spending_txid IN (SELECT txid FROM transactions WHERE block_height >= $1)
+            "#,
+            "DELETE FROM pruned_outputs WHERE spending_txid IN \
+             (SELECT txid FROM transactions WHERE block_height >= $1)",
+            "DELETE FROM outputs WHERE txid IN \
+             (SELECT txid FROM transactions WHERE block_height >= $1)",
+            "DELETE FROM inputs WHERE txid IN \
+             (SELECT txid FROM transactions WHERE block_height >= $1)",
+            r#"
+            UPDATE address_stats AS s SET
+                received = s.received - f.received,
+                sent = s.sent - f.sent,
+                tx_count = s.tx_count - f.tx_count,
+                funded_txo_count = s.funded_txo_count - f.funded,
+                spent_txo_count = s.spent_txo_count - f.spent,
+                last_seen_height = (SELECT MAX(block_height) FROM address_transactions a
+                                    WHERE a.address = s.address AND a.block_height < $1)
+            FROM (
+                SELECT address, SUM(received) AS received, SUM(sent) AS sent,
+                       COUNT(*) AS tx_count, SUM(funded_outputs) AS funded,
+                       SUM(spent_outputs) AS spent
+                FROM address_transactions WHERE block_height >= $1
+                GROUP BY address
+            ) AS f
+            WHERE s.address = f.address
+            "#,
+            "DELETE FROM address_stats WHERE tx_count <= 0",
+            "DELETE FROM address_transactions WHERE block_height >= $1",
+            "DELETE FROM runes_transactions WHERE block_height >= $1",
+            // Transitions are recorded before the update so they see the old status
+            r#"
+            INSERT INTO bridge_event_transitions
+            (event_id, from_status, to_status, block_height, reason)
+            SELECT id, status, 'orphaned', $1,
+                   CASE WHEN status = 'finalized' THEN 'reorg after finality' ELSE 'reorg' END
+            FROM bridge_events
+            WHERE block_height >= $1 AND status <> 'orphaned' AND event_type <> 'withdrawal'
+            "#,
+            r#"
+            UPDATE bridge_events SET status = 'orphaned'
+            WHERE block_height >= $1 AND status <> 'orphaned' AND event_type <> 'withdrawal'
+            "#,
+            // Withdrawals stay valid on Starknet; only their payout match is undone
+            r#"
+            INSERT INTO bridge_event_transitions
+            (event_id, from_status, to_status, block_height, reason)
+            SELECT id, status, 'pending', $1, 'payout reorged out' FROM bridge_events
+            WHERE block_height >= $1 AND event_type = 'withdrawal'
+            "#,
+            r#"
+            UPDATE bridge_events
+            SET status = 'pending', txid = NULL, vout = NULL, block_height = NULL
+            WHERE block_height >= $1 AND event_type = 'withdrawal'
+            "#,
+            "DELETE FROM transactions WHERE block_height >= $1",
+            "DELETE FROM blocks WHERE height >= $1",
+            // Re-indexed blocks are pruned again
+            "UPDATE sync_cursors SET block_number = $1 - 1 \
+             WHERE name LIKE 'retention_%' AND block_number >= $1",
+        ];
+
+        for statement in statements {
+            sqlx::query(statement)
+                .bind(height)
+                .execute(&mut *db_tx)
+                .await?;
+        }
+
+        db_tx.commit().await?;
+
+        info!("Rolled back indexed data from height {}", height);
+        Ok(())
+    }
+
+    async fn prune(&self, policy: RetentionPolicy, from: u64, to: u64) -> Result<u64> {
+        let mut db_tx = self.writer.begin().await?;
+
+        let removed = match policy {
+            RetentionPolicy::SpentOutputs => {
+                // Flag the creating transactions left without outputs while the outputs
+                // still point at them; the others keep answering with what remains
+                sqlx::query(&format!(
+                    r#"
+                    UPDATE transactions AS t SET pruned = TRUE
+                    WHERE t.txid IN (
+                        SELECT o.txid FROM outputs o
+                        JOIN transactions s ON s.txid = o.spending_txid
+                        WHERE s.block_height BETWEEN $1 AND $2
+                    )
+                    AND NOT EXISTS (
+                        SELECT 1 FROM outputs o
+                        LEFT JOIN transactions s ON s.txid = o.spending_txid
+                        WHERE o.txid = t.txid AND (s.block_height IS NULL OR s.block_height > $2)
+                    )
+                    AND NOT ({})
+                    "#,
+                    PROTOCOL_TRANSACTION
+                ))
+                .bind(from as i64)
+                .bind(to as i64)
+                .execute(&mut *db_tx)
+                .await?;
+
+                // Electrum histories still list the pruned outputs' funding and spending
+                sqlx::query(&format!(
+                    r#"
+                    INSERT INTO pruned_outputs (txid, vout, scripthash, spending_txid)
+                    SELECT o.txid, o.vout, o.scripthash, o.spending_txid FROM outputs o
+                    WHERE o.spending_txid IN
+                          (SELECT txid FROM transactions WHERE block_height BETWEEN $1 AND $2)
+                      AND EXISTS (SELECT 1 FROM transactions t
+                                  WHERE t.txid = o.txid AND NOT ({}))
+                    ON CONFLICT (txid, vout) DO NOTHING
+                    "#,
+                    PROTOCOL_TRANSACTION
+                ))
+                .bind(from as i64)
+                .bind(to as i64)
+                .execute(&mut *db_tx)
+                .await?;
+
+                sqlx::query(&format!(
+                    r#"
+                    DELETE FROM outputs AS o
+                    WHERE o.spending_txid IN
+                          (SELECT txid FROM transactions WHERE block_height BETWEEN $1 AND $2)
+                      AND EXISTS (SELECT 1 FROM transactions t
+                                  WHERE t.txid = o.txid AND NOT ({}))
+                    "#,
+                    PROTOCOL_TRANSACTION
+                ))
+                .bind(from as i64)
+                .bind(to as i64)
+                .execute(&mut *db_tx)
+                .await?
+                .rows_affected()
+            }
+            RetentionPolicy::Transactions => {
+                sqlx::query(&format!(
+                    r#"
+                    UPDATE transactions AS t SET pruned = TRUE
+                    WHERE t.block_height BETWEEN $1 AND $2 AND NOT ({})
+                    "#,
+                    PROTOCOL_TRANSACTION
+                ))
+                .bind(from as i64)
+                .bind(to as i64)
+                .execute(&mut *db_tx)
+                .await?;
+
+                sqlx::query(
+                    r#"
+                    DELETE FROM inputs WHERE txid IN (
+                        SELECT txid FROM transactions
+                        WHERE block_height BETWEEN $1 AND $2 AND pruned
+                    )
+                    "#,
+                )
+                .bind(from as i64)
+                .bind(to as i64)
+                .execute(&mut *db_tx)
+                .await?
+                .rows_affected()
+            }
+            RetentionPolicy::AddressHistory => {
+                sqlx::query("DELETE FROM address_transactions WHERE block_height BETWEEN $1 AND $2")
+                    .bind(from as i64)
+                    .bind(to as i64)
+                    .execute(&mut *db_tx)
+                    .await?
+                    .rows_affected()
+            }
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO sync_cursors (name, block_number, updated_at) VALUES ($1, $2, $3)
+            ON CONFLICT (name) DO UPDATE
+            SET block_number = excluded.block_number, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(policy.cursor())
+        .bind(to as i64)
+        .bind(Utc::now())
+        .execute(&mut *db_tx)
+        .await?;
+
+        db_tx.commit().await?;
+
+        Ok(removed)
+    }
+
+    async fn get_block_by_height(&self, height: u64) -> Result<Option<BlockModel>> {
+        let block = sqlx::query_as::<_, BlockModel>("SELECT * FROM blocks WHERE height = $1")
+            .bind(height as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(block)
+    }
+
+    async fn get_block_by_hash(&self, hash: Hash256) -> Result<Option<BlockModel>> {
+        let block = sqlx::query_as::<_, BlockModel>("SELECT * FROM blocks WHERE hash = $1")
+            .bind(hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(block)
+    }
+
+    async fn get_tip_block(&self) -> Result<Option<BlockModel>> {
+        let block =
+            sqlx::query_as::<_, BlockModel>("SELECT * FROM blocks ORDER BY height DESC LIMIT 1")
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(block)
+    }
+
+    async fn get_blocks(
+        &self,
+        from: Option<u64>,
+        to: Option<u64>,
+        after: Option<u64>,
+        descending: bool,
+        limit: i64,
+    ) -> Result<Vec<BlockModel>> {
+        let blocks = sqlx::query_as::<_, BlockModel>(&format!(
+            r#"
+            SELECT * FROM blocks
+            WHERE ($1 IS NULL OR height >= $1)
+              AND ($2 IS NULL OR height <= $2)
+              AND ($3 IS NULL OR height {} $3)
+            ORDER BY height {}
+            LIMIT $4
+            "#,
+            if descending { "<" } else { ">" },
+            if descending { "DESC" } else { "ASC" },
+        ))
+        .bind(from.map(|h| h as i64))
+        .bind(to.map(|h| h as i64))
+        .bind(after.map(|h| h as i64))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(blocks)
+    }
+
+    async fn get_block_txids(&self, height: u64) -> Result<Vec<Hash256>> {
+        let txids = sqlx::query_scalar::<_, Hash256>(
+            r#"
+            SELECT txid FROM transactions
+            WHERE block_height = $1 AND tx_index IS NOT NULL
+            ORDER BY tx_index
+            "#,
+        )
+        .bind(height as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(txids)
+    }
+
+    async fn get_transaction(&self, txid: Hash256) -> Result<Option<TransactionModel>> {
+        let tx =
+            sqlx::query_as::<_, TransactionModel>("SELECT * FROM transactions WHERE txid = $1")
+                .bind(txid)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(tx)
+    }
+
+    async fn get_address_stats(&self, address: &str) -> Result<Option<AddressStatsModel>> {
+        let stats = sqlx::query_as::<_, AddressStatsModel>(
+            "SELECT * FROM address_stats WHERE address = $1",
+        )
+        .bind(address)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(stats)
+    }
+
+    async fn get_address_transactions(
+        &self,
+        address: &str,
+        after: Option<(i64, i32)>,
+        limit: i64,
+    ) -> Result<Vec<AddressTransactionModel>> {
+        let transactions = sqlx::query_as::<_, AddressTransactionModel>(
+            r#"
+            SELECT * FROM address_transactions
+            WHERE address = $1
+              AND ($2 IS NULL OR (block_height, tx_index) < ($2, $3))
+            ORDER BY block_height DESC, tx_index DESC
+            LIMIT $4
+            "#,
+        )
+        .bind(address)
+        .bind(after.map(|(height, _)| height))
+        .bind(after.map(|(_, index)| index))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transactions)
+    }
+
+    async fn get_address_utxos(
+        &self,
+        address: &str,
+        after: Option<(i64, Hash256, i32)>,
+        limit: i64,
+    ) -> Result<Vec<UtxoModel>> {
+        let (height, txid, vout) = match after {
+            Some((height, txid, vout)) => (Some(height), Some(txid), Some(vout)),
+            None => (None, None, None),
+        };
+
+        let utxos = sqlx::query_as::<_, UtxoModel>(
+            r#"
+            SELECT o.txid, o.vout, o.value, o.script_pubkey, o.address, t.block_height,
+                   t.block_hash, t.timestamp AS block_time
+            FROM outputs o
+            JOIN transactions t ON t.txid = o.txid
+            WHERE o.address = $1 AND NOT o.spent
+              AND ($2 IS NULL OR (t.block_height, o.txid, o.vout) < ($2, $3, $4))
+            ORDER BY t.block_height DESC, o.txid DESC, o.vout DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(address)
+        .bind(height)
+        .bind(txid)
+        .bind(vout)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(utxos)
+    }
+
+    async fn get_scripthash_history(&self, scripthash: Hash256) -> Result<Vec<ScriptHistoryModel>> {
+        let history = sqlx::query_as::<_, ScriptHistoryModel>(
+            r#"
+            SELECT t.txid, t.block_height FROM transactions t
+            WHERE t.txid IN (
+                SELECT txid FROM outputs WHERE scripthash = $1
+                UNION
+                SELECT spending_txid FROM outputs
+                WHERE scripthash = $1 AND spending_txid IS NOT NULL
+                UNION
+                SELECT txid FROM pruned_outputs WHERE scripthash = $1
+                UNION
+                SELECT spending_txid FROM pruned_outputs WHERE scripthash = $1
+            )
+            ORDER BY t.block_height, t.tx_index
+            "#,
+        )
+        .bind(scripthash)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(history)
+    }
+
+    async fn get_scripthash_utxos(&self, scripthash: Hash256) -> Result<Vec<UtxoModel>> {
+        let utxos = sqlx::query_as::<_, UtxoModel>(
+            r#"
+            SELECT o.txid, o.vout, o.value, o.script_pubkey, o.address, t.block_height,
+                   t.block_hash, t.timestamp AS block_time
+            FROM outputs o
+            JOIN transactions t ON t.txid = o.txid
+            WHERE o.scripthash = $1 AND NOT o.spent
+            ORDER BY t.block_height, t.tx_index, o.vout
+            "#,
+        )
+        .bind(scripthash)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(utxos)
+    }
+
+    async fn get_scripthash_balance(&self, scripthash: Hash256) -> Result<i64> {
+        let balance = sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT SUM(value) FROM outputs WHERE scripthash = $1 AND NOT spent",
+        )
+        .bind(scripthash)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(balance.unwrap_or_default())
+    }
+
+    async fn get_transaction_prevouts(&self, txid: Hash256) -> Result<Vec<OutputModel>> {
+        let prevouts = sqlx::query_as::<_, OutputModel>(
+            r#"
+            SELECT o.* FROM inputs i
+            JOIN outputs o ON o.txid = i.prev_txid AND o.vout = i.prev_vout
+            WHERE i.txid = $1
+            "#,
+        )
+        .bind(txid)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(prevouts)
+    }
+
+    async fn get_transaction_detail(&self, txid: Hash256) -> Result<Option<TransactionDetail>> {
+        let Some(transaction) = self.get_transaction(txid).await? else {
+            return Ok(None);
+        };
+
+        let inputs =
+            sqlx::query_as::<_, InputRow>("SELECT * FROM inputs WHERE txid = $1 ORDER BY vin")
+                .bind(txid)
+                .fetch_all(&self.pool);
+        let outputs =
+            sqlx::query_as::<_, OutputModel>("SELECT * FROM outputs WHERE txid = $1 ORDER BY vout")
+                .bind(txid)
+                .fetch_all(&self.pool);
+        let runes = sqlx::query_as::<_, RunesTransactionModel>(
+            "SELECT * FROM runes_transactions WHERE txid = $1 ORDER BY id",
+        )
+        .bind(txid)
+        .fetch_all(&self.pool);
+        let bridge_events = sqlx::query_as::<_, BridgeEventModel>(
+            "SELECT * FROM bridge_events WHERE txid = $1 ORDER BY id",
+        )
+        .bind(txid)
+        .fetch_all(&self.pool);
+        let tip = sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(height) FROM blocks")
+            .fetch_one(&self.pool);
+
+        let (inputs, outputs, runes, bridge_events, tip) =
+            tokio::try_join!(inputs, outputs, runes, bridge_events, tip)?;
+
+        Ok(Some(TransactionDetail::new(
+            transaction,
+            tip,
+            inputs.into_iter().map(InputModel::from).collect(),
+            outputs,
+            runes,
+            bridge_events,
+        )))
+    }
+
+    async fn get_runes_transactions(
+        &self,
+        after: Option<(i64, i32)>,
+        limit: i64,
+    ) -> Result<Vec<RunesTransactionModel>> {
+        let txs = sqlx::query_as::<_, RunesTransactionModel>(
+            r#"
+            SELECT * FROM runes_transactions
+            WHERE ($1 IS NULL OR (block_height, id) < ($1, $2))
+            ORDER BY block_height DESC, id DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(after.map(|(height, _)| height))
+        .bind(after.map(|(_, id)| id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(txs)
+    }
+
+    async fn get_inscription(&self, inscription_id: &str) -> Result<Option<InscriptionModel>> {
+        let inscription = sqlx::query_as::<_, InscriptionModel>(&format!(
+            "SELECT {} FROM inscriptions WHERE inscription_id = $1",
+            INSCRIPTION_COLUMNS
+        ))
+        .bind(inscription_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(inscription)
+    }
+
+    async fn get_inscription_content(&self, inscription_id: &str) -> Result<Option<Vec<u8>>> {
+        let content = sqlx::query_scalar::<_, Option<Vec<u8>>>(
+            "SELECT content FROM inscriptions WHERE inscription_id = $1",
+        )
+        .bind(inscription_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(content.flatten())
+    }
+
+    async fn get_inscriptions(
+        &self,
+        filter: &InscriptionFilter,
+        after: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<InscriptionModel>> {
+        let inscriptions = sqlx::query_as::<_, InscriptionModel>(&format!(
+            r#"
+            SELECT {} FROM inscriptions
+            WHERE ($1 IS NULL OR content_type = $1)
+              AND ($2 IS NULL OR block_height >= $2)
+              AND ($3 IS NULL OR block_height <= $3)
+              AND ($4 IS NULL OR number < $4)
+            ORDER BY number DESC
+            LIMIT $5
+            "#,
+            INSCRIPTION_COLUMNS
+        ))
+        .bind(&filter.content_type)
+        .bind(filter.from_height.map(|h| h as i64))
+        .bind(filter.to_height.map(|h| h as i64))
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(inscriptions)
+    }
+
+    async fn get_inscriptions_at_output(
+        &self,
+        txid: Hash256,
+        vout: u32,
+    ) -> Result<Vec<InscriptionModel>> {
+        let inscriptions = sqlx::query_as::<_, InscriptionModel>(&format!(
+            "SELECT {} FROM inscriptions WHERE location_txid = $1 AND location_vout = $2 \
+             ORDER BY location_offset",
+            INSCRIPTION_COLUMNS
+        ))
+        .bind(txid)
+        .bind(vout as i32)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(inscriptions)
+    }
+
+    async fn get_inscription_transfers(
+        &self,
+        inscription_id: &str,
+    ) -> Result<Vec<InscriptionTransferModel>> {
+        let transfers = sqlx::query_as::<_, InscriptionTransferModel>(
+            "SELECT * FROM inscription_transfers WHERE inscription_id = $1 ORDER BY id",
+        )
+        .bind(inscription_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transfers)
+    }
+
+    async fn get_inscriptions_by_address(
+        &self,
+        address: &str,
+        after: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<InscriptionModel>> {
+        let inscriptions = sqlx::query_as::<_, InscriptionModel>(&format!(
+            "SELECT {} FROM inscriptions WHERE address = $1 \
+             AND ($2 IS NULL OR number < $2) ORDER BY number DESC LIMIT $3",
+            INSCRIPTION_COLUMNS
+        ))
+        .bind(address)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(inscriptions)
+    }
+
+    async fn get_brc20_token(&self, tick: &str) -> Result<Option<Brc20TokenModel>> {
+        let token =
+            sqlx::query_as::<_, Brc20TokenModel>("SELECT * FROM brc20_tokens WHERE tick = $1")
+                .bind(tick)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(token)
+    }
+
+    async fn get_brc20_tokens(
+        &self,
+        after: Option<(i64, String)>,
+        limit: i64,
+    ) -> Result<Vec<Brc20TokenModel>> {
+        let (height, tick) = after.unzip();
+        let tokens = sqlx::query_as::<_, Brc20TokenModel>(
+            r#"
+            SELECT * FROM brc20_tokens
+            WHERE ($1 IS NULL OR (block_height, tick) > ($1, $2))
+            ORDER BY block_height, tick
+            LIMIT $3
+            "#,
+        )
+        .bind(height)
+        .bind(tick)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    async fn get_brc20_balance(
+        &self,
+        address: &str,
+        tick: &str,
+    ) -> Result<Option<Brc20BalanceModel>> {
+        let balance = sqlx::query_as::<_, Brc20BalanceModel>(
+            "SELECT * FROM brc20_balances WHERE address = $1 AND tick = $2",
+        )
+        .bind(address)
+        .bind(tick)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(balance)
+    }
+
+    async fn get_brc20_balances(&self, address: &str) -> Result<Vec<Brc20BalanceModel>> {
+        let balances = sqlx::query_as::<_, Brc20BalanceModel>(
+            "SELECT * FROM brc20_balances WHERE address = $1 ORDER BY tick",
+        )
+        .bind(address)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(balances)
+    }
+
+    async fn get_brc20_transfer(&self, inscription_id: &str) -> Result<Option<Brc20TransferModel>> {
+        let transfer = sqlx::query_as::<_, Brc20TransferModel>(
+            "SELECT * FROM brc20_transfers WHERE inscription_id = $1",
+        )
+        .bind(inscription_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(transfer)
+    }
+
+    async fn get_bridge_event(&self, id: i32) -> Result<Option<BridgeEventModel>> {
+        let event =
+            sqlx::query_as::<_, BridgeEventModel>("SELECT * FROM bridge_events WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(event)
+    }
+
+    async fn get_bridge_events(
+        &self,
+        status: Option<&str>,
+        event_type: Option<&str>,
+        after: Option<i32>,
+        limit: i64,
+    ) -> Result<Vec<BridgeEventModel>> {
+        let events = sqlx::query_as::<_, BridgeEventModel>(
+            r#"
+            SELECT * FROM bridge_events
+            WHERE ($1 IS NULL OR status = $1)
+              AND ($2 IS NULL OR event_type = $2)
+              AND ($3 IS NULL OR id < $3)
+            ORDER BY id DESC
+            LIMIT $4
+            "#,
+        )
+        .bind(status)
+        .bind(event_type)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    async fn get_bridge_event_transitions(
+        &self,
+        event_id: i32,
+    ) -> Result<Vec<BridgeEventTransitionModel>> {
+        let transitions = sqlx::query_as::<_, BridgeEventTransitionModel>(
+            "SELECT * FROM bridge_event_transitions WHERE event_id = $1 ORDER BY id",
+        )
+        .bind(event_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(transitions)
+    }
+
+    async fn get_header_submissions(
+        &self,
+        status: Option<&str>,
+        after: Option<i32>,
+        limit: i64,
+    ) -> Result<Vec<HeaderRelaySubmissionModel>> {
+        let submissions = sqlx::query_as::<_, HeaderRelaySubmissionModel>(
+            r#"
+            SELECT * FROM header_relay_submissions
+            WHERE ($1 IS NULL OR status = $1)
+              AND ($2 IS NULL OR id < $2)
+            ORDER BY id DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(status)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(submissions)
+    }
+}
+
+/// Returns `value + sign * delta` for decimal strings; a missing delta is zero.
+fn add_amount(value: &str, delta: &Option<String>, sign: i32) -> Result<String> {
+    let parse = |amount: &str| {
+        parse_amount(amount, AMOUNT_DECIMALS)
+            .ok_or_else(|| anyhow::anyhow!("Invalid BRC-20 amount: {}", amount))
+    };
+    let value = parse(value)?;
+    let delta = match delta {
+        Some(delta) => parse(delta)?,
+        None => 0,
+    };
+
+    let result = match sign {
+        s if s > 0 => value.checked_add(delta),
+        s if s < 0 => value.checked_sub(delta),
+        _ => Some(value),
+    };
+    let Some(result) = result else {
+        bail!("BRC-20 amount out of range");
+    };
+
+    Ok(format_amount(result, AMOUNT_DECIMALS))
+}
+
+async fn adjust_brc20_minted(
+    conn: &mut SqliteConnection,
+    tick: &str,
+    amount: &Option<String>,
+    sign: i32,
+) -> Result<()> {
+    let minted = sqlx::query_scalar::<_, String>("SELECT minted FROM brc20_tokens WHERE tick = $1")
+        .bind(tick)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let Some(minted) = minted else {
+        return Ok(());
+    };
+
+    sqlx::query("UPDATE brc20_tokens SET minted = $2 WHERE tick = $1")
+        .bind(tick)
+        .bind(add_amount(&minted, amount, sign)?)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Adds `sign * amount` to available and transferable balances respectively.
+async fn adjust_brc20_balance(
+    conn: &mut SqliteConnection,
+    address: Option<&str>,
+    tick: &str,
+    amount: &Option<String>,
+    available_sign: i32,
+    transferable_sign: i32,
+) -> Result<()> {
+    let balance = sqlx::query_as::<_, (String, String)>(
+        "SELECT available, transferable FROM brc20_balances WHERE address = $1 AND tick = $2",
+    )
+    .bind(address)
+    .bind(tick)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some((available, transferable)) = balance else {
+        return Ok(());
+    };
+
+    sqlx::query(
+        "UPDATE brc20_balances SET available = $3, transferable = $4 \
+         WHERE address = $1 AND tick = $2",
+    )
+    .bind(address)
+    .bind(tick)
+    .bind(add_amount(&available, amount, available_sign)?)
+    .bind(add_amount(&transferable, amount, transferable_sign)?)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn credit_brc20_available(
+    conn: &mut SqliteConnection,
+    address: Option<&str>,
+    event: &Brc20Event,
+) -> Result<()> {
+    let available = sqlx::query_scalar::<_, String>(
+        "SELECT available FROM brc20_balances WHERE address = $1 AND tick = $2",
+    )
+    .bind(address)
+    .bind(&event.tick)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO brc20_balances (address, tick, available) VALUES ($1, $2, $3)
+        ON CONFLICT (address, tick) DO UPDATE SET available = excluded.available
+        "#,
+    )
+    .bind(address)
+    .bind(&event.tick)
+    .bind(add_amount(
+        available.as_deref().unwrap_or("0"),
+        &event.amount,
+        1,
+    )?)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn insert_brc20_event(conn: &mut SqliteConnection, event: &Brc20Event) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO brc20_events
+        (inscription_id, operation, tick, amount, from_address, to_address, txid,
+         block_height, timestamp)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(&event.inscription_id)
+    .bind(event.operation.to_string())
+    .bind(&event.tick)
+    .bind(&event.amount)
+    .bind(&event.from_address)
+    .bind(&event.to_address)
+    .bind(event.txid)
+    .bind(event.block_height as i64)
+    .bind(event.timestamp)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn insert_bridge_transition(
+    conn: &mut SqliteConnection,
+    event_id: i32,
+    from: Option<&str>,
+    to: BridgeEventStatus,
+    block_height: Option<i64>,
+    reason: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO bridge_event_transitions
+        (event_id, from_status, to_status, block_height, reason)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(event_id)
+    .bind(from)
+    .bind(to.to_string())
+    .bind(block_height)
+    .bind(reason)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
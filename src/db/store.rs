@@ -0,0 +1,440 @@
+//! The storage interface used by the indexer, the Starknet followers and the
+//! APIs. PostgreSQL is the production backend; SQLite runs the same indexer
+//! self-contained for development and CI.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bitcoin::{Block, Network, Transaction};
+use chrono::{DateTime, Utc};
+
+use super::migrate::MigrationStatus;
+use super::models::{
+    AddressStatsModel, AddressTransactionModel, BlockModel, Brc20BalanceModel, Brc20Event,
+    Brc20TokenModel, Brc20TransferModel, BridgeEventData, BridgeEventModel, BridgeEventStatus,
+    BridgeEventTransitionModel, ChainStatsModel, Hash256, HeaderRelayStatus,
+    HeaderRelaySubmissionModel, InscriptionData, InscriptionLocation, InscriptionModel,
+    InscriptionTransferModel, OutputModel, PoolStats, RunesData, RunesTransactionModel,
+    ScriptHistoryModel, TimeseriesBucketModel, TransactionDetail, TransactionModel, UtxoModel,
+};
+
+/// Blocks over which the recent indexing speed is measured
+pub const INDEXING_RATE_WINDOW: i64 = 100;
+
+/// Optional filters for inscription listings
+#[derive(Debug, Default, Clone)]
+pub struct InscriptionFilter {
+    pub content_type: Option<String>,
+    pub from_height: Option<u64>,
+    pub to_height: Option<u64>,
+}
+
+/// What a retention policy prunes once it falls out of its window. Detail of
+/// transactions carrying Runes, inscription, BRC-20 or bridge data is never
+/// pruned, and pruned transactions keep their row so blocks still list every
+/// txid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionPolicy {
+    /// Spent outputs, by the height of the spending transaction
+    SpentOutputs,
+    /// Inputs, with their scripts and witnesses
+    Transactions,
+    /// Per-address history rows; the address totals are kept
+    AddressHistory,
+}
+
+impl RetentionPolicy {
+    pub const ALL: [RetentionPolicy; 3] = [
+        RetentionPolicy::SpentOutputs,
+        RetentionPolicy::Transactions,
+        RetentionPolicy::AddressHistory,
+    ];
+
+    /// Sync cursor holding the height this policy has pruned up to
+    pub fn cursor(&self) -> String {
+        format!("retention_{}", self)
+    }
+}
+
+impl std::fmt::Display for RetentionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            RetentionPolicy::SpentOutputs => "spent_outputs",
+            RetentionPolicy::Transactions => "transactions",
+            RetentionPolicy::AddressHistory => "address_history",
+        };
+        f.write_str(s)
+    }
+}
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    // Connections
+    /// Usage of the connection pools behind this handle.
+    fn pool_stats(&self) -> Vec<PoolStats>;
+
+    // Schema
+    /// Applies pending migrations, verifying the checksums of applied ones.
+    async fn run_migrations(&self) -> Result<()>;
+
+    /// Embedded migrations alongside what the database has recorded.
+    async fn migration_status(&self) -> Result<Vec<MigrationStatus>>;
+
+    /// Records migrations up to `version` as applied without running them, for
+    /// databases whose schema was created by hand.
+    async fn baseline_migrations(&self, version: i64) -> Result<u64>;
+
+    /// Converts the append-only event tables to TimescaleDB hypertables.
+    async fn enable_timescaledb(&self) -> Result<()>;
+
+    // Indexing
+    async fn get_last_block_height(&self) -> Result<Option<u64>>;
+
+    /// Stores a block with its header fields. `chainwork` and `median_time` come
+    /// from the node since they depend on ancestors we may not have indexed.
+    async fn insert_block(
+        &self,
+        block: &Block,
+        height: u64,
+        chainwork: &[u8],
+        median_time: Option<u64>,
+    ) -> Result<()>;
+
+    /// Stores a transaction with its inputs and outputs and folds its flows into
+    /// the per-address stats. Output addresses are encoded for `network`.
+    /// Storing the same transaction again changes nothing.
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_transaction(
+        &self,
+        tx: &Transaction,
+        tx_index: usize,
+        block_height: u64,
+        block_header: &bitcoin::block::Header,
+        fee: Option<i64>,
+        prevouts: &[Option<OutputModel>],
+        network: Network,
+    ) -> Result<()>;
+
+    async fn get_output(&self, txid: Hash256, vout: u32) -> Result<Option<OutputModel>>;
+
+    async fn mark_output_spent(
+        &self,
+        txid: Hash256,
+        vout: u32,
+        spending_txid: Hash256,
+        spending_vin: u32,
+    ) -> Result<()>;
+
+    /// Unspent outputs in `(txid, vout)` order, for rebuilding the UTXO cache.
+    async fn get_unspent_outputs(
+        &self,
+        after: Option<(Hash256, i32)>,
+        limit: i64,
+    ) -> Result<Vec<OutputModel>>;
+
+    async fn insert_runes_transaction(
+        &self,
+        runes_data: &RunesData,
+        tx: &Transaction,
+        block_height: u64,
+    ) -> Result<()>;
+
+    /// Stores an inscription, keeping the body inline unless it lives in the blob store.
+    async fn insert_inscription(
+        &self,
+        inscription: &InscriptionData,
+        content_hash: &str,
+        store_content: bool,
+        tx: &Transaction,
+        block_height: u64,
+        block_header: &bitcoin::block::Header,
+    ) -> Result<()>;
+
+    /// Moves an inscription to its new location and records the transfer.
+    async fn insert_inscription_transfer(
+        &self,
+        inscription_id: &str,
+        from: Option<&InscriptionLocation>,
+        to: Option<&InscriptionLocation>,
+        txid: Hash256,
+        block_height: u64,
+        block_header: &bitcoin::block::Header,
+    ) -> Result<()>;
+
+    async fn insert_brc20_token(&self, token: &Brc20TokenModel, event: &Brc20Event) -> Result<()>;
+
+    /// Credits a mint to the receiver's available balance.
+    async fn apply_brc20_mint(&self, event: &Brc20Event) -> Result<()>;
+
+    /// Moves balance from available to transferable and records the transfer inscription.
+    async fn apply_brc20_inscribe_transfer(&self, event: &Brc20Event) -> Result<()>;
+
+    /// Settles a transfer inscription on its first move, crediting
+    /// `to_address`. Transfers without one are burned.
+    async fn apply_brc20_transfer(&self, event: &Brc20Event) -> Result<()>;
+
+    // Starknet bridge
+    async fn insert_bridge_event(
+        &self,
+        event: &BridgeEventData,
+        block_height: u64,
+        block_header: &bitcoin::block::Header,
+    ) -> Result<()>;
+
+    /// Stores a withdrawal requested on Starknet, ignoring events already ingested.
+    async fn insert_bridge_withdrawal(&self, event: &BridgeEventData) -> Result<()>;
+
+    /// Withdrawals requested by a Starknet transaction, oldest first.
+    async fn get_bridge_withdrawals(&self, starknet_tx_hash: &str)
+        -> Result<Vec<BridgeEventModel>>;
+
+    /// Links a withdrawal to its Bitcoin payout and records the outcome. A
+    /// withdrawal already linked is left as is, since both the indexer and the
+    /// withdrawal monitor may find its payout.
+    async fn match_bridge_withdrawal(
+        &self,
+        id: i32,
+        txid: Hash256,
+        vout: Option<u32>,
+        block_height: u64,
+        status: BridgeEventStatus,
+        reason: &str,
+    ) -> Result<()>;
+
+    /// Flags pending withdrawals that have waited longer than `timeout_secs` for a payout.
+    async fn flag_unmatched_withdrawals(&self, timeout_secs: u64) -> Result<()>;
+
+    // Header relay and external chain followers
+    async fn get_sync_cursor(&self, name: &str) -> Result<Option<u64>>;
+
+    async fn set_sync_cursor(&self, name: &str, block_number: u64) -> Result<()>;
+
+    /// Returns the indexed blocks in `[from, to]`, ascending.
+    async fn get_blocks_range(&self, from: u64, to: u64) -> Result<Vec<BlockModel>>;
+
+    /// Returns the hashes of the indexed blocks in `[from, to]`, ascending.
+    async fn get_block_hashes(&self, from: u64, to: u64) -> Result<Vec<Hash256>>;
+
+    async fn get_last_relayed_height(&self) -> Result<Option<u64>>;
+
+    /// Returns the oldest submission still awaiting a receipt.
+    async fn get_in_flight_header_submission(&self) -> Result<Option<HeaderRelaySubmissionModel>>;
+
+    /// Records a sent submission together with the `(height, hash)` headers it covers.
+    async fn insert_header_submission(
+        &self,
+        headers: &[(u64, Hash256)],
+        starknet_tx_hash: &str,
+    ) -> Result<i32>;
+
+    /// Records a submission that never reached the sequencer.
+    async fn insert_failed_header_submission(&self, from: u64, to: u64, error: &str)
+        -> Result<i32>;
+
+    /// Returns the latest settled submission of the batch starting at `from`.
+    async fn get_last_settled_header_submission(
+        &self,
+        from: u64,
+    ) -> Result<Option<HeaderRelaySubmissionModel>>;
+
+    /// Records a failed submission as the `attempt`-th consecutive failure of
+    /// its batch, which may be sent again from `retry_after`.
+    async fn schedule_header_relay_retry(
+        &self,
+        id: i32,
+        attempt: u32,
+        retry_after: DateTime<Utc>,
+    ) -> Result<()>;
+
+    /// Settles a submission. Headers of a reverted or rejected submission are
+    /// released so the next batch resends them.
+    async fn update_header_submission(
+        &self,
+        id: i32,
+        status: HeaderRelayStatus,
+        error: Option<&str>,
+    ) -> Result<()>;
+
+    /// Releases relayed headers that no longer match the indexed chain, marking
+    /// their submissions orphaned. Returns the fork height, if any.
+    async fn rollback_header_relay(&self) -> Result<Option<u64>>;
+
+    /// Advances bridge events by confirmation depth relative to `tip_height`.
+    async fn update_bridge_event_statuses(
+        &self,
+        tip_height: u64,
+        confirmations: u64,
+        finality_confirmations: u64,
+    ) -> Result<()>;
+
+    // Chain statistics
+    /// Records what a fully indexed block added and folds it into the chain totals.
+    async fn insert_block_stats(&self, height: u64) -> Result<()>;
+
+    /// Whether `height` has its stats recorded, the last step of indexing a
+    /// block. A block row without them was interrupted part way.
+    async fn has_block_stats(&self, height: u64) -> Result<bool>;
+
+    async fn set_node_height(&self, height: u64) -> Result<()>;
+
+    /// Reads the maintained totals, with the indexing speed over the last
+    /// `INDEXING_RATE_WINDOW` blocks.
+    async fn get_chain_stats(&self) -> Result<ChainStatsModel>;
+
+    /// Maintained `1h` or `1d` buckets starting within `from..=to`, oldest
+    /// first. Buckets without blocks are absent.
+    async fn get_timeseries(
+        &self,
+        resolution: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<TimeseriesBucketModel>>;
+
+    // Reorgs
+    /// Removes everything indexed at or above `height`, undoing its effects on
+    /// spent outputs, inscription locations and the BRC-20 ledger. Bridge events
+    /// from those blocks are kept but marked orphaned.
+    async fn rollback_from(&self, height: u64) -> Result<()>;
+
+    // Retention
+    /// Prunes what `policy` drops from blocks `from..=to` and advances its
+    /// cursor to `to`. Returns the rows removed.
+    async fn prune(&self, policy: RetentionPolicy, from: u64, to: u64) -> Result<u64>;
+
+    // Queries
+    async fn get_block_by_height(&self, height: u64) -> Result<Option<BlockModel>>;
+
+    async fn get_block_by_hash(&self, hash: Hash256) -> Result<Option<BlockModel>>;
+
+    async fn get_tip_block(&self) -> Result<Option<BlockModel>>;
+
+    /// Lists blocks within optional height bounds, continuing after the `after`
+    /// height in the requested direction.
+    async fn get_blocks(
+        &self,
+        from: Option<u64>,
+        to: Option<u64>,
+        after: Option<u64>,
+        descending: bool,
+        limit: i64,
+    ) -> Result<Vec<BlockModel>>;
+
+    /// Returns a block's txids in block order.
+    async fn get_block_txids(&self, height: u64) -> Result<Vec<Hash256>>;
+
+    async fn get_transaction(&self, txid: Hash256) -> Result<Option<TransactionModel>>;
+
+    async fn get_address_stats(&self, address: &str) -> Result<Option<AddressStatsModel>>;
+
+    /// Lists an address's transactions newest first, continuing after the
+    /// `(block_height, tx_index)` cursor.
+    async fn get_address_transactions(
+        &self,
+        address: &str,
+        after: Option<(i64, i32)>,
+        limit: i64,
+    ) -> Result<Vec<AddressTransactionModel>>;
+
+    /// Lists an address's unspent outputs newest first, continuing after the
+    /// `(block_height, txid, vout)` cursor.
+    async fn get_address_utxos(
+        &self,
+        address: &str,
+        after: Option<(i64, Hash256, i32)>,
+        limit: i64,
+    ) -> Result<Vec<UtxoModel>>;
+
+    /// Confirmed transactions funding or spending outputs with `scripthash`, in
+    /// block order, including outputs the spent outputs policy pruned.
+    async fn get_scripthash_history(&self, scripthash: Hash256) -> Result<Vec<ScriptHistoryModel>>;
+
+    async fn get_scripthash_utxos(&self, scripthash: Hash256) -> Result<Vec<UtxoModel>>;
+
+    async fn get_scripthash_balance(&self, scripthash: Hash256) -> Result<i64>;
+
+    /// Returns the indexed outputs spent by a transaction's inputs.
+    async fn get_transaction_prevouts(&self, txid: Hash256) -> Result<Vec<OutputModel>>;
+
+    /// Assembles the full view of a transaction for the API.
+    async fn get_transaction_detail(&self, txid: Hash256) -> Result<Option<TransactionDetail>>;
+
+    /// Lists runes operations newest first, continuing after the
+    /// `(block_height, id)` of the last one seen.
+    async fn get_runes_transactions(
+        &self,
+        after: Option<(i64, i32)>,
+        limit: i64,
+    ) -> Result<Vec<RunesTransactionModel>>;
+
+    async fn get_inscription(&self, inscription_id: &str) -> Result<Option<InscriptionModel>>;
+
+    /// Returns the inline body, or `None` if it is kept in the blob store.
+    async fn get_inscription_content(&self, inscription_id: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Lists inscriptions newest first, continuing below the `after` number.
+    async fn get_inscriptions(
+        &self,
+        filter: &InscriptionFilter,
+        after: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<InscriptionModel>>;
+
+    async fn get_inscriptions_at_output(
+        &self,
+        txid: Hash256,
+        vout: u32,
+    ) -> Result<Vec<InscriptionModel>>;
+
+    async fn get_inscription_transfers(
+        &self,
+        inscription_id: &str,
+    ) -> Result<Vec<InscriptionTransferModel>>;
+
+    async fn get_inscriptions_by_address(
+        &self,
+        address: &str,
+        after: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<InscriptionModel>>;
+
+    async fn get_brc20_token(&self, tick: &str) -> Result<Option<Brc20TokenModel>>;
+
+    /// Lists tokens in deploy order, continuing after the `(block_height, tick)`
+    /// of the last one seen.
+    async fn get_brc20_tokens(
+        &self,
+        after: Option<(i64, String)>,
+        limit: i64,
+    ) -> Result<Vec<Brc20TokenModel>>;
+
+    async fn get_brc20_balance(
+        &self,
+        address: &str,
+        tick: &str,
+    ) -> Result<Option<Brc20BalanceModel>>;
+
+    async fn get_brc20_balances(&self, address: &str) -> Result<Vec<Brc20BalanceModel>>;
+
+    async fn get_brc20_transfer(&self, inscription_id: &str) -> Result<Option<Brc20TransferModel>>;
+
+    async fn get_bridge_event(&self, id: i32) -> Result<Option<BridgeEventModel>>;
+
+    async fn get_bridge_events(
+        &self,
+        status: Option<&str>,
+        event_type: Option<&str>,
+        after: Option<i32>,
+        limit: i64,
+    ) -> Result<Vec<BridgeEventModel>>;
+
+    async fn get_bridge_event_transitions(
+        &self,
+        event_id: i32,
+    ) -> Result<Vec<BridgeEventTransitionModel>>;
+
+    async fn get_header_submissions(
+        &self,
+        status: Option<&str>,
+        after: Option<i32>,
+        limit: i64,
+    ) -> Result<Vec<HeaderRelaySubmissionModel>>;
+}
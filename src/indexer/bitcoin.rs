@@ -1,21 +1,61 @@
 use anyhow::Result;
-use bitcoin::{Block, Transaction};
+use bitcoin::{
+    hashes::{sha256, Hash},
+    Block, Network, OutPoint, Transaction, Txid,
+};
 use bitcoincore_rpc::{Auth, Client, RpcApi};
-use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+};
 use tokio::time::{sleep, Duration};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-use crate::{config::Config, db::Database, indexer::runes::RunesProcessor};
+use crate::{
+    config::Config,
+    db::{
+        models::{Hash256, InscriptionLocation, OutputModel},
+        ContentStore, Storage,
+    },
+    indexer::{
+        brc20::Brc20Processor,
+        bridge::{match_withdrawal, BridgeProcessor},
+        inscriptions::{locate_sat, InscriptionProcessor},
+        runes::RunesProcessor,
+        utxo_cache::UtxoCache,
+    },
+};
+
+const INITIAL_SUBSIDY: u64 = 50 * 100_000_000;
+const HALVING_INTERVAL: u64 = 210_000;
+
+/// An inscription whose sat was spent as fee, to be claimed by the coinbase
+struct FeeInscription {
+    inscription_id: String,
+    txid: Hash256,
+    from: Option<InscriptionLocation>,
+    /// Offset of the sat within the block's accumulated fees, unknown when an
+    /// earlier transaction's fee is
+    fee_offset: Option<u64>,
+}
 
 pub struct BitcoinIndexer {
     client: Client,
-    db: Arc<Database>,
+    db: Arc<dyn Storage>,
     config: Arc<Config>,
     runes_processor: RunesProcessor,
+    inscription_processor: InscriptionProcessor,
+    brc20_processor: Brc20Processor,
+    bridge_processor: BridgeProcessor,
+    /// Network stored addresses are encoded for
+    network: Network,
+    content_store: Option<ContentStore>,
+    utxo_cache: Option<UtxoCache>,
 }
 
 impl BitcoinIndexer {
-    pub async fn new(db: Arc<Database>, config: Arc<Config>) -> Result<Self> {
+    pub async fn new(db: Arc<dyn Storage>, config: Arc<Config>) -> Result<Self> {
         let auth = Auth::UserPass(
             config.bitcoin_rpc_user.clone(),
             config.bitcoin_rpc_pass.clone(),
@@ -23,30 +63,62 @@ impl BitcoinIndexer {
 
         let client = Client::new(&config.bitcoin_rpc_url, auth)?;
         let runes_processor = RunesProcessor::new();
+        let inscription_processor = InscriptionProcessor::new();
+        let brc20_processor = Brc20Processor::new(db.clone());
+        let network = config.network()?;
+        let bridge_processor = BridgeProcessor::new(&config, network)?;
+        let content_store = config
+            .inscription_content_dir
+            .as_ref()
+            .map(ContentStore::new);
+        let utxo_cache = config
+            .utxo_cache_dir
+            .as_deref()
+            .map(|dir| UtxoCache::open(dir, config.utxo_cache_capacity))
+            .transpose()?;
 
         Ok(Self {
             client,
             db,
             config,
             runes_processor,
+            inscription_processor,
+            brc20_processor,
+            bridge_processor,
+            network,
+            content_store,
+            utxo_cache,
         })
     }
 
     pub async fn start(&self) -> Result<()> {
         info!("Starting Bitcoin indexer");
 
-        // Get the last indexed height from database
-        let mut current_height = self
-            .db
-            .get_last_block_height()
-            .await?
-            .unwrap_or(self.config.indexer_start_height);
+        // A block is written in several steps; one cut short by a crash is
+        // discarded and indexed again
+        if let Some(tip) = self.db.get_last_block_height().await? {
+            if !self.db.has_block_stats(tip).await? {
+                warn!("Block {} was only partly indexed, re-indexing it", tip);
+                self.db.rollback_from(tip).await?;
+            }
+        }
+
+        if let Some(cache) = &self.utxo_cache {
+            cache.load(self.db.as_ref()).await?;
+        }
+
+        // Resume after the last indexed height from database
+        let mut next_height = match self.db.get_last_block_height().await? {
+            Some(height) => height + 1,
+            None => self.config.indexer_start_height,
+        };
 
-        loop {
+        'poll: loop {
             // Get the latest block height from Bitcoin node
-            let latest_height = self.client.get_block_count()? as u64;
+            let latest_height = self.client.get_block_count()?;
+            self.db.set_node_height(latest_height).await?;
 
-            if current_height >= latest_height {
+            if next_height > latest_height {
                 debug!("Caught up to latest block {}", latest_height);
                 sleep(Duration::from_secs(self.config.indexer_poll_interval_secs)).await;
                 continue;
@@ -54,20 +126,65 @@ impl BitcoinIndexer {
 
             // Process blocks in batches
             let end_height =
-                (current_height + self.config.indexer_batch_size as u64).min(latest_height);
+                (next_height + self.config.indexer_batch_size as u64).min(latest_height);
+
+            let start_height = next_height;
+            for height in start_height..=end_height {
+                // Make sure the block builds on what we have indexed
+                if let Some(fork_height) = self.handle_reorg(height).await? {
+                    next_height = fork_height;
+                    continue 'poll;
+                }
 
-            for height in current_height..=end_height {
                 if let Err(e) = self.process_block(height).await {
                     error!("Error processing block {}: {}", height, e);
-                    // Continue with next block
+                    // Discard the partially indexed block and retry it on the next poll
+                    self.db.rollback_from(height).await?;
+                    if let Some(cache) = &self.utxo_cache {
+                        cache.discard()?;
+                    }
+                    next_height = height;
+                    sleep(Duration::from_secs(self.config.indexer_poll_interval_secs)).await;
+                    continue 'poll;
                 }
+
+                next_height = height + 1;
             }
 
-            current_height = end_height + 1;
             info!("Indexed up to block {}", end_height);
         }
     }
 
+    /// Rolls back indexed blocks that are no longer on the node's best chain,
+    /// returning the height to resume from if a reorg was found.
+    async fn handle_reorg(&self, height: u64) -> Result<Option<u64>> {
+        let mut fork_height = height;
+        while fork_height > 0 {
+            let Some(stored) = self.db.get_block_by_height(fork_height - 1).await? else {
+                break;
+            };
+            if stored.hash == Hash256::from(self.client.get_block_hash(fork_height - 1)?) {
+                break;
+            }
+            fork_height -= 1;
+        }
+
+        if fork_height == height {
+            return Ok(None);
+        }
+
+        warn!(
+            "Reorg detected at height {}, rolling back to {}",
+            height, fork_height
+        );
+        self.db.rollback_from(fork_height).await?;
+        if let Some(cache) = &self.utxo_cache {
+            cache.rollback(fork_height, self.db.as_ref()).await?;
+        }
+
+        Ok(Some(fork_height))
+    }
+
     async fn process_block(&self, height: u64) -> Result<()> {
         // Get block hash
         let block_hash = self.client.get_block_hash(height)?;
@@ -75,35 +192,416 @@ impl BitcoinIndexer {
         // Get full block
         let block = self.client.get_block(&block_hash)?;
 
+        let header_info = self.client.get_block_header_info(&block_hash)?;
+
         // Store block in database
-        self.db.insert_block(&block, height).await?;
+        self.db
+            .insert_block(
+                &block,
+                height,
+                &header_info.chainwork,
+                header_info.median_time.map(|t| t as u64),
+            )
+            .await?;
+
+        // Process transactions, accumulating fees for the coinbase
+        let mut fees = Some(0u64);
+        let mut fee_inscriptions = Vec::new();
+        for (tx_index, tx) in block.txdata.iter().enumerate() {
+            let fee = self
+                .process_transaction(tx, tx_index, height, &block, fees, &mut fee_inscriptions)
+                .await?;
+            fees = fees.zip(fee).map(|(fees, fee)| fees + fee);
+        }
+
+        self.assign_fee_inscriptions(height, &block, fee_inscriptions)
+            .await?;
+
+        // Advance bridge events now that the chain has grown
+        self.db
+            .update_bridge_event_statuses(
+                height,
+                self.config.bridge_confirmations,
+                self.config.bridge_finality_confirmations,
+            )
+            .await?;
 
-        // Process transactions
-        for tx in &block.txdata {
-            self.process_transaction(tx, height, &block).await?;
+        self.db.insert_block_stats(height).await?;
+
+        if let Some(cache) = &self.utxo_cache {
+            cache.flush(height).await?;
         }
 
         Ok(())
     }
 
+    /// Processes a transaction and returns the fee it paid, if its inputs'
+    /// values are known.
     async fn process_transaction(
         &self,
         tx: &Transaction,
+        tx_index: usize,
         block_height: u64,
         block: &Block,
-    ) -> Result<()> {
+        fees_before: Option<u64>,
+        fee_inscriptions: &mut Vec<FeeInscription>,
+    ) -> Result<Option<u64>> {
+        let txid = Hash256::from(tx.txid());
+
+        // Resolve prevouts; those created before the start height are not indexed,
+        // so only their values are asked of the node
+        let mut prevouts = Vec::with_capacity(tx.input.len());
+        let mut input_values = vec![Some(0); tx.input.len()];
+        if !tx.is_coinbase() {
+            let mut parents = HashMap::new();
+            for (vin, input) in tx.input.iter().enumerate() {
+                let prevout = self.get_prevout(&input.previous_output).await?;
+                input_values[vin] = match &prevout {
+                    Some(output) => Some(output.value as u64),
+                    None => self.fetch_prevout_value(&input.previous_output, &mut parents)?,
+                };
+                prevouts.push(prevout);
+            }
+        }
+        if input_values.iter().any(Option::is_none) {
+            warn!(
+                "Transaction {} spends outputs neither indexed nor served by the node \
+                 (is txindex enabled?); skipping sat flow past its first unknown input",
+                txid
+            );
+        }
+
+        let output_values: Vec<u64> = tx.output.iter().map(|o| o.value.to_sat()).collect();
+        let output_total: u64 = output_values.iter().sum();
+        let fee = if tx.is_coinbase() {
+            None
+        } else {
+            input_values
+                .iter()
+                .copied()
+                .sum::<Option<u64>>()
+                .map(|input_total| input_total.saturating_sub(output_total))
+        };
+
+        // Offset of each input's first sat, known up to the first input of
+        // unknown value
+        let input_offsets: Vec<Option<u64>> = input_values
+            .iter()
+            .scan(Some(0u64), |start, value| {
+                let offset = *start;
+                *start = start.zip(*value).map(|(start, value)| start + value);
+                Some(offset)
+            })
+            .collect();
+
         // Store transaction
         self.db
-            .insert_transaction(tx, block_height, &block.header)
+            .insert_transaction(
+                tx,
+                tx_index,
+                block_height,
+                &block.header,
+                fee.map(|f| f as i64),
+                &prevouts,
+                self.network,
+            )
             .await?;
+        if let Some(cache) = &self.utxo_cache {
+            cache.insert_outputs(tx, self.network)?;
+        }
+
+        // Resolve where a sat entering at `offset` ends up
+        let locate = |offset: u64| match locate_sat(offset, &output_values) {
+            Some((vout, offset)) => Ok(InscriptionLocation {
+                txid,
+                vout,
+                offset,
+                address: bitcoin::Address::from_script(
+                    &tx.output[vout as usize].script_pubkey,
+                    self.network,
+                )
+                .ok()
+                .map(|a| a.to_string()),
+            }),
+            None => Err(fees_before.map(|fees| fees + offset - output_total)),
+        };
+
+        // Spend inputs, carrying any inscriptions they hold along the sat flow
+        for (vin, input) in tx.input.iter().enumerate() {
+            if tx.is_coinbase() {
+                continue;
+            }
+
+            let prevout = &input.previous_output;
+            let prev_txid = Hash256::from(prevout.txid);
+            self.db
+                .mark_output_spent(prev_txid, prevout.vout, txid, vin as u32)
+                .await?;
+            if let Some(cache) = &self.utxo_cache {
+                cache.spend(prevout)?;
+            }
+
+            for inscription in self
+                .db
+                .get_inscriptions_at_output(prev_txid, prevout.vout)
+                .await?
+            {
+                let from = InscriptionLocation {
+                    txid: prev_txid,
+                    vout: prevout.vout,
+                    offset: inscription.location_offset.unwrap_or_default() as u64,
+                    address: inscription.address.clone(),
+                };
+                let Some(input_start) = input_offsets[vin] else {
+                    warn!(
+                        "Leaving inscription {} at {}:{}, its sat's offset in {} is unknown",
+                        inscription.inscription_id, prev_txid, prevout.vout, txid
+                    );
+                    continue;
+                };
+
+                match locate(input_start + from.offset) {
+                    Ok(to) => {
+                        self.transfer_inscription(
+                            &inscription.inscription_id,
+                            Some(&from),
+                            Some(&to),
+                            false,
+                            txid,
+                            block_height,
+                            block,
+                        )
+                        .await?
+                    }
+                    Err(fee_offset) => fee_inscriptions.push(FeeInscription {
+                        inscription_id: inscription.inscription_id,
+                        txid,
+                        from: Some(from),
+                        fee_offset,
+                    }),
+                }
+            }
+        }
 
         // Check if this is a Runes transaction
-        if let Some(runes_data) = self.runes_processor.process_transaction(tx)? {
+        let runes_data = self.runes_processor.process_transaction(tx)?;
+        if let Some(runes_data) = &runes_data {
+            self.db
+                .insert_runes_transaction(runes_data, tx, block_height)
+                .await?;
+        }
+
+        // Check whether this pays out a Starknet withdrawal
+        if let Some(starknet_tx_hash) = self.bridge_processor.parse_withdrawal_reference(tx) {
+            for withdrawal in self.db.get_bridge_withdrawals(&starknet_tx_hash).await? {
+                if withdrawal.txid.is_some() {
+                    continue;
+                }
+
+                let outputs = tx.output.iter().enumerate();
+                let result = match_withdrawal(
+                    tx.txid(),
+                    outputs.map(|(vout, output)| (vout as u32, output)),
+                    &withdrawal,
+                    self.network,
+                );
+                self.db
+                    .match_bridge_withdrawal(
+                        withdrawal.id,
+                        txid,
+                        result.vout,
+                        block_height,
+                        result.status,
+                        &result.reason,
+                    )
+                    .await?;
+            }
+        }
+
+        // Check for deposits into the Starknet bridge
+        for event in self
+            .bridge_processor
+            .process_transaction(tx, runes_data.as_ref())
+        {
+            self.db
+                .insert_bridge_event(&event, block_height, &block.header)
+                .await?;
+        }
+
+        // Check for inscriptions revealed by this transaction; each starts on the
+        // first sat of the input that revealed it
+        for mut inscription in self.inscription_processor.process_transaction(tx)? {
+            match input_offsets[inscription.input_index as usize].map(locate) {
+                Some(Ok(location)) => inscription.location = Some(location),
+                Some(Err(fee_offset)) => fee_inscriptions.push(FeeInscription {
+                    inscription_id: inscription.inscription_id.clone(),
+                    txid,
+                    from: None,
+                    fee_offset,
+                }),
+                None => warn!(
+                    "Inscription {} has no location, its sat's offset in {} is unknown",
+                    inscription.inscription_id, txid
+                ),
+            }
+
+            let content_hash = sha256::Hash::hash(&inscription.content).to_string();
+
+            if let Some(store) = &self.content_store {
+                store.put(&content_hash, &inscription.content).await?;
+            }
+
             self.db
-                .insert_runes_transaction(&runes_data, tx, block_height)
+                .insert_inscription(
+                    &inscription,
+                    &content_hash,
+                    self.content_store.is_none(),
+                    tx,
+                    block_height,
+                    &block.header,
+                )
                 .await?;
+
+            self.brc20_processor
+                .process_inscription(&inscription, txid, block_height, block_timestamp(block))
+                .await?;
+        }
+
+        Ok(if tx.is_coinbase() { Some(0) } else { fee })
+    }
+
+    /// Looks up a spent output, trying the UTXO cache before the database.
+    async fn get_prevout(&self, outpoint: &OutPoint) -> Result<Option<OutputModel>> {
+        if let Some(cache) = &self.utxo_cache {
+            if let Some(output) = cache.get(outpoint)? {
+                return Ok(Some(output));
+            }
+        }
+
+        self.db
+            .get_output(outpoint.txid.into(), outpoint.vout)
+            .await
+    }
+
+    /// Looks up the value of an output that was never indexed in the node's
+    /// copy of its transaction, which needs `txindex` once it is confirmed.
+    /// `parents` keeps the transactions fetched for the current spender.
+    fn fetch_prevout_value(
+        &self,
+        outpoint: &OutPoint,
+        parents: &mut HashMap<Txid, Transaction>,
+    ) -> Result<Option<u64>> {
+        let parent = match parents.entry(outpoint.txid) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                match self.client.get_raw_transaction(&outpoint.txid, None) {
+                    Ok(tx) => entry.insert(tx),
+                    // RPC_INVALID_ADDRESS_OR_KEY: unknown to the node
+                    Err(bitcoincore_rpc::Error::JsonRpc(bitcoincore_rpc::jsonrpc::Error::Rpc(
+                        e,
+                    ))) if e.code == -5 => return Ok(None),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        };
+
+        Ok(parent
+            .output
+            .get(outpoint.vout as usize)
+            .map(|output| output.value.to_sat()))
+    }
+
+    /// Moves inscriptions spent as fees into the coinbase outputs, after the subsidy.
+    async fn assign_fee_inscriptions(
+        &self,
+        height: u64,
+        block: &Block,
+        fee_inscriptions: Vec<FeeInscription>,
+    ) -> Result<()> {
+        let Some(coinbase) = block.txdata.first() else {
+            return Ok(());
+        };
+
+        let coinbase_txid = Hash256::from(coinbase.txid());
+        let output_values: Vec<u64> = coinbase.output.iter().map(|o| o.value.to_sat()).collect();
+        let subsidy = INITIAL_SUBSIDY
+            .checked_shr((height / HALVING_INTERVAL) as u32)
+            .unwrap_or(0);
+
+        for inscription in fee_inscriptions {
+            let Some(fee_offset) = inscription.fee_offset else {
+                warn!(
+                    "Inscription {} was spent as fee at an unknown offset in block {}",
+                    inscription.inscription_id, height
+                );
+                continue;
+            };
+
+            // Sats the miner did not claim are lost
+            let to = locate_sat(subsidy + fee_offset, &output_values).map(|(vout, offset)| {
+                InscriptionLocation {
+                    txid: coinbase_txid,
+                    vout,
+                    offset,
+                    address: bitcoin::Address::from_script(
+                        &coinbase.output[vout as usize].script_pubkey,
+                        self.network,
+                    )
+                    .ok()
+                    .map(|a| a.to_string()),
+                }
+            });
+
+            self.transfer_inscription(
+                &inscription.inscription_id,
+                inscription.from.as_ref(),
+                to.as_ref(),
+                true,
+                inscription.txid,
+                height,
+                block,
+            )
+            .await?;
         }
 
         Ok(())
     }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn transfer_inscription(
+        &self,
+        inscription_id: &str,
+        from: Option<&InscriptionLocation>,
+        to: Option<&InscriptionLocation>,
+        spent_as_fee: bool,
+        txid: Hash256,
+        block_height: u64,
+        block: &Block,
+    ) -> Result<()> {
+        self.db
+            .insert_inscription_transfer(
+                inscription_id,
+                from,
+                to,
+                txid,
+                block_height,
+                &block.header,
+            )
+            .await?;
+
+        self.brc20_processor
+            .process_transfer(
+                inscription_id,
+                to,
+                spent_as_fee,
+                txid,
+                block_height,
+                block_timestamp(block),
+            )
+            .await
+    }
+}
+
+fn block_timestamp(block: &Block) -> DateTime<Utc> {
+    DateTime::<Utc>::from_timestamp(block.header.time as i64, 0).unwrap_or_else(Utc::now)
 }
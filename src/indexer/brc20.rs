@@ -0,0 +1,604 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::db::{
+    models::{
+        Brc20Event, Brc20EventKind, Brc20TokenModel, Hash256, InscriptionData, InscriptionLocation,
+    },
+    Storage,
+};
+
+const PROTOCOL: &str = "brc-20";
+/// Ticks are 4 bytes of UTF-8, not 4 characters
+const TICK_LENGTH: usize = 4;
+const MAX_DECIMALS: u8 = 18;
+
+#[derive(Debug, Clone)]
+pub enum Brc20Operation {
+    Deploy {
+        tick: String,
+        max: String,
+        limit: Option<String>,
+        decimals: Option<String>,
+    },
+    Mint {
+        tick: String,
+        amount: String,
+    },
+    Transfer {
+        tick: String,
+        amount: String,
+    },
+}
+
+/// Maintains the BRC-20 ledger from `deploy`/`mint`/`transfer` JSON inscriptions
+pub struct Brc20Processor {
+    db: Arc<dyn Storage>,
+}
+
+impl Brc20Processor {
+    pub fn new(db: Arc<dyn Storage>) -> Self {
+        Self { db }
+    }
+
+    pub fn parse(&self, inscription: &InscriptionData) -> Option<Brc20Operation> {
+        let content_type = inscription.content_type.as_deref()?;
+        if !(content_type.starts_with("text/plain") || content_type.starts_with("application/json"))
+            || inscription.content_encoding.is_some()
+        {
+            return None;
+        }
+
+        let json: Value = serde_json::from_slice(&inscription.content).ok()?;
+        let field = |name: &str| json.get(name).and_then(Value::as_str).map(str::to_string);
+
+        if field("p")? != PROTOCOL {
+            return None;
+        }
+
+        let tick = field("tick")?;
+        if tick.len() != TICK_LENGTH {
+            return None;
+        }
+        let tick = tick.to_lowercase();
+
+        match field("op")?.as_str() {
+            "deploy" => Some(Brc20Operation::Deploy {
+                tick,
+                max: field("max")?,
+                limit: field("lim"),
+                decimals: field("dec"),
+            }),
+            "mint" => Some(Brc20Operation::Mint {
+                tick,
+                amount: field("amt")?,
+            }),
+            "transfer" => Some(Brc20Operation::Transfer {
+                tick,
+                amount: field("amt")?,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Applies a newly created inscription to the ledger if it is a valid operation.
+    pub async fn process_inscription(
+        &self,
+        inscription: &InscriptionData,
+        txid: Hash256,
+        block_height: u64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<()> {
+        let Some(operation) = self.parse(inscription) else {
+            return Ok(());
+        };
+
+        // Balances belong to whoever received the inscription at creation
+        let Some(owner) = inscription
+            .location
+            .as_ref()
+            .and_then(|location| location.address.clone())
+        else {
+            return Ok(());
+        };
+
+        let event = |operation, tick: &str, amount: Option<String>| Brc20Event {
+            inscription_id: inscription.inscription_id.clone(),
+            operation,
+            tick: tick.to_string(),
+            amount,
+            from_address: None,
+            to_address: None,
+            txid,
+            block_height,
+            timestamp,
+        };
+
+        match operation {
+            Brc20Operation::Deploy {
+                tick,
+                max,
+                limit,
+                decimals,
+            } => {
+                if self.db.get_brc20_token(&tick).await?.is_some() {
+                    return Ok(());
+                }
+
+                let decimals = match decimals {
+                    Some(decimals) => match decimals.parse::<u8>() {
+                        Ok(decimals) if decimals <= MAX_DECIMALS => decimals,
+                        _ => return Ok(()),
+                    },
+                    None => MAX_DECIMALS,
+                };
+
+                let Some(max) = parse_amount(&max, decimals) else {
+                    return Ok(());
+                };
+                let limit = match limit {
+                    Some(limit) => match parse_amount(&limit, decimals) {
+                        Some(limit) => limit,
+                        None => return Ok(()),
+                    },
+                    None => max,
+                };
+                if max == 0 || max > u64::MAX as u128 * scale(decimals) || limit == 0 {
+                    return Ok(());
+                }
+
+                let token = Brc20TokenModel {
+                    tick: tick.clone(),
+                    inscription_id: inscription.inscription_id.clone(),
+                    max_supply: format_amount(max, decimals),
+                    mint_limit: format_amount(limit, decimals),
+                    decimals: decimals as i32,
+                    minted: "0".to_string(),
+                    deployer: Some(owner.clone()),
+                    block_height: block_height as i64,
+                    timestamp,
+                };
+
+                let event = Brc20Event {
+                    to_address: Some(owner),
+                    ..event(Brc20EventKind::Deploy, &tick, None)
+                };
+                self.db.insert_brc20_token(&token, &event).await?;
+            }
+            Brc20Operation::Mint { tick, amount } => {
+                let Some(token) = self.db.get_brc20_token(&tick).await? else {
+                    return Ok(());
+                };
+                let decimals = token.decimals as u8;
+
+                let (Some(amount), Some(max), Some(limit), Some(minted)) = (
+                    parse_amount(&amount, decimals),
+                    parse_amount(&token.max_supply, decimals),
+                    parse_amount(&token.mint_limit, decimals),
+                    parse_amount(&token.minted, decimals),
+                ) else {
+                    return Ok(());
+                };
+
+                // The last mint is clamped to whatever supply remains
+                let remaining = max.saturating_sub(minted);
+                if amount == 0 || amount > limit || remaining == 0 {
+                    return Ok(());
+                }
+                let amount = amount.min(remaining);
+
+                let event = Brc20Event {
+                    to_address: Some(owner),
+                    ..event(
+                        Brc20EventKind::Mint,
+                        &tick,
+                        Some(format_amount(amount, decimals)),
+                    )
+                };
+                self.db.apply_brc20_mint(&event).await?;
+            }
+            Brc20Operation::Transfer { tick, amount } => {
+                let Some(token) = self.db.get_brc20_token(&tick).await? else {
+                    return Ok(());
+                };
+                let decimals = token.decimals as u8;
+
+                let Some(amount) = parse_amount(&amount, decimals) else {
+                    return Ok(());
+                };
+                let available = self
+                    .db
+                    .get_brc20_balance(&owner, &tick)
+                    .await?
+                    .and_then(|balance| parse_amount(&balance.available, decimals))
+                    .unwrap_or_default();
+                if amount == 0 || amount > available {
+                    return Ok(());
+                }
+
+                let event = Brc20Event {
+                    from_address: Some(owner),
+                    ..event(
+                        Brc20EventKind::InscribeTransfer,
+                        &tick,
+                        Some(format_amount(amount, decimals)),
+                    )
+                };
+                self.db.apply_brc20_inscribe_transfer(&event).await?;
+            }
+        }
+
+        debug!("Applied BRC-20 inscription {}", inscription.inscription_id);
+        Ok(())
+    }
+
+    /// Settles a transfer inscription the first time it moves. Spent as fee
+    /// it returns the amount to the sender; sent to an output without an
+    /// address, such as OP_RETURN, it burns it.
+    pub async fn process_transfer(
+        &self,
+        inscription_id: &str,
+        to: Option<&InscriptionLocation>,
+        spent_as_fee: bool,
+        txid: Hash256,
+        block_height: u64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<()> {
+        let Some(transfer) = self.db.get_brc20_transfer(inscription_id).await? else {
+            return Ok(());
+        };
+        if transfer.used {
+            return Ok(());
+        }
+
+        let to_address = if spent_as_fee {
+            Some(transfer.from_address.clone())
+        } else {
+            to.and_then(|location| location.address.clone())
+        };
+        let event = Brc20Event {
+            inscription_id: inscription_id.to_string(),
+            operation: Brc20EventKind::Transfer,
+            tick: transfer.tick,
+            amount: Some(transfer.amount),
+            from_address: Some(transfer.from_address),
+            to_address,
+            txid,
+            block_height,
+            timestamp,
+        };
+        self.db.apply_brc20_transfer(&event).await?;
+
+        debug!("Settled BRC-20 transfer {}", inscription_id);
+        Ok(())
+    }
+}
+
+fn scale(decimals: u8) -> u128 {
+    10u128.pow(decimals as u32)
+}
+
+/// Parses a plain decimal string into an integer scaled by `10^decimals`.
+pub fn parse_amount(value: &str, decimals: u8) -> Option<u128> {
+    let (whole, fraction) = match value.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction.trim_end_matches('0')),
+        None => (value, ""),
+    };
+
+    if (whole.is_empty() && fraction.is_empty())
+        || fraction.len() > decimals as usize
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let whole: u128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let fraction: u128 = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<u128>().ok()? * scale(decimals - fraction.len() as u8)
+    };
+
+    whole.checked_mul(scale(decimals))?.checked_add(fraction)
+}
+
+/// Formats a scaled integer back into its shortest decimal string.
+pub fn format_amount(value: u128, decimals: u8) -> String {
+    let whole = value / scale(decimals);
+    let fraction = value % scale(decimals);
+    if fraction == 0 {
+        return whole.to_string();
+    }
+
+    let fraction = format!("{:0width$}", fraction, width = decimals as usize);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats_amounts() {
+        assert_eq!(parse_amount("21000000", 0), Some(21_000_000));
+        assert_eq!(parse_amount("1.5", 2), Some(150));
+        assert_eq!(parse_amount("1.50", 2), Some(150));
+        assert_eq!(parse_amount(".5", 1), Some(5));
+        assert_eq!(parse_amount("1.", 0), Some(1));
+        assert_eq!(parse_amount("0.000000000000000001", 18), Some(1));
+
+        // More fraction digits than the token has decimals
+        assert_eq!(parse_amount("1.234", 2), None);
+        assert_eq!(parse_amount("1.5", 0), None);
+        for invalid in ["", ".", "-1", "+1", "1e3", " 1", "1,5", "1.2.3"] {
+            assert_eq!(parse_amount(invalid, 18), None, "{:?}", invalid);
+        }
+        assert_eq!(parse_amount(&u128::MAX.to_string(), 1), None);
+
+        assert_eq!(format_amount(150, 2), "1.5");
+        assert_eq!(format_amount(100, 2), "1");
+        assert_eq!(format_amount(0, 18), "0");
+        assert_eq!(format_amount(5, 18), "0.000000000000000005");
+        for (value, decimals) in [(123_456_789, 4), (1, 18), (u64::MAX as u128, 8)] {
+            let formatted = format_amount(value, decimals);
+            assert_eq!(parse_amount(&formatted, decimals), Some(value));
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    mod ledger {
+        use super::*;
+        use crate::db::SqliteDatabase;
+        use bitcoin::{blockdata::constants::genesis_block, Network};
+
+        const ALICE: &str = "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080";
+
+        struct Ledger {
+            db: Arc<dyn Storage>,
+            processor: Brc20Processor,
+            block: bitcoin::Block,
+            inscriptions: u32,
+        }
+
+        impl Ledger {
+            async fn new() -> Self {
+                let db = SqliteDatabase::new("sqlite::memory:", 1).await.unwrap();
+                db.run_migrations().await.unwrap();
+                let block = genesis_block(Network::Regtest);
+                db.insert_block(&block, 0, &[0; 32], None).await.unwrap();
+                db.insert_transaction(
+                    &block.txdata[0],
+                    0,
+                    0,
+                    &block.header,
+                    None,
+                    &[],
+                    Network::Regtest,
+                )
+                .await
+                .unwrap();
+
+                let db: Arc<dyn Storage> = Arc::new(db);
+                Self {
+                    processor: Brc20Processor::new(db.clone()),
+                    db,
+                    block,
+                    inscriptions: 0,
+                }
+            }
+
+            fn txid(&self) -> Hash256 {
+                self.block.txdata[0].txid().into()
+            }
+
+            /// Inscribes `json` to Alice, returning the inscription id
+            async fn inscribe(&mut self, json: Value) -> String {
+                let inscription = InscriptionData {
+                    inscription_id: format!("{}i{}", self.txid(), self.inscriptions),
+                    input_index: 0,
+                    content_type: Some("text/plain;charset=utf-8".to_string()),
+                    content_encoding: None,
+                    metaprotocol: None,
+                    content: json.to_string().into_bytes(),
+                    location: Some(InscriptionLocation {
+                        txid: self.txid(),
+                        vout: 0,
+                        offset: 0,
+                        address: Some(ALICE.to_string()),
+                    }),
+                };
+                self.inscriptions += 1;
+
+                self.db
+                    .insert_inscription(
+                        &inscription,
+                        "",
+                        false,
+                        &self.block.txdata[0],
+                        0,
+                        &self.block.header,
+                    )
+                    .await
+                    .unwrap();
+                self.processor
+                    .process_inscription(&inscription, self.txid(), 0, Utc::now())
+                    .await
+                    .unwrap();
+                inscription.inscription_id
+            }
+
+            async fn transfer(&self, inscription_id: &str, to: Option<&str>, spent_as_fee: bool) {
+                let to = InscriptionLocation {
+                    txid: self.txid(),
+                    vout: 0,
+                    offset: 0,
+                    address: to.map(str::to_string),
+                };
+                self.processor
+                    .process_transfer(
+                        inscription_id,
+                        Some(&to),
+                        spent_as_fee,
+                        self.txid(),
+                        0,
+                        Utc::now(),
+                    )
+                    .await
+                    .unwrap();
+            }
+
+            /// Alice's available and transferable balance
+            async fn balance(&self, tick: &str) -> Option<(String, String)> {
+                self.db
+                    .get_brc20_balance(ALICE, tick)
+                    .await
+                    .unwrap()
+                    .map(|balance| (balance.available, balance.transferable))
+            }
+        }
+
+        fn op(op: &str, tick: &str, fields: Value) -> Value {
+            let mut json = serde_json::json!({ "p": "brc-20", "op": op, "tick": tick });
+            json.as_object_mut()
+                .unwrap()
+                .extend(fields.as_object().unwrap().clone());
+            json
+        }
+
+        fn balance(available: &str, transferable: &str) -> Option<(String, String)> {
+            Some((available.to_string(), transferable.to_string()))
+        }
+
+        #[tokio::test]
+        async fn measures_ticks_in_bytes() {
+            let ledger = Ledger::new().await;
+            let parse = |tick: &str| {
+                let content = op("mint", tick, serde_json::json!({ "amt": "1" }));
+                let inscription = InscriptionData {
+                    inscription_id: format!("{}i0", ledger.txid()),
+                    input_index: 0,
+                    content_type: Some("text/plain;charset=utf-8".to_string()),
+                    content_encoding: None,
+                    metaprotocol: None,
+                    content: content.to_string().into_bytes(),
+                    location: None,
+                };
+                ledger.processor.parse(&inscription).is_some()
+            };
+
+            assert!(parse("ORDI"));
+            assert!(parse("\u{1F415}"));
+            assert!(parse("\u{e9}th"));
+            // Four characters, but more than four bytes
+            assert!(!parse("\u{e9}t\u{e9}s"));
+            assert!(!parse("\u{1F415}\u{1F415}\u{1F415}\u{1F415}"));
+            assert!(!parse("ord"));
+            assert!(!parse("ordis"));
+        }
+
+        #[tokio::test]
+        async fn applies_token_decimals() {
+            let mut ledger = Ledger::new().await;
+
+            // At most 18 decimals
+            ledger
+                .inscribe(op(
+                    "deploy",
+                    "wide",
+                    serde_json::json!({ "max": "1", "dec": "19" }),
+                ))
+                .await;
+            assert!(ledger.db.get_brc20_token("wide").await.unwrap().is_none());
+
+            ledger
+                .inscribe(op(
+                    "deploy",
+                    "cent",
+                    serde_json::json!({ "max": "1000", "dec": "2" }),
+                ))
+                .await;
+            let token = ledger.db.get_brc20_token("cent").await.unwrap().unwrap();
+            assert_eq!(token.decimals, 2);
+            assert_eq!(token.mint_limit, "1000");
+
+            ledger
+                .inscribe(op("mint", "cent", serde_json::json!({ "amt": "1.555" })))
+                .await;
+            assert_eq!(ledger.balance("cent").await, None);
+            ledger
+                .inscribe(op("mint", "cent", serde_json::json!({ "amt": "1.50" })))
+                .await;
+            assert_eq!(ledger.balance("cent").await, balance("1.5", "0"));
+        }
+
+        #[tokio::test]
+        async fn clamps_the_last_mint() {
+            let mut ledger = Ledger::new().await;
+            ledger
+                .inscribe(op(
+                    "deploy",
+                    "ordi",
+                    serde_json::json!({ "max": "100", "lim": "60" }),
+                ))
+                .await;
+
+            // Above the limit
+            ledger
+                .inscribe(op("mint", "ordi", serde_json::json!({ "amt": "61" })))
+                .await;
+            assert_eq!(ledger.balance("ordi").await, None);
+
+            for _ in 0..3 {
+                ledger
+                    .inscribe(op("mint", "ordi", serde_json::json!({ "amt": "60" })))
+                    .await;
+            }
+            assert_eq!(ledger.balance("ordi").await, balance("100", "0"));
+            let token = ledger.db.get_brc20_token("ordi").await.unwrap().unwrap();
+            assert_eq!(token.minted, "100");
+        }
+
+        #[tokio::test]
+        async fn burns_transfers_to_outputs_without_an_address() {
+            let mut ledger = Ledger::new().await;
+            ledger
+                .inscribe(op("deploy", "ordi", serde_json::json!({ "max": "100" })))
+                .await;
+            ledger
+                .inscribe(op("mint", "ordi", serde_json::json!({ "amt": "60" })))
+                .await;
+            let burned = ledger
+                .inscribe(op("transfer", "ordi", serde_json::json!({ "amt": "10" })))
+                .await;
+            let fee = ledger
+                .inscribe(op("transfer", "ordi", serde_json::json!({ "amt": "20" })))
+                .await;
+            assert_eq!(ledger.balance("ordi").await, balance("30", "30"));
+
+            // Sent to an OP_RETURN output
+            ledger.transfer(&burned, None, false).await;
+            assert_eq!(ledger.balance("ordi").await, balance("30", "20"));
+
+            // Spent as fee into a coinbase output with an address
+            ledger.transfer(&fee, Some("bcrt1qminer"), true).await;
+            assert_eq!(ledger.balance("ordi").await, balance("50", "0"));
+            assert_eq!(
+                ledger
+                    .db
+                    .get_brc20_balance("bcrt1qminer", "ordi")
+                    .await
+                    .unwrap()
+                    .map(|b| b.available),
+                None
+            );
+        }
+    }
+}
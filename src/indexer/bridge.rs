@@ -0,0 +1,480 @@
+use anyhow::{anyhow, Result};
+use bitcoin::{
+    script::{Instruction, PushBytesBuf},
+    Address, Network, ScriptBuf, Transaction, TxOut, Txid,
+};
+use std::{collections::HashMap, str::FromStr};
+use tracing::{debug, warn};
+
+use crate::{
+    config::Config,
+    db::models::{BridgeEventData, BridgeEventModel, BridgeEventStatus, RunesData},
+    indexer::runes::allocate,
+};
+
+/// Starknet field prime, 2^251 + 17 * 2^192 + 1, big-endian
+const FELT_PRIME: [u8; 32] = [
+    0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+];
+
+pub const ASSET_BTC: &str = "BTC";
+
+/// OP_RETURN prefix committing a payout to a Starknet withdrawal transaction hash
+const WITHDRAWAL_REF_PREFIX: &[u8] = b"SNWD";
+
+/// Outcome of checking a payout against a withdrawal request
+#[derive(Debug, Clone)]
+pub struct WithdrawalMatch {
+    pub vout: Option<u32>,
+    pub status: BridgeEventStatus,
+    pub reason: String,
+}
+
+/// Detects deposits to the bridge's Bitcoin addresses. A deposit carries its
+/// Starknet recipient in an OP_RETURN output, either as a 32-byte big-endian
+/// felt or as a `0x`-prefixed hex string.
+pub struct BridgeProcessor {
+    deposit_scripts: HashMap<ScriptBuf, String>,
+}
+
+impl BridgeProcessor {
+    pub fn new(config: &Config, network: Network) -> Result<Self> {
+        let mut deposit_scripts = HashMap::new();
+
+        for address in split_list(config.bridge_deposit_addresses.as_deref()) {
+            let script = Address::from_str(address)?
+                .require_network(network)?
+                .script_pubkey();
+            deposit_scripts.insert(script, address.to_string());
+        }
+
+        for script in split_list(config.bridge_deposit_scripts.as_deref()) {
+            let script = ScriptBuf::from_hex(script)
+                .map_err(|e| anyhow!("Invalid bridge deposit script {}: {}", script, e))?;
+            let label = Address::from_script(&script, network)
+                .map(|a| a.to_string())
+                .unwrap_or_else(|_| script.to_hex_string());
+            deposit_scripts.insert(script, label);
+        }
+
+        Ok(Self { deposit_scripts })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.deposit_scripts.is_empty()
+    }
+
+    pub fn process_transaction(
+        &self,
+        tx: &Transaction,
+        runes_data: Option<&RunesData>,
+    ) -> Vec<BridgeEventData> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+
+        let deposits: Vec<(usize, &TxOut, &String)> = tx
+            .output
+            .iter()
+            .enumerate()
+            .filter_map(|(vout, output)| {
+                self.deposit_scripts
+                    .get(&output.script_pubkey)
+                    .map(|address| (vout, output, address))
+            })
+            .collect();
+
+        if deposits.is_empty() {
+            return Vec::new();
+        }
+
+        let Some(starknet_address) = tx.output.iter().find_map(parse_starknet_address) else {
+            warn!(
+                "Bridge deposit {} has no valid Starknet recipient, skipping",
+                tx.txid()
+            );
+            return Vec::new();
+        };
+
+        let event =
+            |vout: usize, address: &String, asset: String, amount: i64, metadata| BridgeEventData {
+                event_type: "deposit".to_string(),
+                txid: Some(tx.txid().into()),
+                vout: Some(vout as u32),
+                starknet_tx_hash: None,
+                starknet_event_index: None,
+                bitcoin_address: Some(address.clone()),
+                starknet_address: Some(starknet_address.clone()),
+                amount: Some(amount),
+                asset: Some(asset),
+                metadata: Some(metadata),
+            };
+
+        // Outputs the runestone assigns runes to deposit those runes rather
+        // than their carrier sats, crediting only the amount each one receives
+        let runes: HashMap<u32, (&String, u128)> = runes_data
+            .and_then(|runes| Some((runes.rune_id.as_ref()?, allocate(runes, tx))))
+            .map(|(rune_id, allocations)| {
+                allocations
+                    .into_iter()
+                    .map(|(vout, amount)| (vout, (rune_id, amount)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let events: Vec<_> = deposits
+            .into_iter()
+            .filter_map(|(vout, output, address)| {
+                let carrier_value = output.value.to_sat();
+                let (asset, amount) = match runes.get(&(vout as u32)) {
+                    Some((rune_id, amount)) => match i64::try_from(*amount) {
+                        Ok(amount) => (rune_id.to_string(), amount),
+                        Err(_) => {
+                            warn!(
+                                "Rune deposit {}:{} of {} overflows",
+                                tx.txid(),
+                                vout,
+                                amount
+                            );
+                            return None;
+                        }
+                    },
+                    None => (ASSET_BTC.to_string(), carrier_value as i64),
+                };
+                Some(event(
+                    vout,
+                    address,
+                    asset,
+                    amount,
+                    serde_json::json!({ "carrier_value": carrier_value }),
+                ))
+            })
+            .collect();
+
+        debug!(
+            "Detected {} bridge deposit(s) in {}",
+            events.len(),
+            tx.txid()
+        );
+        events
+    }
+
+    /// Returns the Starknet withdrawal a payout commits to via an
+    /// `OP_RETURN "SNWD" <32-byte tx hash>` output.
+    pub fn parse_withdrawal_reference(&self, tx: &Transaction) -> Option<String> {
+        tx.output.iter().find_map(|output| {
+            let payload = op_return_payload(output)?;
+            let hash = payload.strip_prefix(WITHDRAWAL_REF_PREFIX)?;
+            (hash.len() == 32).then(|| format!("0x{}", hex::encode(hash)))
+        })
+    }
+}
+
+/// The `OP_RETURN "SNWD" <32-byte tx hash>` output a payout for
+/// `starknet_tx_hash` carries, in its minimal push encoding.
+pub fn withdrawal_reference_script(starknet_tx_hash: &str) -> Option<ScriptBuf> {
+    let hash = normalize_felt_hex(starknet_tx_hash)?;
+    let payload = PushBytesBuf::try_from([WITHDRAWAL_REF_PREFIX, &hash].concat()).ok()?;
+    Some(ScriptBuf::new_op_return(payload))
+}
+
+/// Checks that a payout's `outputs`, numbered by vout, pay the withdrawal's
+/// recipient exactly the requested amount.
+pub fn match_withdrawal<'a>(
+    txid: Txid,
+    outputs: impl IntoIterator<Item = (u32, &'a TxOut)>,
+    withdrawal: &BridgeEventModel,
+    network: Network,
+) -> WithdrawalMatch {
+    let recipient = withdrawal
+        .bitcoin_address
+        .as_deref()
+        .and_then(|address| Address::from_str(address).ok())
+        .and_then(|address| address.require_network(network).ok())
+        .map(|address| address.script_pubkey());
+
+    let Some((vout, output)) = recipient.and_then(|script| {
+        outputs
+            .into_iter()
+            .find(|(_, output)| output.script_pubkey == script)
+    }) else {
+        return WithdrawalMatch {
+            vout: None,
+            status: BridgeEventStatus::Mismatched,
+            reason: format!("payout {} has no output to the recipient", txid),
+        };
+    };
+
+    let paid = output.value.to_sat() as i64;
+    if Some(paid) != withdrawal.amount {
+        return WithdrawalMatch {
+            vout: Some(vout),
+            status: BridgeEventStatus::Mismatched,
+            reason: format!(
+                "payout {} pays {} sats, expected {:?}",
+                txid, paid, withdrawal.amount
+            ),
+        };
+    }
+
+    WithdrawalMatch {
+        vout: Some(vout),
+        status: BridgeEventStatus::Pending,
+        reason: format!("matched payout {}", txid),
+    }
+}
+
+fn split_list(value: Option<&str>) -> impl Iterator<Item = &str> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+fn op_return_payload(output: &TxOut) -> Option<Vec<u8>> {
+    if !output.script_pubkey.is_op_return() {
+        return None;
+    }
+
+    output
+        .script_pubkey
+        .instructions()
+        .skip(1)
+        .find_map(|instruction| match instruction {
+            Ok(Instruction::PushBytes(bytes)) => Some(bytes.as_bytes().to_vec()),
+            _ => None,
+        })
+}
+
+fn parse_starknet_address(output: &TxOut) -> Option<String> {
+    let payload = op_return_payload(output)?;
+
+    let felt = match payload.len() {
+        32 => payload,
+        _ => {
+            let text = std::str::from_utf8(&payload).ok()?;
+            normalize_felt_hex(text)?
+        }
+    };
+
+    let felt: [u8; 32] = felt.try_into().ok()?;
+    // Zero is not a deployable account address
+    if felt >= FELT_PRIME || felt == [0u8; 32] {
+        return None;
+    }
+
+    Some(format!("0x{}", hex::encode(felt)))
+}
+
+/// Decodes a `0x`-prefixed hex string of up to 64 digits into 32 big-endian bytes.
+fn normalize_felt_hex(value: &str) -> Option<Vec<u8>> {
+    let digits = value.strip_prefix("0x")?;
+    if digits.is_empty() || digits.len() > 64 {
+        return None;
+    }
+
+    hex::decode(format!("{:0>64}", digits)).ok()
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::{
+        models::{RuneEdict, RuneOperation},
+        SqliteDatabase, Storage,
+    };
+    use bitcoin::{
+        absolute::LockTime, blockdata::constants::genesis_block, transaction::Version, Amount,
+    };
+
+    const BRIDGE_SCRIPT: &str = "0014000102030405060708090a0b0c0d0e0f10111213";
+    const RECIPIENT: &str = "0x0000000000000000000000000000000000000000000000000000000000000abc";
+
+    fn processor() -> BridgeProcessor {
+        let config = Config::for_tests(serde_json::json!({
+            "bridge_deposit_scripts": BRIDGE_SCRIPT,
+        }));
+        BridgeProcessor::new(&config, Network::Regtest).unwrap()
+    }
+
+    /// Pays `change`, then the bridge, then names the Starknet recipient
+    fn deposit_tx() -> Transaction {
+        let output = |sats, script_pubkey| TxOut {
+            value: Amount::from_sat(sats),
+            script_pubkey,
+        };
+        let recipient = PushBytesBuf::try_from(RECIPIENT.as_bytes().to_vec()).unwrap();
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: Vec::new(),
+            output: vec![
+                output(546, ScriptBuf::from_bytes(vec![0x51])),
+                output(10_000, ScriptBuf::from_hex(BRIDGE_SCRIPT).unwrap()),
+                output(0, ScriptBuf::new_op_return(recipient)),
+            ],
+        }
+    }
+
+    fn transfer(edicts: Vec<RuneEdict>) -> RunesData {
+        RunesData {
+            rune_id: Some("RUNE_0000000000000001".to_string()),
+            operation: RuneOperation::Transfer,
+            amount: Some(1000),
+            from_address: None,
+            to_address: None,
+            metadata: None,
+            edicts,
+        }
+    }
+
+    fn credited(events: &[BridgeEventData]) -> Vec<(Option<u32>, String, i64)> {
+        events
+            .iter()
+            .map(|e| (e.vout, e.asset.clone().unwrap(), e.amount.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn credits_btc_deposits_to_the_recipient() {
+        let events = processor().process_transaction(&deposit_tx(), None);
+
+        assert_eq!(credited(&events), [(Some(1), "BTC".into(), 10_000)]);
+        assert_eq!(events[0].event_type, "deposit");
+        assert_eq!(events[0].starknet_address.as_deref(), Some(RECIPIENT));
+    }
+
+    #[test]
+    fn credits_runes_deposits_with_what_the_edicts_assign() {
+        let tx = deposit_tx();
+        let rune = "RUNE_0000000000000001".to_string();
+
+        // Only the 300 the edict gives the bridge; the rest is change
+        let runes = transfer(vec![RuneEdict {
+            output: 1,
+            amount: 300,
+        }]);
+        let events = processor().process_transaction(&tx, Some(&runes));
+        assert_eq!(credited(&events), [(Some(1), rune.clone(), 300)]);
+        assert_eq!(
+            events[0].metadata.as_ref().unwrap()["carrier_value"],
+            10_000
+        );
+
+        // Runes that all stay with the change leave a plain BTC deposit
+        let events = processor().process_transaction(&tx, Some(&transfer(Vec::new())));
+        assert_eq!(credited(&events), [(Some(1), "BTC".into(), 10_000)]);
+
+        let runes = transfer(vec![RuneEdict {
+            output: 1,
+            amount: 0,
+        }]);
+        let events = processor().process_transaction(&tx, Some(&runes));
+        assert_eq!(credited(&events), [(Some(1), rune, 1000)]);
+    }
+
+    /// `(from, to, reason)` of each status change of event `id`, oldest first
+    async fn history(
+        db: &SqliteDatabase,
+        id: i32,
+    ) -> Vec<(Option<String>, String, Option<String>)> {
+        db.get_bridge_event_transitions(id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.from_status, t.to_status, t.reason))
+            .collect()
+    }
+
+    async fn status(db: &SqliteDatabase, id: i32) -> String {
+        db.get_bridge_event(id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn confirms_finalizes_and_orphans_deposits() {
+        let db = SqliteDatabase::new("sqlite::memory:", 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+        let header = genesis_block(Network::Regtest).header;
+
+        // Two deposits, mined at heights 10 and 12
+        let deposit = processor()
+            .process_transaction(&deposit_tx(), None)
+            .remove(0);
+        let later = BridgeEventData {
+            vout: Some(0),
+            ..deposit.clone()
+        };
+        db.insert_bridge_event(&deposit, 10, &header).await.unwrap();
+        db.insert_bridge_event(&later, 12, &header).await.unwrap();
+
+        // Confirmed at 3 confirmations, finalized at 6
+        let advance = |tip| db.update_bridge_event_statuses(tip, 3, 6);
+        advance(11).await.unwrap();
+        assert_eq!(status(&db, 1).await, "pending");
+        advance(12).await.unwrap();
+        assert_eq!(status(&db, 1).await, "confirmed");
+        assert_eq!(status(&db, 2).await, "pending");
+        advance(15).await.unwrap();
+        assert_eq!(status(&db, 1).await, "finalized");
+        assert_eq!(status(&db, 2).await, "confirmed");
+
+        // A reorg below the finality depth only orphans the later deposit
+        db.rollback_from(12).await.unwrap();
+        assert_eq!(status(&db, 1).await, "finalized");
+        assert_eq!(
+            history(&db, 2).await,
+            [
+                (None, "pending".into(), None),
+                (
+                    Some("pending".into()),
+                    "confirmed".into(),
+                    Some("3 confirmations".into())
+                ),
+                (
+                    Some("confirmed".into()),
+                    "orphaned".into(),
+                    Some("reorg".into())
+                ),
+            ]
+        );
+
+        // One past it orphans the finalized deposit too, flagged as such
+        db.rollback_from(10).await.unwrap();
+        assert_eq!(
+            history(&db, 1).await,
+            [
+                (None, "pending".into(), None),
+                (
+                    Some("pending".into()),
+                    "confirmed".into(),
+                    Some("3 confirmations".into())
+                ),
+                (
+                    Some("confirmed".into()),
+                    "finalized".into(),
+                    Some("6 confirmations".into())
+                ),
+                (
+                    Some("finalized".into()),
+                    "orphaned".into(),
+                    Some("reorg after finality".into())
+                ),
+            ]
+        );
+        // Orphaned events stay put as the new chain grows
+        advance(20).await.unwrap();
+        assert_eq!(status(&db, 1).await, "orphaned");
+        assert_eq!(status(&db, 2).await, "orphaned");
+
+        // and the deposit is tracked afresh when mined again
+        db.insert_bridge_event(&deposit, 11, &header).await.unwrap();
+        assert_eq!(status(&db, 3).await, "pending");
+    }
+}
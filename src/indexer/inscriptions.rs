@@ -0,0 +1,349 @@
+use anyhow::Result;
+use bitcoin::{
+    opcodes::{all::*, OP_FALSE},
+    script::Instruction,
+    Script, Transaction,
+};
+use tracing::debug;
+
+use crate::db::models::InscriptionData;
+
+// Envelope tags as defined by the ord protocol
+const PROTOCOL_ID: &[u8] = b"ord";
+const TAG_CONTENT_TYPE: u8 = 1;
+const TAG_METAPROTOCOL: u8 = 7;
+const TAG_CONTENT_ENCODING: u8 = 9;
+
+#[derive(Default)]
+pub struct InscriptionProcessor;
+
+impl InscriptionProcessor {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn process_transaction(&self, tx: &Transaction) -> Result<Vec<InscriptionData>> {
+        let txid = tx.txid();
+        let mut inscriptions = Vec::new();
+
+        // Inscriptions are revealed in the tapscript of script-path spends
+        for (vin, input) in tx.input.iter().enumerate() {
+            let Some(tapscript) = input.witness.tapscript() else {
+                continue;
+            };
+
+            for envelope in self.parse_envelopes(tapscript) {
+                let index = inscriptions.len() as u32;
+                inscriptions.push(InscriptionData {
+                    inscription_id: format!("{}i{}", txid, index),
+                    input_index: vin as u32,
+                    ..envelope
+                });
+            }
+        }
+
+        if !inscriptions.is_empty() {
+            debug!("Parsed {} inscription(s) in {}", inscriptions.len(), txid);
+        }
+
+        Ok(inscriptions)
+    }
+
+    fn parse_envelopes(&self, script: &Script) -> Vec<InscriptionData> {
+        let mut envelopes = Vec::new();
+        let mut instructions = script.instructions().peekable();
+
+        // Look for the `OP_FALSE OP_IF "ord"` envelope header
+        while let Some(Ok(instruction)) = instructions.next() {
+            if !is_empty_push(&instruction) {
+                continue;
+            }
+            if !matches!(instructions.peek(), Some(Ok(Instruction::Op(OP_IF)))) {
+                continue;
+            }
+            instructions.next();
+            match instructions.next() {
+                Some(Ok(Instruction::PushBytes(id))) if id.as_bytes() == PROTOCOL_ID => {}
+                _ => continue,
+            }
+
+            let mut fields: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+            let mut body: Option<Vec<u8>> = None;
+            let mut complete = false;
+
+            while let Some(Ok(instruction)) = instructions.next() {
+                if let Instruction::Op(OP_ENDIF) = instruction {
+                    complete = true;
+                    break;
+                }
+                let Some(bytes) = push_bytes(&instruction) else {
+                    break;
+                };
+
+                match body.as_mut() {
+                    Some(body) => body.extend_from_slice(&bytes),
+                    None if bytes.is_empty() => body = Some(Vec::new()),
+                    None => match instructions.next() {
+                        Some(Ok(value)) => match push_bytes(&value) {
+                            Some(value) => fields.push((bytes, value)),
+                            None => break,
+                        },
+                        _ => break,
+                    },
+                }
+            }
+
+            if !complete {
+                continue;
+            }
+
+            let field = |tag: u8| {
+                fields
+                    .iter()
+                    .find(|(key, _)| key.as_slice() == [tag])
+                    .map(|(_, value)| String::from_utf8_lossy(value).into_owned())
+            };
+
+            envelopes.push(InscriptionData {
+                inscription_id: String::new(),
+                input_index: 0,
+                content_type: field(TAG_CONTENT_TYPE),
+                content_encoding: field(TAG_CONTENT_ENCODING),
+                metaprotocol: field(TAG_METAPROTOCOL),
+                content: body.unwrap_or_default(),
+                location: None,
+            });
+        }
+
+        envelopes
+    }
+}
+
+fn is_empty_push(instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction::PushBytes(bytes) => bytes.is_empty(),
+        Instruction::Op(op) => *op == OP_FALSE,
+    }
+}
+
+fn push_bytes(instruction: &Instruction) -> Option<Vec<u8>> {
+    match instruction {
+        Instruction::PushBytes(bytes) => Some(bytes.as_bytes().to_vec()),
+        // Small tags may be minimally encoded as OP_1..OP_16
+        Instruction::Op(op) => {
+            let code = op.to_u8();
+            if (OP_PUSHNUM_1.to_u8()..=OP_PUSHNUM_16.to_u8()).contains(&code) {
+                Some(vec![code - OP_PUSHNUM_1.to_u8() + 1])
+            } else if *op == OP_PUSHNUM_NEG1 {
+                Some(vec![0x81])
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Finds the output holding the sat at `offset` under first-in-first-out sat flow,
+/// returning the output index and the offset within it.
+pub fn locate_sat(offset: u64, output_values: &[u64]) -> Option<(u32, u64)> {
+    let mut start = 0u64;
+    for (vout, value) in output_values.iter().enumerate() {
+        if offset < start + value {
+            return Some((vout as u32, offset - start));
+        }
+        start += value;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{
+        absolute::LockTime,
+        script::{Builder, PushBytesBuf},
+        transaction::Version,
+        ScriptBuf, TxIn, Witness,
+    };
+
+    /// A reveal transaction spending one script path per tapscript
+    fn reveal(tapscripts: &[ScriptBuf]) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: tapscripts
+                .iter()
+                .map(|script| TxIn {
+                    witness: Witness::from_slice(&[script.as_bytes(), &[0xc0; 33]]),
+                    ..Default::default()
+                })
+                .collect(),
+            output: Vec::new(),
+        }
+    }
+
+    fn push(builder: Builder, bytes: &[u8]) -> Builder {
+        builder.push_slice(PushBytesBuf::try_from(bytes.to_vec()).unwrap())
+    }
+
+    /// Opens an `OP_FALSE OP_IF "ord"` envelope
+    fn envelope(builder: Builder) -> Builder {
+        push(
+            builder.push_opcode(OP_FALSE).push_opcode(OP_IF),
+            PROTOCOL_ID,
+        )
+    }
+
+    fn parse(tapscripts: &[ScriptBuf]) -> Vec<InscriptionData> {
+        InscriptionProcessor::new()
+            .process_transaction(&reveal(tapscripts))
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_fields_and_bodies_split_across_pushes() {
+        // Key path check first, as in ord's reveal scripts
+        let script = push(Builder::new(), &[2; 32]).push_opcode(OP_CHECKSIG);
+        let script = envelope(script).push_opcode(OP_PUSHNUM_1);
+        let script = push(script, b"text/plain;charset=utf-8");
+        let script = push(script, &[TAG_CONTENT_ENCODING]);
+        let script = push(script, b"br");
+        let script = push(script, &[TAG_METAPROTOCOL]);
+        let script = push(script, b"sns");
+        let script = push(script.push_opcode(OP_FALSE), b"Hello, ");
+        let script = push(script, b"world!")
+            .push_opcode(OP_PUSHNUM_5)
+            .push_opcode(OP_ENDIF)
+            .into_script();
+
+        let inscriptions = parse(&[script]);
+        assert_eq!(inscriptions.len(), 1);
+        let inscription = &inscriptions[0];
+        assert_eq!(
+            inscription.content_type.as_deref(),
+            Some("text/plain;charset=utf-8")
+        );
+        assert_eq!(inscription.content_encoding.as_deref(), Some("br"));
+        assert_eq!(inscription.metaprotocol.as_deref(), Some("sns"));
+        assert_eq!(inscription.content, b"Hello, world!\x05");
+        assert_eq!(inscription.location, None);
+    }
+
+    #[test]
+    fn numbers_inscriptions_across_envelopes_and_inputs() {
+        let text = |builder: Builder, body: &[u8]| {
+            let builder = push(envelope(builder), &[TAG_CONTENT_TYPE]);
+            let builder = push(builder, b"text/plain").push_opcode(OP_FALSE);
+            push(builder, body).push_opcode(OP_ENDIF)
+        };
+        let first = text(text(Builder::new(), b"a"), b"b").into_script();
+        let second = text(Builder::new(), b"c").into_script();
+
+        let tx = reveal(&[first, ScriptBuf::new(), second]);
+        let inscriptions = InscriptionProcessor::new()
+            .process_transaction(&tx)
+            .unwrap();
+        let parsed: Vec<_> = inscriptions
+            .iter()
+            .map(|i| (i.inscription_id.clone(), i.input_index, i.content.clone()))
+            .collect();
+        let txid = tx.txid();
+        assert_eq!(
+            parsed,
+            [
+                (format!("{}i0", txid), 0, b"a".to_vec()),
+                (format!("{}i1", txid), 0, b"b".to_vec()),
+                (format!("{}i2", txid), 2, b"c".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn indexes_cursed_envelopes() {
+        // An unknown even tag and a repeated field, both cursed under ord's
+        // rules, with no body at all
+        let script = push(envelope(Builder::new()), &[66]);
+        let script = push(script, b"?");
+        let script = push(script, &[TAG_CONTENT_TYPE]);
+        let script = push(script, b"text/plain");
+        let script = push(script, &[TAG_CONTENT_TYPE]);
+        let script = push(script, b"image/png")
+            .push_opcode(OP_ENDIF)
+            .into_script();
+
+        let inscriptions = parse(&[script]);
+        assert_eq!(inscriptions.len(), 1);
+        assert_eq!(inscriptions[0].content_type.as_deref(), Some("text/plain"));
+        assert!(inscriptions[0].content.is_empty());
+    }
+
+    #[test]
+    fn skips_malformed_envelopes() {
+        let unterminated = push(envelope(Builder::new()).push_opcode(OP_FALSE), b"body");
+        let wrong_protocol = push(
+            Builder::new().push_opcode(OP_FALSE).push_opcode(OP_IF),
+            b"orb",
+        )
+        .push_opcode(OP_ENDIF);
+        let without_false =
+            push(Builder::new().push_opcode(OP_IF), PROTOCOL_ID).push_opcode(OP_ENDIF);
+        let tag_without_value =
+            push(envelope(Builder::new()), &[TAG_CONTENT_TYPE]).push_opcode(OP_ENDIF);
+        for script in [
+            unterminated,
+            wrong_protocol,
+            without_false,
+            tag_without_value,
+        ] {
+            let script = script.into_script();
+            assert!(
+                parse(std::slice::from_ref(&script)).is_empty(),
+                "{}",
+                script
+            );
+        }
+
+        // A non-push opcode ends the envelope, but a later one still parses
+        let script = push(envelope(Builder::new()).push_opcode(OP_FALSE), b"bad")
+            .push_opcode(OP_CHECKSIG)
+            .push_opcode(OP_ENDIF);
+        let script = push(envelope(script).push_opcode(OP_FALSE), b"good")
+            .push_opcode(OP_ENDIF)
+            .into_script();
+        let inscriptions = parse(&[script]);
+        assert_eq!(inscriptions.len(), 1);
+        assert_eq!(inscriptions[0].content, b"good");
+    }
+
+    #[test]
+    fn locates_sats_first_in_first_out() {
+        let outputs = [1_000, 0, 546, 10_000];
+        assert_eq!(locate_sat(0, &outputs), Some((0, 0)));
+        assert_eq!(locate_sat(999, &outputs), Some((0, 999)));
+        // Empty outputs hold no sats
+        assert_eq!(locate_sat(1_000, &outputs), Some((2, 0)));
+        assert_eq!(locate_sat(1_545, &outputs), Some((2, 545)));
+        assert_eq!(locate_sat(1_546, &outputs), Some((3, 0)));
+        assert_eq!(locate_sat(11_545, &outputs), Some((3, 9_999)));
+
+        // Past the outputs the sat goes to the fee
+        assert_eq!(locate_sat(11_546, &outputs), None);
+        assert_eq!(locate_sat(0, &[]), None);
+    }
+
+    #[test]
+    fn locates_fee_sats_in_the_coinbase() {
+        // Fees enter the coinbase after the subsidy, in transaction order
+        let subsidy = 50 * 100_000_000;
+        let coinbase = [subsidy + 300, 200];
+        assert_eq!(locate_sat(subsidy, &coinbase), Some((0, subsidy)));
+        assert_eq!(
+            locate_sat(subsidy + 299, &coinbase),
+            Some((0, subsidy + 299))
+        );
+        assert_eq!(locate_sat(subsidy + 300, &coinbase), Some((1, 0)));
+
+        // Fees the miner did not claim are lost
+        assert_eq!(locate_sat(subsidy + 500, &coinbase), None);
+    }
+}
@@ -1,5 +1,15 @@
 pub mod bitcoin;
+pub mod brc20;
+pub mod bridge;
+pub mod inscriptions;
+pub mod retention;
 pub mod runes;
+pub mod utxo_cache;
 
 pub use bitcoin::BitcoinIndexer;
+pub use brc20::Brc20Processor;
+pub use bridge::BridgeProcessor;
+pub use inscriptions::InscriptionProcessor;
+pub use retention::Pruner;
 pub use runes::RunesProcessor;
+pub use utxo_cache::UtxoCache;
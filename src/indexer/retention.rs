@@ -0,0 +1,221 @@
+use anyhow::Result;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::Config,
+    db::{RetentionPolicy, Storage},
+};
+
+/// Blocks below the tip that are never pruned, whatever the policy. A reorg
+/// rolling back past them would need the spent outputs it can't restore.
+pub const REORG_SAFETY_DEPTH: u64 = 100;
+
+/// Blocks pruned per database transaction
+const PRUNE_WINDOW_BLOCKS: u64 = 100;
+
+/// Prunes what the configured retention policies no longer keep, oldest
+/// blocks first. Each policy records the height it has pruned up to as a
+/// sync cursor, so a restart resumes where it stopped and the API can tell
+/// pruned data apart from data that never existed.
+pub struct Pruner {
+    db: Arc<dyn Storage>,
+    config: Arc<Config>,
+    /// Each enabled policy with the blocks it keeps below the tip
+    policies: Vec<(RetentionPolicy, u64)>,
+}
+
+impl Pruner {
+    /// Returns `None` unless at least one retention policy is configured.
+    pub fn new(db: Arc<dyn Storage>, config: Arc<Config>) -> Option<Self> {
+        // Bridge events may still be rolled back until they are final
+        let min_depth = REORG_SAFETY_DEPTH.max(config.bridge_finality_confirmations);
+
+        let policies: Vec<_> = RetentionPolicy::ALL
+            .into_iter()
+            .filter_map(|policy| {
+                let blocks = match policy {
+                    RetentionPolicy::SpentOutputs => config.retention_spent_outputs_blocks,
+                    RetentionPolicy::Transactions => config.retention_transactions_blocks,
+                    RetentionPolicy::AddressHistory => config.retention_address_history_blocks,
+                }?;
+                if blocks < min_depth {
+                    warn!(
+                        "Retention for {} raised from {} to {} blocks to stay below reorg depth",
+                        policy, blocks, min_depth
+                    );
+                }
+                Some((policy, blocks.max(min_depth)))
+            })
+            .collect();
+
+        if policies.is_empty() {
+            return None;
+        }
+        Some(Self {
+            db,
+            config,
+            policies,
+        })
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        for (policy, blocks) in &self.policies {
+            info!("Retention: pruning {} older than {} blocks", policy, blocks);
+        }
+
+        loop {
+            if let Err(e) = self.run().await {
+                error!("Pruning error: {}", e);
+            }
+
+            sleep(Duration::from_secs(self.config.retention_interval_secs)).await;
+        }
+    }
+
+    async fn run(&self) -> Result<()> {
+        let Some(tip) = self.db.get_last_block_height().await? else {
+            return Ok(());
+        };
+
+        for &(policy, blocks) in &self.policies {
+            let Some(cutoff) = tip.checked_sub(blocks) else {
+                continue;
+            };
+
+            let mut from = match self.db.get_sync_cursor(&policy.cursor()).await? {
+                Some(pruned) => pruned + 1,
+                // Nothing pruned yet, so start at the lowest indexed block
+                None => {
+                    let first = self.db.get_blocks(None, None, None, false, 1).await?;
+                    match first.first() {
+                        Some(block) => block.height as u64,
+                        None => continue,
+                    }
+                }
+            };
+            if from > cutoff {
+                continue;
+            }
+
+            let start = from;
+            let mut removed = 0;
+            while from <= cutoff {
+                let to = (from + PRUNE_WINDOW_BLOCKS - 1).min(cutoff);
+                let rows = self.db.prune(policy, from, to).await?;
+                debug!(
+                    "Pruned {} {} rows from blocks {}..={}",
+                    rows, policy, from, to
+                );
+                removed += rows;
+                from = to + 1;
+            }
+
+            info!(
+                "Pruned {} {} rows from blocks {}..={}",
+                removed, policy, start, cutoff
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{models::Hash256, SqliteDatabase},
+        utils::script::electrum_scripthash,
+    };
+    use bitcoin::{
+        absolute::LockTime, blockdata::constants::genesis_block, hashes::Hash, transaction, Amount,
+        Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, WPubkeyHash, Witness,
+    };
+
+    fn pay(previous_output: OutPoint, script: &ScriptBuf) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(50_000),
+                script_pubkey: script.clone(),
+            }],
+        }
+    }
+
+    async fn history(db: &SqliteDatabase, scripthash: Hash256) -> Vec<(String, i64)> {
+        db.get_scripthash_history(scripthash)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.txid.to_string(), entry.block_height))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn pruned_spends_stay_in_scripthash_history() {
+        let db = SqliteDatabase::new("sqlite::memory:", 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+        let alice = ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array([1; 20]));
+        let bob = ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array([2; 20]));
+
+        // Alice is paid in block 1 and pays Bob in block 2
+        let funding = pay(OutPoint::null(), &alice);
+        let payment = pay(OutPoint::new(funding.txid(), 0), &bob);
+        for (height, tx) in [(1u64, &funding), (2, &payment)] {
+            let mut block = genesis_block(Network::Regtest);
+            block.header.nonce = height as u32;
+            block.txdata = vec![tx.clone()];
+            db.insert_block(&block, height, &[0; 32], None)
+                .await
+                .unwrap();
+            let prevouts = if height == 1 {
+                Vec::new()
+            } else {
+                vec![db.get_output(funding.txid().into(), 0).await.unwrap()]
+            };
+            db.insert_transaction(
+                tx,
+                0,
+                height,
+                &block.header,
+                None,
+                &prevouts,
+                Network::Regtest,
+            )
+            .await
+            .unwrap();
+        }
+        db.mark_output_spent(funding.txid().into(), 0, payment.txid().into(), 0)
+            .await
+            .unwrap();
+
+        let scripthash = electrum_scripthash(&alice);
+        let before = history(&db, scripthash).await;
+        assert_eq!(
+            before,
+            [
+                (funding.txid().to_string(), 1),
+                (payment.txid().to_string(), 2)
+            ]
+        );
+
+        let removed = db.prune(RetentionPolicy::SpentOutputs, 0, 2).await.unwrap();
+        assert_eq!(removed, 1);
+        assert!(db
+            .get_output(funding.txid().into(), 0)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(history(&db, scripthash).await, before);
+        assert_eq!(db.get_scripthash_balance(scripthash).await.unwrap(), 0);
+    }
+}
@@ -2,8 +2,12 @@ use anyhow::Result;
 use bitcoin::{Transaction, TxOut};
 use tracing::debug;
 
-use crate::db::models::{RuneOperation, RunesData};
+use crate::db::models::{RuneEdict, RuneOperation, RunesData};
 
+/// Bytes of an edict in a mint or transfer payload: output (u32 LE), then amount (u64 LE)
+const EDICT_LENGTH: usize = 12;
+
+#[derive(Default)]
 pub struct RunesProcessor;
 
 impl RunesProcessor {
@@ -14,7 +18,7 @@ impl RunesProcessor {
     pub fn process_transaction(&self, tx: &Transaction) -> Result<Option<RunesData>> {
         // Check for Runes protocol in OP_RETURN outputs
         for output in &tx.output {
-            if let Some(runes_data) = self.parse_op_return(&output)? {
+            if let Some(runes_data) = self.parse_op_return(output)? {
                 return Ok(Some(runes_data));
             }
         }
@@ -104,6 +108,7 @@ impl RunesProcessor {
                         "symbol": "RUNE",
                         "decimals": 8,
                     })),
+                    edicts: Vec::new(),
                 }
             }
             RuneOperation::Mint => {
@@ -121,6 +126,7 @@ impl RunesProcessor {
                     from_address: None,
                     to_address: None,
                     metadata: None,
+                    edicts: parse_edicts(payload),
                 }
             }
             RuneOperation::Transfer | RuneOperation::Burn => {
@@ -138,6 +144,7 @@ impl RunesProcessor {
                     from_address: None, // Would extract from inputs
                     to_address: None,   // Would extract from outputs
                     metadata: None,
+                    edicts: parse_edicts(payload),
                 }
             }
         };
@@ -146,3 +153,203 @@ impl RunesProcessor {
         Ok(Some(runes_data))
     }
 }
+
+/// Edicts following the rune id of a mint or transfer payload. A trailing
+/// partial edict is ignored.
+fn parse_edicts(payload: &[u8]) -> Vec<RuneEdict> {
+    payload
+        .get(17..)
+        .unwrap_or_default()
+        .chunks_exact(EDICT_LENGTH)
+        .map(|edict| RuneEdict {
+            output: u32::from_le_bytes(edict[..4].try_into().unwrap()),
+            amount: u64::from_le_bytes(edict[4..].try_into().unwrap()) as u128,
+        })
+        .collect()
+}
+
+/// Splits the runes a mint or transfer moves across `tx`'s outputs, as
+/// `(vout, amount)`. Edicts assign in order, each at most what is left; the
+/// remainder goes to the first output that is not an OP_RETURN. An edict naming
+/// an output past the output count makes the runestone a cenotaph, which burns
+/// everything. Burns and etches allocate nothing.
+pub fn allocate(runes: &RunesData, tx: &Transaction) -> Vec<(u32, u128)> {
+    let (RuneOperation::Mint | RuneOperation::Transfer, Some(mut left)) =
+        (&runes.operation, runes.amount)
+    else {
+        return Vec::new();
+    };
+    let outputs = tx.output.len() as u32;
+    if runes.edicts.iter().any(|edict| edict.output > outputs) {
+        return Vec::new();
+    }
+    let spendable: Vec<u32> = (0..outputs)
+        .filter(|&vout| !tx.output[vout as usize].script_pubkey.is_op_return())
+        .collect();
+
+    let mut allocations: Vec<(u32, u128)> = Vec::new();
+    let mut assign = |vout: u32, amount: u128, left: &mut u128| {
+        let amount = amount.min(*left);
+        if amount > 0 {
+            *left -= amount;
+            allocations.push((vout, amount));
+        }
+    };
+
+    for edict in &runes.edicts {
+        if edict.output < outputs {
+            let amount = if edict.amount == 0 {
+                left
+            } else {
+                edict.amount
+            };
+            assign(edict.output, amount, &mut left);
+        } else if !spendable.is_empty() {
+            if edict.amount == 0 {
+                // All that is left, evenly, the first outputs taking the remainder
+                let count = spendable.len() as u128;
+                let (share, extra) = (left / count, left % count);
+                for (i, &vout) in spendable.iter().enumerate() {
+                    assign(vout, share + u128::from((i as u128) < extra), &mut left);
+                }
+            } else {
+                for &vout in &spendable {
+                    assign(vout, edict.amount, &mut left);
+                }
+            }
+        }
+    }
+    if let Some(&vout) = spendable.first() {
+        assign(vout, left, &mut left);
+    }
+
+    // One entry per output, in output order
+    allocations.sort_by_key(|(vout, _)| *vout);
+    allocations.dedup_by(|(vout, amount), (kept_vout, kept_amount)| {
+        let same = vout == kept_vout;
+        if same {
+            *kept_amount += *amount;
+        }
+        same
+    });
+    allocations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{
+        absolute::LockTime, script::PushBytesBuf, transaction::Version, Amount, ScriptBuf,
+    };
+
+    /// A transaction with `outputs` outputs, the last `op_returns` of them OP_RETURNs
+    fn tx(outputs: usize, op_returns: usize) -> Transaction {
+        let output = (0..outputs)
+            .map(|vout| TxOut {
+                value: Amount::from_sat(546),
+                script_pubkey: if vout + op_returns >= outputs {
+                    ScriptBuf::new_op_return(PushBytesBuf::from([vout as u8]))
+                } else {
+                    ScriptBuf::from_bytes(vec![0x51, vout as u8])
+                },
+            })
+            .collect();
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: Vec::new(),
+            output,
+        }
+    }
+
+    fn transfer(amount: u128, edicts: &[(u32, u128)]) -> RunesData {
+        RunesData {
+            rune_id: Some("RUNE_0000000000000001".to_string()),
+            operation: RuneOperation::Transfer,
+            amount: Some(amount),
+            from_address: None,
+            to_address: None,
+            metadata: None,
+            edicts: edicts
+                .iter()
+                .map(|&(output, amount)| RuneEdict { output, amount })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn parses_edicts_after_the_rune_id() {
+        let mut payload = b"RUNE".to_vec();
+        payload.push(0x02);
+        payload.extend(1000u64.to_le_bytes());
+        payload.extend([7; 8]);
+        payload.extend(1u32.to_le_bytes());
+        payload.extend(300u64.to_le_bytes());
+        // A trailing partial edict
+        payload.extend([0; 5]);
+
+        let mut tx = tx(2, 0);
+        tx.output.push(TxOut {
+            value: Amount::ZERO,
+            script_pubkey: ScriptBuf::new_op_return(PushBytesBuf::try_from(payload).unwrap()),
+        });
+        let runes = RunesProcessor::new()
+            .process_transaction(&tx)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(runes.amount, Some(1000));
+        assert_eq!(runes.rune_id.as_deref(), Some("RUNE_0707070707070707"));
+        assert_eq!(
+            runes.edicts,
+            [RuneEdict {
+                output: 1,
+                amount: 300
+            }]
+        );
+    }
+
+    #[test]
+    fn allocates_by_edict_with_the_rest_to_the_first_output() {
+        let tx = tx(4, 1);
+
+        // Without edicts everything goes to the first non-OP_RETURN output
+        assert_eq!(allocate(&transfer(1000, &[]), &tx), [(0, 1000)]);
+
+        assert_eq!(
+            allocate(&transfer(1000, &[(2, 300), (1, 100)]), &tx),
+            [(0, 600), (1, 100), (2, 300)]
+        );
+        // Edicts take at most what is left; zero takes all of it
+        assert_eq!(
+            allocate(&transfer(1000, &[(2, 800), (1, 800)]), &tx),
+            [(1, 200), (2, 800)]
+        );
+        assert_eq!(allocate(&transfer(1000, &[(1, 0)]), &tx), [(1, 1000)]);
+        // Runes assigned to an OP_RETURN are burned there
+        assert_eq!(
+            allocate(&transfer(1000, &[(3, 400)]), &tx),
+            [(0, 600), (3, 400)]
+        );
+
+        // The output count splits across every non-OP_RETURN output
+        assert_eq!(
+            allocate(&transfer(1000, &[(4, 0)]), &tx),
+            [(0, 334), (1, 333), (2, 333)]
+        );
+        assert_eq!(
+            allocate(&transfer(1000, &[(4, 100)]), &tx),
+            [(0, 800), (1, 100), (2, 100)]
+        );
+
+        // Past the output count, the runestone is a cenotaph
+        assert!(allocate(&transfer(1000, &[(1, 100), (5, 1)]), &tx).is_empty());
+
+        let burn = RunesData {
+            operation: RuneOperation::Burn,
+            ..transfer(1000, &[])
+        };
+        assert!(allocate(&burn, &tx).is_empty());
+        assert!(allocate(&transfer(1000, &[]), &self::tx(1, 1)).is_empty());
+    }
+}
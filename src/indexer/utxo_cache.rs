@@ -0,0 +1,472 @@
+use anyhow::{anyhow, Result};
+use bitcoin::{
+    consensus::encode::{deserialize, serialize},
+    Network, OutPoint, Transaction,
+};
+use lru::LruCache;
+use std::{collections::HashSet, num::NonZeroUsize, sync::Mutex};
+use tracing::info;
+
+use crate::db::{
+    models::{Hash256, OutputModel},
+    Storage,
+};
+use crate::indexer::retention::REORG_SAFETY_DEPTH;
+use crate::utils::script::electrum_scripthash;
+
+/// Key holding the height the on-disk state was flushed at. Outpoint keys are
+/// 36 bytes, so it can't collide with them.
+const FLUSHED_HEIGHT_KEY: &[u8] = b"flushed_height";
+
+/// Unspent outputs fetched per query when rebuilding
+const REBUILD_BATCH_SIZE: i64 = 10_000;
+
+/// Length of a serialized outpoint
+const OUTPOINT_LEN: usize = 36;
+
+/// Unspent outputs for prevout lookups during indexing, kept in memory and
+/// spilled least-recently-used first to an embedded sled store.
+///
+/// The database stays the source of truth. Changes are flushed once a block
+/// is committed; a block that fails is undone with [`UtxoCache::discard`].
+/// The changes of the last `REORG_SAFETY_DEPTH` flushed blocks are kept so a
+/// reorg can undo them with [`UtxoCache::rollback`]; after a crash or a
+/// deeper reorg the cache is rebuilt from `outputs`.
+pub struct UtxoCache {
+    disk: sled::Db,
+    /// Undo records of flushed blocks. Each block has a marker keyed by its
+    /// height and its changes keyed by height and sequence number.
+    undo: sled::Tree,
+    state: Mutex<State>,
+}
+
+struct State {
+    memory: LruCache<OutPoint, OutputModel>,
+    /// Outputs only held in memory, written to disk when evicted or flushed
+    dirty: HashSet<OutPoint>,
+    /// Changes since the last flush, newest last
+    undo: Vec<Undo>,
+    /// Whether the disk holds exactly the state at `FLUSHED_HEIGHT_KEY`
+    flushed: bool,
+    /// Height the cache is at once the changes since the last flush are
+    /// discarded
+    height: Option<u64>,
+}
+
+enum Undo {
+    Created(OutPoint),
+    Spent(OutPoint, OutputModel),
+}
+
+impl Undo {
+    /// The outpoint, followed by the output's JSON for a spend
+    fn encode(&self) -> Result<Vec<u8>> {
+        Ok(match self {
+            Undo::Created(outpoint) => serialize(outpoint),
+            Undo::Spent(outpoint, output) => {
+                let mut value = serialize(outpoint);
+                value.extend(encode(output)?);
+                value
+            }
+        })
+    }
+
+    fn decode(value: &[u8]) -> Result<Self> {
+        if value.len() < OUTPOINT_LEN {
+            return Err(anyhow!("Truncated UTXO cache undo record"));
+        }
+        let (outpoint, output) = value.split_at(OUTPOINT_LEN);
+        let outpoint = deserialize(outpoint)?;
+        Ok(if output.is_empty() {
+            Undo::Created(outpoint)
+        } else {
+            Undo::Spent(outpoint, serde_json::from_slice(output)?)
+        })
+    }
+}
+
+impl UtxoCache {
+    pub fn open(dir: &str, capacity: usize) -> Result<Self> {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        let disk = sled::open(dir)?;
+        let undo = disk.open_tree("undo")?;
+        let height = disk
+            .get(FLUSHED_HEIGHT_KEY)?
+            .and_then(|value| value.as_ref().try_into().ok())
+            .map(u64::from_be_bytes);
+
+        Ok(Self {
+            disk,
+            undo,
+            state: Mutex::new(State {
+                memory: LruCache::new(capacity),
+                dirty: HashSet::new(),
+                undo: Vec::new(),
+                flushed: height.is_some(),
+                height,
+            }),
+        })
+    }
+
+    /// Reuses the on-disk state if it was flushed at the database's tip,
+    /// otherwise rebuilds it.
+    pub async fn load(&self, db: &dyn Storage) -> Result<()> {
+        let flushed_height = self.state.lock().unwrap().height;
+
+        match db.get_last_block_height().await? {
+            Some(height) if flushed_height == Some(height) => {
+                info!("UTXO cache is current at height {}", height);
+                Ok(())
+            }
+            _ => self.rebuild(db).await,
+        }
+    }
+
+    /// Replaces the cache with the database's unspent outputs.
+    pub async fn rebuild(&self, db: &dyn Storage) -> Result<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.memory.clear();
+            state.dirty.clear();
+            state.undo.clear();
+            state.flushed = false;
+            state.height = None;
+        }
+        self.disk.clear()?;
+        self.undo.clear()?;
+
+        let mut after = None;
+        let mut count = 0;
+        loop {
+            let outputs = db.get_unspent_outputs(after, REBUILD_BATCH_SIZE).await?;
+            let Some(last) = outputs.last() else {
+                break;
+            };
+            after = Some((last.txid, last.vout));
+            count += outputs.len();
+
+            let mut batch = sled::Batch::default();
+            for output in &outputs {
+                batch.insert(outpoint_key(output.txid, output.vout), encode(output)?);
+            }
+            self.disk.apply_batch(batch)?;
+        }
+
+        if let Some(height) = db.get_last_block_height().await? {
+            self.disk
+                .insert(FLUSHED_HEIGHT_KEY, &height.to_be_bytes())?;
+            let mut state = self.state.lock().unwrap();
+            state.flushed = true;
+            state.height = Some(height);
+        }
+        self.disk.flush_async().await?;
+
+        info!("Rebuilt UTXO cache with {} outputs", count);
+        Ok(())
+    }
+
+    pub fn get(&self, outpoint: &OutPoint) -> Result<Option<OutputModel>> {
+        if let Some(output) = self.state.lock().unwrap().memory.get(outpoint) {
+            return Ok(Some(output.clone()));
+        }
+
+        // Spilled outputs are read in place; they are usually about to be spent
+        match self.disk.get(serialize(outpoint))? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Adds the spendable outputs of a newly indexed transaction, with
+    /// addresses encoded for `network`.
+    pub fn insert_outputs(&self, tx: &Transaction, network: Network) -> Result<()> {
+        let txid = tx.txid();
+        let mut state = self.state.lock().unwrap();
+        self.begin(&mut state)?;
+
+        for (vout, output) in tx.output.iter().enumerate() {
+            if output.script_pubkey.is_op_return() {
+                continue;
+            }
+            let outpoint = OutPoint::new(txid, vout as u32);
+            let model = OutputModel {
+                txid: txid.into(),
+                vout: vout as i32,
+                value: output.value.to_sat() as i64,
+                script_pubkey: output.script_pubkey.as_script().into(),
+                address: bitcoin::Address::from_script(&output.script_pubkey, network)
+                    .ok()
+                    .map(|a| a.to_string()),
+                spent: false,
+                spending_txid: None,
+                spending_vin: None,
+                scripthash: Some(electrum_scripthash(&output.script_pubkey)),
+            };
+
+            self.put(&mut state, outpoint, model)?;
+            state.undo.push(Undo::Created(outpoint));
+        }
+
+        Ok(())
+    }
+
+    pub fn spend(&self, outpoint: &OutPoint) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        self.begin(&mut state)?;
+
+        let spent = match state.memory.pop(outpoint) {
+            Some(output) => {
+                if !state.dirty.remove(outpoint) {
+                    self.disk.remove(serialize(outpoint))?;
+                }
+                Some(output)
+            }
+            None => match self.disk.remove(serialize(outpoint))? {
+                Some(value) => Some(serde_json::from_slice(&value)?),
+                None => None,
+            },
+        };
+
+        if let Some(output) = spent {
+            state.undo.push(Undo::Spent(*outpoint, output));
+        }
+        Ok(())
+    }
+
+    /// Persists everything up to `height`, which the database has committed,
+    /// and keeps the block's changes for [`UtxoCache::rollback`].
+    pub async fn flush(&self, height: u64) -> Result<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+
+            // Written first: if the rest fails the flushed height stays
+            // cleared and the next start rebuilds, dropping the records too
+            let mut records = sled::Batch::default();
+            for key in self.undo.scan_prefix(height.to_be_bytes()).keys() {
+                records.remove(key?);
+            }
+            if let Some(expired) = height.checked_sub(REORG_SAFETY_DEPTH) {
+                for key in self.undo.range(..(expired + 1).to_be_bytes()).keys() {
+                    records.remove(key?);
+                }
+            }
+            records.insert(&height.to_be_bytes(), &[]);
+            for (seq, undo) in state.undo.drain(..).enumerate() {
+                records.insert(undo_key(height, seq as u32), undo.encode()?);
+            }
+            self.undo.apply_batch(records)?;
+
+            self.write_dirty(&mut state, height)?;
+        }
+        self.disk.flush_async().await?;
+
+        Ok(())
+    }
+
+    /// Reverts the changes made since the last flush, for a block that failed
+    /// part way and was rolled back in the database.
+    pub fn discard(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        while let Some(undo) = state.undo.pop() {
+            self.revert(&mut state, undo)?;
+        }
+
+        Ok(())
+    }
+
+    /// Undoes the blocks from `height` up, which the database has rolled back
+    /// in a reorg. Replays their undo records, and rebuilds when any is
+    /// missing, as for reorgs deeper than `REORG_SAFETY_DEPTH`.
+    pub async fn rollback(&self, height: u64, db: &dyn Storage) -> Result<()> {
+        self.discard()?;
+
+        let Some(tip) = self.state.lock().unwrap().height else {
+            return self.rebuild(db).await;
+        };
+        if height > tip {
+            return Ok(());
+        }
+        let Some(resume) = height.checked_sub(1) else {
+            return self.rebuild(db).await;
+        };
+
+        let mut blocks = Vec::new();
+        for block in height..=tip {
+            if !self.undo.contains_key(block.to_be_bytes())? {
+                info!(
+                    "UTXO cache has no undo record for block {}, rebuilding",
+                    block
+                );
+                return self.rebuild(db).await;
+            }
+            let records = self
+                .undo
+                .scan_prefix(block.to_be_bytes())
+                .filter_map(|entry| match entry {
+                    Ok((key, _)) if key.len() == 8 => None,
+                    Ok((_, value)) => Some(Undo::decode(&value)),
+                    Err(err) => Some(Err(err.into())),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            blocks.push(records);
+        }
+
+        {
+            let mut state = self.state.lock().unwrap();
+            self.begin(&mut state)?;
+            for undo in blocks
+                .into_iter()
+                .rev()
+                .flat_map(|records| records.into_iter().rev())
+            {
+                self.revert(&mut state, undo)?;
+            }
+
+            let mut records = sled::Batch::default();
+            for key in self.undo.range(height.to_be_bytes()..).keys() {
+                records.remove(key?);
+            }
+            self.undo.apply_batch(records)?;
+
+            self.write_dirty(&mut state, resume)?;
+        }
+        self.disk.flush_async().await?;
+
+        info!("Rolled back UTXO cache to height {}", resume);
+        Ok(())
+    }
+
+    /// Writes the outputs only held in memory and marks the disk as flushed
+    /// at `height`.
+    fn write_dirty(&self, state: &mut State, height: u64) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for outpoint in state.dirty.drain().collect::<Vec<_>>() {
+            if let Some(output) = state.memory.peek(&outpoint) {
+                batch.insert(serialize(&outpoint), encode(output)?);
+            }
+        }
+        batch.insert(FLUSHED_HEIGHT_KEY, &height.to_be_bytes());
+        self.disk.apply_batch(batch)?;
+
+        state.undo.clear();
+        state.flushed = true;
+        state.height = Some(height);
+        Ok(())
+    }
+
+    fn revert(&self, state: &mut State, undo: Undo) -> Result<()> {
+        match undo {
+            Undo::Created(outpoint) => {
+                state.memory.pop(&outpoint);
+                if !state.dirty.remove(&outpoint) {
+                    self.disk.remove(serialize(&outpoint))?;
+                }
+            }
+            Undo::Spent(outpoint, output) => self.put(state, outpoint, output)?,
+        }
+        Ok(())
+    }
+
+    /// Clears the flushed height before the disk first diverges from it, so a
+    /// crash mid-block leads to a rebuild.
+    fn begin(&self, state: &mut State) -> Result<()> {
+        if state.flushed {
+            self.disk.remove(FLUSHED_HEIGHT_KEY)?;
+            state.flushed = false;
+        }
+        Ok(())
+    }
+
+    fn put(&self, state: &mut State, outpoint: OutPoint, output: OutputModel) -> Result<()> {
+        state.dirty.insert(outpoint);
+        if let Some((evicted, output)) = state.memory.push(outpoint, output) {
+            if evicted != outpoint && state.dirty.remove(&evicted) {
+                self.disk.insert(serialize(&evicted), encode(&output)?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn undo_key(height: u64, seq: u32) -> Vec<u8> {
+    let mut key = height.to_be_bytes().to_vec();
+    key.extend(seq.to_be_bytes());
+    key
+}
+
+fn outpoint_key(txid: Hash256, vout: i32) -> Vec<u8> {
+    serialize(&OutPoint::new(txid.into(), vout as u32))
+}
+
+fn encode(output: &OutputModel) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(output)?)
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::SqliteDatabase;
+    use bitcoin::{blockdata::constants::genesis_block, Amount, TxOut};
+
+    /// A transaction with one output per value
+    fn transaction(values: &[u64]) -> Transaction {
+        let mut tx = genesis_block(Network::Regtest).txdata[0].clone();
+        let script_pubkey = tx.output[0].script_pubkey.clone();
+        tx.output = values
+            .iter()
+            .map(|value| TxOut {
+                value: Amount::from_sat(*value),
+                script_pubkey: script_pubkey.clone(),
+            })
+            .collect();
+        tx
+    }
+
+    #[tokio::test]
+    async fn rolls_back_flushed_blocks() {
+        let dir = std::env::temp_dir().join(format!("utxo-cache-{}", std::process::id()));
+        let db = SqliteDatabase::new("sqlite::memory:", 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+        // A single slot so most outputs live on disk
+        let cache = UtxoCache::open(dir.to_str().unwrap(), 1).unwrap();
+
+        let first = transaction(&[1, 2]);
+        cache.insert_outputs(&first, Network::Regtest).unwrap();
+        cache.flush(1).await.unwrap();
+
+        let second = transaction(&[3]);
+        cache.spend(&OutPoint::new(first.txid(), 0)).unwrap();
+        cache.insert_outputs(&second, Network::Regtest).unwrap();
+        cache.flush(2).await.unwrap();
+
+        // Left pending when the reorg is found
+        let third = transaction(&[4]);
+        cache.insert_outputs(&third, Network::Regtest).unwrap();
+
+        cache.rollback(2, &db).await.unwrap();
+        let value = |tx: &Transaction, vout| {
+            cache
+                .get(&OutPoint::new(tx.txid(), vout))
+                .unwrap()
+                .map(|output| output.value)
+        };
+        assert_eq!(value(&first, 0), Some(1));
+        assert_eq!(value(&first, 1), Some(2));
+        assert_eq!(value(&second, 0), None);
+        assert_eq!(value(&third, 0), None);
+        assert_eq!(
+            cache.disk.get(FLUSHED_HEIGHT_KEY).unwrap().as_deref(),
+            Some(&1u64.to_be_bytes()[..])
+        );
+        assert!(!cache.undo.contains_key(2u64.to_be_bytes()).unwrap());
+
+        // Nothing is left to replay for block 0, so it is rebuilt from the
+        // (empty) database
+        cache.rollback(0, &db).await.unwrap();
+        assert_eq!(value(&first, 1), None);
+
+        drop(cache);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -2,8 +2,9 @@ pub mod api;
 pub mod config;
 pub mod db;
 pub mod indexer;
+pub mod starknet;
 pub mod utils;
 
 pub use config::Config;
-pub use db::Database;
+pub use db::Storage;
 pub use indexer::BitcoinIndexer;
@@ -1,13 +1,14 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use starknet_btc_indexer::{
-    api::{create_api_router, ApiState},
+    api::{create_api_router, electrum::ElectrumServer, ApiState},
     config::Config,
-    db::Database,
-    indexer::BitcoinIndexer,
+    db::{self, migrate::MigrationState, ContentStore, Storage},
+    indexer::{BitcoinIndexer, Pruner},
+    starknet::{HeaderRelayer, WithdrawalMonitor},
 };
 use std::sync::Arc;
 use tokio::signal;
-use tracing::{info, error};
+use tracing::{error, info};
 
 // Use Server from Hyper 0.14 as it is compatible with Axum 0.6
 use hyper::Server;
@@ -19,11 +20,17 @@ async fn main() -> Result<()> {
     let config = Config::from_env()?;
     info!("Configuration loaded");
 
-    let db = Database::new(&config.database_url).await?;
-    db.run_migrations().await?;
+    let db = db::connect(&config).await?;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate") {
+        return migrate(db.as_ref(), &config, &args[1..]).await;
+    }
+
+    apply_schema(db.as_ref(), &config).await?;
     info!("Database initialized");
 
-    let db = Arc::new(db);
+    let api_db = db::connect_reader(&config, &db).await?;
     let config = Arc::new(config);
 
     let indexer = BitcoinIndexer::new(db.clone(), config.clone()).await?;
@@ -33,9 +40,48 @@ async fn main() -> Result<()> {
         }
     });
 
+    let withdrawal_monitor = WithdrawalMonitor::new(db.clone(), config.clone())?;
+    let withdrawal_handle = withdrawal_monitor.map(|monitor| {
+        tokio::spawn(async move {
+            if let Err(e) = monitor.start().await {
+                error!("Withdrawal monitor error: {}", e);
+            }
+        })
+    });
+
+    let header_relayer = HeaderRelayer::new(db.clone(), config.clone())?;
+    let relayer_handle = header_relayer.map(|relayer| {
+        tokio::spawn(async move {
+            if let Err(e) = relayer.start().await {
+                error!("Header relayer error: {}", e);
+            }
+        })
+    });
+
+    let pruner_handle = Pruner::new(db.clone(), config.clone()).map(|pruner| {
+        tokio::spawn(async move {
+            if let Err(e) = pruner.start().await {
+                error!("Pruner error: {}", e);
+            }
+        })
+    });
+
+    let electrum_handle = ElectrumServer::new(api_db.clone(), config.clone()).map(|server| {
+        tokio::spawn(async move {
+            if let Err(e) = server.start().await {
+                error!("Electrum server error: {}", e);
+            }
+        })
+    });
+
     let api_state = ApiState {
-        db: db.clone(),
+        db: api_db,
+        writer: db.clone(),
         config: config.clone(),
+        content_store: config
+            .inscription_content_dir
+            .as_ref()
+            .map(ContentStore::new),
     };
 
     let app = create_api_router(api_state);
@@ -50,11 +96,72 @@ async fn main() -> Result<()> {
         .await?;
 
     indexer_handle.abort();
+    for handle in [
+        withdrawal_handle,
+        relayer_handle,
+        pruner_handle,
+        electrum_handle,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        handle.abort();
+    }
     info!("Shutting down gracefully");
 
     Ok(())
 }
 
+async fn apply_schema(db: &dyn Storage, config: &Config) -> Result<()> {
+    db.run_migrations().await?;
+    if config.timescaledb_enabled {
+        db.enable_timescaledb().await?;
+    }
+    Ok(())
+}
+
+/// `migrate [run|status|verify|baseline <version>]`: manages the schema without
+/// starting the indexer.
+async fn migrate(db: &dyn Storage, config: &Config, args: &[String]) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] | ["run"] => apply_schema(db, config).await?,
+        ["status"] => {
+            for status in db.migration_status().await? {
+                println!(
+                    "{:>4}  {:<18} {}",
+                    status.version, status.state, status.description
+                );
+            }
+        }
+        // Succeeds only when the database is exactly at this build's schema
+        ["verify"] => {
+            let statuses = db.migration_status().await?;
+            let problems: Vec<_> = statuses
+                .iter()
+                .filter(|status| status.state != MigrationState::Applied)
+                .collect();
+            for status in &problems {
+                println!(
+                    "{:>4}  {:<18} {}",
+                    status.version, status.state, status.description
+                );
+            }
+            if !problems.is_empty() {
+                bail!("{} migration(s) not applied as embedded", problems.len());
+            }
+            println!("All {} migrations applied and verified", statuses.len());
+        }
+        ["baseline", version] => {
+            let recorded = db.baseline_migrations(version.parse()?).await?;
+            println!("Recorded {} migration(s) as applied", recorded);
+        }
+        _ => bail!("usage: migrate [run|status|verify|baseline <version>]"),
+    }
+
+    Ok(())
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
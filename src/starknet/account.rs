@@ -0,0 +1,269 @@
+use anyhow::{anyhow, Result};
+use starknet_crypto::{poseidon_hash_many, rfc6979_generate_k, sign, FieldElement};
+
+use crate::starknet::encoding::short_string;
+
+/// A call dispatched through an account's `__execute__`
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub to: FieldElement,
+    pub selector: FieldElement,
+    pub calldata: Vec<FieldElement>,
+}
+
+/// Most a transaction may consume of one resource, and pay per unit of it in fri
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceBound {
+    pub max_amount: u64,
+    pub max_price_per_unit: u128,
+}
+
+impl ResourceBound {
+    /// Packs the bound under its resource name as hashed by version 3
+    /// transactions: name, then max amount (64 bits), then max price (128 bits).
+    fn felt(&self, name: &str) -> FieldElement {
+        let mut bytes = [0u8; 32];
+        bytes[8 - name.len()..8].copy_from_slice(name.as_bytes());
+        bytes[8..16].copy_from_slice(&self.max_amount.to_be_bytes());
+        bytes[16..].copy_from_slice(&self.max_price_per_unit.to_be_bytes());
+        FieldElement::from_bytes_be(&bytes).expect("248-bit value fits in a felt")
+    }
+}
+
+/// Fee limits of a version 3 transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceBounds {
+    pub l1_gas: ResourceBound,
+    pub l1_data_gas: ResourceBound,
+    pub l2_gas: ResourceBound,
+}
+
+impl ResourceBounds {
+    /// Most the transaction can be charged in fri, `tip` per unit of L2 gas included.
+    pub fn max_fee(&self, tip: u64) -> u128 {
+        [self.l1_gas, self.l1_data_gas, self.l2_gas]
+            .iter()
+            .map(|bound| (bound.max_amount as u128).saturating_mul(bound.max_price_per_unit))
+            .fold(0u128, u128::saturating_add)
+            .saturating_add((self.l2_gas.max_amount as u128).saturating_mul(tip as u128))
+    }
+}
+
+/// A signed version 3 invoke transaction, ready for `starknet_addInvokeTransaction`.
+/// Fees are paid in STRK, with nonce and fee data availability on L1.
+#[derive(Debug, Clone)]
+pub struct SignedInvoke {
+    pub transaction_hash: FieldElement,
+    pub sender_address: FieldElement,
+    pub calldata: Vec<FieldElement>,
+    pub resource_bounds: ResourceBounds,
+    pub tip: u64,
+    pub nonce: FieldElement,
+    pub signature: Vec<FieldElement>,
+}
+
+/// Account contract controlled by a single Stark key
+pub struct Account {
+    address: FieldElement,
+    private_key: FieldElement,
+}
+
+impl Account {
+    pub fn new(address: &str, private_key: &str) -> Result<Self> {
+        Ok(Self {
+            address: FieldElement::from_hex_be(address)
+                .map_err(|e| anyhow!("Invalid Starknet account address: {}", e))?,
+            private_key: FieldElement::from_hex_be(private_key)
+                .map_err(|e| anyhow!("Invalid Starknet private key: {}", e))?,
+        })
+    }
+
+    pub fn address(&self) -> FieldElement {
+        self.address
+    }
+
+    /// `__execute__` calldata for `calls` in the Cairo 1 multicall encoding.
+    pub fn execute_calldata(calls: &[Call]) -> Vec<FieldElement> {
+        let mut calldata = vec![FieldElement::from(calls.len() as u64)];
+        for call in calls {
+            calldata.push(call.to);
+            calldata.push(call.selector);
+            calldata.push(FieldElement::from(call.calldata.len() as u64));
+            calldata.extend_from_slice(&call.calldata);
+        }
+        calldata
+    }
+
+    /// Builds and signs a version 3 invoke of `calls`.
+    pub fn sign_invoke(
+        &self,
+        calls: &[Call],
+        chain_id: &str,
+        nonce: FieldElement,
+        resource_bounds: ResourceBounds,
+        tip: u64,
+    ) -> Result<SignedInvoke> {
+        let calldata = Self::execute_calldata(calls);
+
+        let chain_id = match chain_id.strip_prefix("0x") {
+            Some(_) => FieldElement::from_hex_be(chain_id).ok(),
+            None => short_string(chain_id),
+        }
+        .ok_or_else(|| anyhow!("Invalid Starknet chain id {}", chain_id))?;
+
+        let transaction_hash = poseidon_hash_many(&[
+            short_string("invoke").expect("ASCII prefix"),
+            FieldElement::THREE,
+            self.address,
+            poseidon_hash_many(&[
+                FieldElement::from(tip),
+                resource_bounds.l1_gas.felt("L1_GAS"),
+                resource_bounds.l2_gas.felt("L2_GAS"),
+                resource_bounds.l1_data_gas.felt("L1_DATA"),
+            ]),
+            // No paymaster data
+            poseidon_hash_many(&[]),
+            chain_id,
+            nonce,
+            // Nonce and fee data availability modes, both L1
+            FieldElement::ZERO,
+            // No account deployment data
+            poseidon_hash_many(&[]),
+            poseidon_hash_many(&calldata),
+        ]);
+
+        let k = rfc6979_generate_k(&transaction_hash, &self.private_key, None);
+        let signature = sign(&self.private_key, &transaction_hash, &k)
+            .map_err(|e| anyhow!("Failed to sign invoke transaction: {}", e))?;
+
+        Ok(SignedInvoke {
+            transaction_hash,
+            sender_address: self.address,
+            calldata,
+            resource_bounds,
+            tip,
+            nonce,
+            signature: vec![signature.r, signature.s],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet_crypto::{get_public_key, verify};
+
+    const ADDRESS: &str = "0x4d2";
+    const PRIVATE_KEY: &str = "0x139fe4d6f02e666e86a6f58e65060f115cd3c185bd9e98bd829636931458f79";
+
+    fn felt(value: u64) -> FieldElement {
+        FieldElement::from(value)
+    }
+
+    fn bounds() -> ResourceBounds {
+        ResourceBounds {
+            l1_gas: ResourceBound {
+                max_amount: 0,
+                max_price_per_unit: 0x5af3107a4000,
+            },
+            l1_data_gas: ResourceBound {
+                max_amount: 0x600,
+                max_price_per_unit: 0x1000,
+            },
+            l2_gas: ResourceBound {
+                max_amount: 0x100000,
+                max_price_per_unit: 0x2540be400,
+            },
+        }
+    }
+
+    /// Packs a resource bound arithmetically, independently of `ResourceBound::felt`
+    fn bound_felt(name: &str, bound: &ResourceBound) -> FieldElement {
+        let shift = |bits: usize| {
+            FieldElement::from_hex_be(&format!("0x1{}", "0".repeat(bits / 4))).unwrap()
+        };
+        short_string(name).unwrap() * shift(192)
+            + felt(bound.max_amount) * shift(128)
+            + FieldElement::from_hex_be(&format!("{:#x}", bound.max_price_per_unit)).unwrap()
+    }
+
+    #[test]
+    fn signs_v3_invokes() {
+        let account = Account::new(ADDRESS, PRIVATE_KEY).unwrap();
+        let call = Call {
+            to: felt(0xc0),
+            selector: felt(0x5e),
+            calldata: vec![felt(1), felt(2)],
+        };
+
+        let invoke = account
+            .sign_invoke(&[call], "SN_SEPOLIA", felt(3), bounds(), 7)
+            .unwrap();
+
+        // Cairo 1 multicall: call count, then each call's target, selector and calldata
+        let calldata = [1, 0xc0, 0x5e, 2, 1, 2].map(felt).to_vec();
+        assert_eq!(invoke.calldata, calldata);
+
+        let bounds = bounds();
+        let expected = poseidon_hash_many(&[
+            FieldElement::from_hex_be("0x696e766f6b65").unwrap(), // "invoke"
+            felt(3),
+            felt(0x4d2),
+            poseidon_hash_many(&[
+                felt(7),
+                bound_felt("L1_GAS", &bounds.l1_gas),
+                bound_felt("L2_GAS", &bounds.l2_gas),
+                bound_felt("L1_DATA", &bounds.l1_data_gas),
+            ]),
+            poseidon_hash_many(&[]),
+            FieldElement::from_hex_be("0x534e5f5345504f4c4941").unwrap(), // "SN_SEPOLIA"
+            felt(3),
+            FieldElement::ZERO,
+            poseidon_hash_many(&[]),
+            poseidon_hash_many(&calldata),
+        ]);
+        assert_eq!(invoke.transaction_hash, expected);
+        assert_eq!(invoke.sender_address, felt(0x4d2));
+        assert_eq!((invoke.nonce, invoke.tip), (felt(3), 7));
+        assert_eq!(invoke.resource_bounds, bounds);
+
+        let public_key = get_public_key(&FieldElement::from_hex_be(PRIVATE_KEY).unwrap());
+        let [r, s] = invoke.signature[..] else {
+            panic!("expected an (r, s) signature");
+        };
+        assert!(verify(&public_key, &expected, &r, &s).unwrap());
+    }
+
+    #[test]
+    fn bounds_the_total_fee() {
+        let bounds = bounds();
+        let gas = 0x600 * 0x1000 + 0x100000 * 0x2540be400u128;
+        assert_eq!(bounds.max_fee(0), gas);
+        assert_eq!(bounds.max_fee(2), gas + 2 * 0x100000);
+
+        let unbounded = ResourceBound {
+            max_amount: u64::MAX,
+            max_price_per_unit: u128::MAX,
+        };
+        let bounds = ResourceBounds {
+            l2_gas: unbounded,
+            ..bounds
+        };
+        assert_eq!(bounds.max_fee(0), u128::MAX);
+    }
+
+    #[test]
+    fn accepts_chain_ids_as_names_or_hex() {
+        let account = Account::new(ADDRESS, PRIVATE_KEY).unwrap();
+        let sign = |chain_id| account.sign_invoke(&[], chain_id, felt(0), bounds(), 0);
+
+        let named = sign("SN_MAIN").unwrap();
+        let hex = sign("0x534e5f4d41494e").unwrap();
+        assert_eq!(named.transaction_hash, hex.transaction_hash);
+        // Signing is deterministic (RFC 6979)
+        assert_eq!(named.signature, hex.signature);
+
+        assert!(sign("0xnot-hex").is_err());
+        assert!(sign(&"x".repeat(32)).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use starknet_crypto::FieldElement;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::starknet::{
+    account::{ResourceBound, ResourceBounds, SignedInvoke},
+    encoding::felt_hex,
+};
+
+/// JSON-RPC error code for an unknown transaction hash
+const TXN_HASH_NOT_FOUND: i64 = 29;
+
+/// Version of a version 3 transaction that is only estimated, never executed
+const QUERY_VERSION_3: &str = "0x100000000000000000000000000000003";
+
+/// Minimal Starknet JSON-RPC client
+pub struct StarknetClient {
+    http: reqwest::Client,
+    url: String,
+    next_id: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmittedEvent {
+    pub from_address: String,
+    pub keys: Vec<String>,
+    pub data: Vec<String>,
+    pub block_hash: Option<String>,
+    pub block_number: Option<u64>,
+    pub transaction_hash: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventsChunk {
+    pub events: Vec<EmittedEvent>,
+    pub continuation_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventFilter {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub address: String,
+    pub keys: Vec<Vec<String>>,
+    pub chunk_size: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransactionStatus {
+    /// `RECEIVED`, `REJECTED`, `ACCEPTED_ON_L2` or `ACCEPTED_ON_L1`
+    pub finality_status: String,
+    /// `SUCCEEDED` or `REVERTED` once executed
+    pub execution_status: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+/// Resources a transaction is estimated to consume, and their current prices in fri
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeeEstimate {
+    pub l1_gas_consumed: u64,
+    pub l1_gas_price: u128,
+    pub l1_data_gas_consumed: u64,
+    pub l1_data_gas_price: u128,
+    pub l2_gas_consumed: u64,
+    pub l2_gas_price: u128,
+}
+
+#[derive(Deserialize)]
+struct RawFeeEstimate {
+    l1_gas_consumed: String,
+    l1_gas_price: String,
+    l1_data_gas_consumed: String,
+    l1_data_gas_price: String,
+    l2_gas_consumed: String,
+    l2_gas_price: String,
+}
+
+#[derive(Deserialize)]
+struct InvokeResult {
+    transaction_hash: String,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl StarknetClient {
+    pub fn new(url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            url: url.to_string(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub async fn block_number(&self) -> Result<u64> {
+        self.request("starknet_blockNumber", json!([])).await
+    }
+
+    pub async fn get_events(
+        &self,
+        filter: &EventFilter,
+        continuation_token: Option<&str>,
+    ) -> Result<EventsChunk> {
+        let mut params = json!({
+            "from_block": { "block_number": filter.from_block },
+            "to_block": { "block_number": filter.to_block },
+            "address": filter.address,
+            "keys": filter.keys,
+            "chunk_size": filter.chunk_size,
+        });
+        if let Some(token) = continuation_token {
+            params["continuation_token"] = json!(token);
+        }
+
+        self.request("starknet_getEvents", json!({ "filter": params }))
+            .await
+    }
+
+    pub async fn chain_id(&self) -> Result<String> {
+        self.request("starknet_chainId", json!([])).await
+    }
+
+    pub async fn get_nonce(&self, address: &FieldElement) -> Result<FieldElement> {
+        let nonce: String = self
+            .request("starknet_getNonce", json!(["pending", felt_hex(address)]))
+            .await?;
+        FieldElement::from_hex_be(&nonce).map_err(|e| anyhow!("Invalid nonce {}: {}", nonce, e))
+    }
+
+    /// Estimates the resources an invoke of `calldata` from `sender` consumes,
+    /// skipping the account's signature validation.
+    pub async fn estimate_invoke_fee(
+        &self,
+        sender: &FieldElement,
+        calldata: &[FieldElement],
+        nonce: &FieldElement,
+    ) -> Result<FeeEstimate> {
+        let invoke = invoke_transaction(
+            QUERY_VERSION_3,
+            sender,
+            calldata,
+            nonce,
+            &[],
+            &ResourceBounds::default(),
+            0,
+        );
+        let estimates: Vec<RawFeeEstimate> = self
+            .request(
+                "starknet_estimateFee",
+                json!({
+                    "request": [invoke],
+                    "simulation_flags": ["SKIP_VALIDATE"],
+                    "block_id": "pending",
+                }),
+            )
+            .await?;
+        let estimate = estimates
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Starknet RPC starknet_estimateFee returned no estimate"))?;
+
+        Ok(FeeEstimate {
+            l1_gas_consumed: parse_hex(&estimate.l1_gas_consumed)?,
+            l1_gas_price: parse_hex(&estimate.l1_gas_price)?,
+            l1_data_gas_consumed: parse_hex(&estimate.l1_data_gas_consumed)?,
+            l1_data_gas_price: parse_hex(&estimate.l1_data_gas_price)?,
+            l2_gas_consumed: parse_hex(&estimate.l2_gas_consumed)?,
+            l2_gas_price: parse_hex(&estimate.l2_gas_price)?,
+        })
+    }
+
+    /// Submits a signed invoke and returns its transaction hash.
+    pub async fn add_invoke_transaction(&self, invoke: &SignedInvoke) -> Result<String> {
+        let result: InvokeResult = self
+            .request(
+                "starknet_addInvokeTransaction",
+                json!({
+                    "invoke_transaction": invoke_transaction(
+                        "0x3",
+                        &invoke.sender_address,
+                        &invoke.calldata,
+                        &invoke.nonce,
+                        &invoke.signature,
+                        &invoke.resource_bounds,
+                        invoke.tip,
+                    )
+                }),
+            )
+            .await?;
+
+        Ok(result.transaction_hash)
+    }
+
+    /// Returns `None` while the node does not know the transaction.
+    pub async fn get_transaction_status(
+        &self,
+        transaction_hash: &str,
+    ) -> Result<Option<TransactionStatus>> {
+        let response: RpcResponse<TransactionStatus> = self
+            .send(
+                "starknet_getTransactionStatus",
+                json!({ "transaction_hash": transaction_hash }),
+            )
+            .await?;
+
+        match response.error {
+            Some(error) if error.code == TXN_HASH_NOT_FOUND => Ok(None),
+            _ => Self::into_result("starknet_getTransactionStatus", response).map(Some),
+        }
+    }
+
+    pub async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let response = self.send(method, params).await?;
+        Self::into_result(method, response)
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<RpcResponse<T>> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+
+        let response: RpcResponse<T> = self
+            .http
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response)
+    }
+
+    fn into_result<T>(method: &str, response: RpcResponse<T>) -> Result<T> {
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(anyhow!(
+                "Starknet RPC {} failed ({}): {}{}",
+                method,
+                error.code,
+                error.message,
+                error.data.map(|d| format!(" {}", d)).unwrap_or_default()
+            )),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(anyhow!("Starknet RPC {} returned no result", method)),
+        }
+    }
+}
+
+/// Parses a hex felt into its value, rejecting anything wider than 128 bits.
+pub fn felt_to_u128(felt: &str) -> Option<u128> {
+    let digits = felt.strip_prefix("0x").unwrap_or(felt);
+    let digits = digits.trim_start_matches('0');
+    if digits.len() > 32 {
+        return None;
+    }
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u128::from_str_radix(digits, 16).ok()
+}
+
+/// Formats a felt in the canonical zero-padded `0x` form used by `bridge_events`.
+pub fn normalize_felt(felt: &str) -> Option<String> {
+    let digits = felt.strip_prefix("0x")?.to_lowercase();
+    if digits.is_empty() || digits.len() > 64 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("0x{:0>64}", digits))
+}
+
+/// Decodes a Cairo `ByteArray` from its serialized felts: the number of full
+/// 31-byte words, the words, a pending word and the pending word's length.
+pub fn decode_byte_array(felts: &[String]) -> Option<String> {
+    let word = |felt: &String, len: usize| -> Option<Vec<u8>> {
+        let bytes = hex::decode(normalize_felt(felt)?.trim_start_matches("0x")).ok()?;
+        Some(bytes[32 - len..].to_vec())
+    };
+
+    let count = felt_to_u128(felts.first()?)? as usize;
+    if felts.len() < count + 3 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(count * 31);
+    for felt in &felts[1..=count] {
+        bytes.extend(word(felt, 31)?);
+    }
+
+    let pending_len = felt_to_u128(&felts[count + 2])? as usize;
+    if pending_len > 30 {
+        return None;
+    }
+    bytes.extend(word(&felts[count + 1], pending_len)?);
+
+    String::from_utf8(bytes).ok()
+}
+
+/// A version 3 `INVOKE_TXN` without paymaster, with data availability on L1
+fn invoke_transaction(
+    version: &str,
+    sender: &FieldElement,
+    calldata: &[FieldElement],
+    nonce: &FieldElement,
+    signature: &[FieldElement],
+    bounds: &ResourceBounds,
+    tip: u64,
+) -> Value {
+    let felts = |values: &[FieldElement]| values.iter().map(felt_hex).collect::<Vec<_>>();
+    let bound = |bound: &ResourceBound| {
+        json!({
+            "max_amount": format!("{:#x}", bound.max_amount),
+            "max_price_per_unit": format!("{:#x}", bound.max_price_per_unit),
+        })
+    };
+
+    json!({
+        "type": "INVOKE",
+        "version": version,
+        "sender_address": felt_hex(sender),
+        "calldata": felts(calldata),
+        "signature": felts(signature),
+        "nonce": felt_hex(nonce),
+        "resource_bounds": {
+            "l1_gas": bound(&bounds.l1_gas),
+            "l1_data_gas": bound(&bounds.l1_data_gas),
+            "l2_gas": bound(&bounds.l2_gas),
+        },
+        "tip": format!("{:#x}", tip),
+        "paymaster_data": [],
+        "account_deployment_data": [],
+        "nonce_data_availability_mode": "L1",
+        "fee_data_availability_mode": "L1",
+    })
+}
+
+fn parse_hex<T: TryFrom<u128>>(value: &str) -> Result<T> {
+    u128::from_str_radix(value.trim_start_matches("0x"), 16)
+        .ok()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| anyhow!("Invalid fee estimate value {}", value))
+}
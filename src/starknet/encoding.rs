@@ -0,0 +1,97 @@
+use sha3::{Digest, Keccak256};
+use starknet_crypto::FieldElement;
+
+/// Splits an 80-byte block header into the 20 big-endian u32 words Cairo
+/// light clients consume.
+pub fn header_words(header: &[u8; 80]) -> [u32; 20] {
+    let mut words = [0u32; 20];
+    for (word, chunk) in words.iter_mut().zip(header.chunks_exact(4)) {
+        *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    words
+}
+
+/// Calldata for an `Array<[u32; 20]>` of headers: the count, then each
+/// header's words.
+pub fn headers_calldata(headers: &[[u8; 80]]) -> Vec<FieldElement> {
+    let mut calldata = Vec::with_capacity(1 + headers.len() * 20);
+    calldata.push(FieldElement::from(headers.len() as u64));
+    for header in headers {
+        calldata.extend(header_words(header).map(FieldElement::from));
+    }
+    calldata
+}
+
+/// Splits a 32-byte hash, in internal byte order, into 8 big-endian u32 words.
+pub fn hash_words(hash: &[u8; 32]) -> [u32; 8] {
+    let mut words = [0u32; 8];
+    for (word, chunk) in words.iter_mut().zip(hash.chunks_exact(4)) {
+        *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    words
+}
+
+/// Starknet keccak: keccak256 truncated to 250 bits, used for entrypoint selectors.
+pub fn selector_from_name(name: &str) -> FieldElement {
+    let mut hash: [u8; 32] = Keccak256::digest(name.as_bytes()).into();
+    hash[0] &= 0x03;
+    FieldElement::from_bytes_be(&hash).expect("250-bit value fits in a felt")
+}
+
+/// Encodes an ASCII string of at most 31 bytes as a felt, e.g. chain ids.
+pub fn short_string(value: &str) -> Option<FieldElement> {
+    if value.len() > 31 || !value.is_ascii() {
+        return None;
+    }
+    FieldElement::from_byte_slice_be(value.as_bytes()).ok()
+}
+
+pub fn felt_hex(felt: &FieldElement) -> String {
+    format!("{:#x}", felt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{blockdata::constants::genesis_block, consensus::serialize, Network};
+
+    #[test]
+    fn splits_headers_into_big_endian_words() {
+        let header: [u8; 80] = serialize(&genesis_block(Network::Bitcoin).header)
+            .try_into()
+            .unwrap();
+
+        let mut expected = [0u32; 20];
+        expected[0] = 0x01000000;
+        expected[9..].copy_from_slice(&[
+            0x3ba3edfd, 0x7a7b12b2, 0x7ac72c3e, 0x67768f61, 0x7fc81bc3, 0x888a5132, 0x3a9fb8aa,
+            0x4b1e5e4a, 0x29ab5f49, 0xffff001d, 0x1dac2b7c,
+        ]);
+        assert_eq!(header_words(&header), expected);
+
+        let calldata = headers_calldata(&[header, header]);
+        assert_eq!(calldata.len(), 41);
+        assert_eq!(calldata[0], FieldElement::from(2u64));
+        assert_eq!(calldata[1], FieldElement::from(0x01000000u32));
+        assert_eq!(calldata[21], calldata[1]);
+        assert_eq!(calldata[40], FieldElement::from(0x1dac2b7cu32));
+    }
+
+    #[test]
+    fn encodes_selectors_and_short_strings() {
+        assert_eq!(
+            selector_from_name("transfer"),
+            FieldElement::from_hex_be(
+                "0x83afd3f4caedc6eebf44246fe54e38c95e3179a5ec9ea81740eca5b482d12e"
+            )
+            .unwrap()
+        );
+
+        assert_eq!(
+            short_string("SN_MAIN"),
+            FieldElement::from_hex_be("0x534e5f4d41494e").ok()
+        );
+        assert_eq!(short_string(&"x".repeat(32)), None);
+        assert_eq!(short_string("é"), None);
+    }
+}
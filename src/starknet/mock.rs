@@ -0,0 +1,77 @@
+//! Local JSON-RPC server for tests, answering each call from a closure.
+
+use axum::{routing::post, Extension, Json, Router};
+use serde_json::{json, Value};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+type Handler = dyn Fn(&str, &Value) -> Result<Value, (i64, String)> + Send + Sync;
+
+#[derive(Clone)]
+struct State {
+    handler: Arc<Handler>,
+    requests: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+pub struct MockRpc {
+    pub url: String,
+    requests: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+impl MockRpc {
+    /// Serves on a free local port; `handler` gets the method and params and
+    /// returns the result or an error code and message.
+    pub async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&str, &Value) -> Result<Value, (i64, String)> + Send + Sync + 'static,
+    {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let state = State {
+            handler: Arc::new(handler),
+            requests: requests.clone(),
+        };
+
+        let router = Router::new()
+            .route("/", post(handle))
+            .layer(Extension(state));
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(router.into_make_service());
+        let url = format!("http://{}/", server.local_addr());
+        tokio::spawn(server);
+
+        Self { url, requests }
+    }
+
+    /// Params of every call to `method`, in order
+    pub fn calls(&self, method: &str) -> Vec<Value> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| name == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+}
+
+async fn handle(Extension(state): Extension<State>, Json(request): Json<Value>) -> Json<Value> {
+    let method = request["method"].as_str().unwrap_or_default().to_string();
+    let params = request["params"].clone();
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push((method.clone(), params.clone()));
+
+    let body = match (state.handler)(&method, &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": { "code": code, "message": message },
+        }),
+    };
+    Json(body)
+}
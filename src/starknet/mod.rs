@@ -0,0 +1,11 @@
+pub mod account;
+pub mod client;
+pub mod encoding;
+#[cfg(all(test, feature = "sqlite"))]
+pub(crate) mod mock;
+pub mod relayer;
+pub mod withdrawals;
+
+pub use client::StarknetClient;
+pub use relayer::HeaderRelayer;
+pub use withdrawals::WithdrawalMonitor;
@@ -0,0 +1,682 @@
+use anyhow::{anyhow, Result};
+use bitcoin::{consensus::serialize, BlockHash};
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use chrono::{DateTime, Utc};
+use starknet_crypto::FieldElement;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::Config,
+    db::{
+        models::{HeaderRelayStatus, HeaderRelaySubmissionModel},
+        Storage,
+    },
+    starknet::{
+        account::{Account, Call, ResourceBound, ResourceBounds},
+        client::FeeEstimate,
+        encoding::{headers_calldata, selector_from_name},
+        StarknetClient,
+    },
+};
+
+/// Seconds before a submission the node has never seen is treated as dropped
+const UNKNOWN_TX_TIMEOUT_SECS: i64 = 10 * 60;
+
+/// Failed attempts at one batch before the relayer parks it
+const MAX_BATCH_ATTEMPTS: u32 = 8;
+
+/// Wait after a batch first fails; doubles with every further failure
+const RETRY_BASE_DELAY_SECS: u64 = 30;
+
+/// Longest wait between two attempts at a batch while it backs off
+const RETRY_MAX_DELAY_SECS: u64 = 60 * 60;
+
+/// Wait between attempts at a batch that is out of attempts
+const RETRY_PARKED_DELAY_SECS: u64 = 24 * 60 * 60;
+
+/// Percentage added to estimated resource amounts and prices
+const FEE_ESTIMATE_MARGIN_PERCENT: u128 = 50;
+
+/// Submits indexed Bitcoin headers, in order and in batches, to a Starknet
+/// light-client contract. One submission is in flight at a time; the next batch
+/// is sent once its receipt settles.
+///
+/// Each invoke is a version 3 transaction whose resource bounds are the fee
+/// estimate plus `FEE_ESTIMATE_MARGIN_PERCENT`, capped by `starknet_relay_max_fee`.
+///
+/// A batch that fails to send, reverts or is rejected is retried with
+/// exponential backoff. After `MAX_BATCH_ATTEMPTS` failures it is only retried
+/// once a day rather than keep paying fees. Failed submissions record their
+/// attempt and next retry time, so the backoff survives restarts.
+///
+/// Calldata for the entrypoint is `Array<[u32; 20]>`: the header count followed
+/// by each 80-byte header as 20 big-endian words.
+pub struct HeaderRelayer {
+    starknet: StarknetClient,
+    bitcoin: Client,
+    db: Arc<dyn Storage>,
+    config: Arc<Config>,
+    account: Account,
+    contract: FieldElement,
+    selector: FieldElement,
+    max_fee: u128,
+}
+
+impl HeaderRelayer {
+    /// Returns `None` unless the Starknet endpoint, relay contract and account are configured.
+    pub fn new(db: Arc<dyn Storage>, config: Arc<Config>) -> Result<Option<Self>> {
+        let (Some(url), Some(contract), Some(address), Some(private_key)) = (
+            config.starknet_rpc_url.as_deref(),
+            config.starknet_relay_contract.as_deref(),
+            config.starknet_account_address.as_deref(),
+            config.starknet_private_key.as_deref(),
+        ) else {
+            return Ok(None);
+        };
+
+        let contract = FieldElement::from_hex_be(contract)
+            .map_err(|e| anyhow!("Invalid starknet_relay_contract: {}", e))?;
+        let max_fee =
+            u128::from_str_radix(config.starknet_relay_max_fee.trim_start_matches("0x"), 16)
+                .map_err(|e| anyhow!("Invalid starknet_relay_max_fee: {}", e))?;
+
+        let auth = Auth::UserPass(
+            config.bitcoin_rpc_user.clone(),
+            config.bitcoin_rpc_pass.clone(),
+        );
+        let bitcoin = Client::new(&config.bitcoin_rpc_url, auth)?;
+
+        Ok(Some(Self {
+            starknet: StarknetClient::new(url),
+            bitcoin,
+            db,
+            account: Account::new(address, private_key)?,
+            contract,
+            selector: selector_from_name(&config.starknet_relay_entrypoint),
+            max_fee,
+            config,
+        }))
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        info!(
+            "Starting header relay to {:#x}::{}",
+            self.contract, self.config.starknet_relay_entrypoint
+        );
+
+        let chain_id = loop {
+            match self.starknet.chain_id().await {
+                Ok(chain_id) => break chain_id,
+                Err(e) => error!("Error fetching Starknet chain id: {}", e),
+            }
+            sleep(Duration::from_secs(self.config.starknet_poll_interval_secs)).await;
+        };
+
+        loop {
+            if let Err(e) = self.poll(&chain_id).await {
+                error!("Header relay error: {}", e);
+            }
+
+            sleep(Duration::from_secs(self.config.starknet_poll_interval_secs)).await;
+        }
+    }
+
+    async fn poll(&self, chain_id: &str) -> Result<()> {
+        if let Some(submission) = self.db.get_in_flight_header_submission().await? {
+            if !self.settle(&submission).await? {
+                return Ok(());
+            }
+        }
+
+        if let Some(fork) = self.db.rollback_header_relay().await? {
+            warn!("Relayed headers reorged out from height {}", fork);
+        }
+
+        let Some(tip) = self.db.get_last_block_height().await? else {
+            return Ok(());
+        };
+        let from = match self.db.get_last_relayed_height().await? {
+            Some(height) => height + 1,
+            None => self
+                .config
+                .starknet_relay_start_height
+                .unwrap_or(self.config.indexer_start_height),
+        };
+        if from > tip {
+            debug!("Header relay caught up to height {}", tip);
+            return Ok(());
+        }
+
+        let to = tip.min(from + self.config.starknet_relay_batch_size.max(1) - 1);
+        let failures = self.failures(from).await?;
+        if let Some((attempts, retry_after)) = failures {
+            if Utc::now() < retry_after {
+                if attempts >= MAX_BATCH_ATTEMPTS {
+                    error!(
+                        "Header relay from {} failed {} times; parked until {}",
+                        from, attempts, retry_after
+                    );
+                } else {
+                    debug!(
+                        "Header relay from {} backing off after {} failure(s)",
+                        from, attempts
+                    );
+                }
+                return Ok(());
+            }
+        }
+
+        if let Err(e) = self.submit(chain_id, from, to).await {
+            warn!("Header relay submission {}..={} failed: {}", from, to, e);
+            let id = self
+                .db
+                .insert_failed_header_submission(from, to, &e.to_string())
+                .await?;
+            let attempts = failures.map_or(0, |(attempts, _)| attempts);
+            self.record_failure(id, from, attempts + 1).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Consecutive failures of the batch starting at `from`, and when it may be
+    /// sent again.
+    async fn failures(&self, from: u64) -> Result<Option<(u32, DateTime<Utc>)>> {
+        let last = self.db.get_last_settled_header_submission(from).await?;
+        Ok(last.and_then(|submission| Some((submission.attempt? as u32, submission.retry_after?))))
+    }
+
+    /// Schedules the next attempt at the batch starting at `from` after its
+    /// failed submission `id`, the `attempt`-th in a row.
+    async fn record_failure(&self, id: i32, from: u64, attempt: u32) -> Result<()> {
+        let delay = if attempt >= MAX_BATCH_ATTEMPTS {
+            error!(
+                "Header relay from {} failed {} times; retrying once a day",
+                from, attempt
+            );
+            RETRY_PARKED_DELAY_SECS
+        } else {
+            RETRY_BASE_DELAY_SECS
+                .saturating_mul(1 << (attempt - 1).min(16))
+                .min(RETRY_MAX_DELAY_SECS)
+        };
+        let retry_after = Utc::now() + chrono::Duration::seconds(delay as i64);
+
+        self.db
+            .schedule_header_relay_retry(id, attempt, retry_after)
+            .await
+    }
+
+    /// Checks an in-flight submission, returning whether it has settled.
+    async fn settle(&self, submission: &HeaderRelaySubmissionModel) -> Result<bool> {
+        let Some(tx_hash) = submission.starknet_tx_hash.as_deref() else {
+            return Ok(true);
+        };
+        let Some(status) = self.starknet.get_transaction_status(tx_hash).await? else {
+            // A transaction the node never learns about was dropped before inclusion
+            let age = submission
+                .created_at
+                .map(|created_at| (Utc::now() - created_at).num_seconds())
+                .unwrap_or_default();
+            if age < UNKNOWN_TX_TIMEOUT_SECS {
+                debug!("Header relay transaction {} not yet known", tx_hash);
+                return Ok(false);
+            }
+            warn!("Header relay transaction {} was dropped", tx_hash);
+            self.record_settled_failure(submission).await?;
+            self.db
+                .update_header_submission(
+                    submission.id,
+                    HeaderRelayStatus::Rejected,
+                    Some("transaction dropped"),
+                )
+                .await?;
+            return Ok(true);
+        };
+
+        let outcome = match (
+            status.finality_status.as_str(),
+            status.execution_status.as_deref(),
+        ) {
+            ("REJECTED", _) => HeaderRelayStatus::Rejected,
+            (_, Some("REVERTED")) => HeaderRelayStatus::Reverted,
+            ("ACCEPTED_ON_L2" | "ACCEPTED_ON_L1", _) => HeaderRelayStatus::Accepted,
+            _ => return Ok(false),
+        };
+
+        match outcome {
+            HeaderRelayStatus::Accepted => {
+                info!(
+                    "Relayed headers {}..={} in {}",
+                    submission.from_height, submission.to_height, tx_hash
+                );
+            }
+            _ => {
+                warn!(
+                    "Header relay transaction {} {}: {}",
+                    tx_hash,
+                    outcome,
+                    status
+                        .failure_reason
+                        .as_deref()
+                        .unwrap_or("no reason given")
+                );
+                self.record_settled_failure(submission).await?;
+            }
+        }
+        self.db
+            .update_header_submission(submission.id, outcome, status.failure_reason.as_deref())
+            .await?;
+        Ok(true)
+    }
+
+    /// Schedules the next attempt at the batch of a failed `submission`. Runs
+    /// while it is still in flight, so that earlier failures are the latest settled.
+    async fn record_settled_failure(&self, submission: &HeaderRelaySubmissionModel) -> Result<()> {
+        let from = submission.from_height as u64;
+        let attempts = self
+            .failures(from)
+            .await?
+            .map_or(0, |(attempts, _)| attempts);
+        self.record_failure(submission.id, from, attempts + 1).await
+    }
+
+    async fn submit(&self, chain_id: &str, from: u64, to: u64) -> Result<()> {
+        let blocks = self.db.get_blocks_range(from, to).await?;
+        if blocks.len() as u64 != to - from + 1 {
+            return Err(anyhow!("blocks {}..={} are not fully indexed", from, to));
+        }
+
+        let mut raw_headers = Vec::with_capacity(blocks.len());
+        let mut headers = Vec::with_capacity(blocks.len());
+
+        // Send the exact headers we indexed so the relay follows our view of the chain
+        for block in &blocks {
+            let raw = match &block.header {
+                Some(header) => header.as_bytes().to_vec(),
+                // Blocks indexed before headers were stored
+                None => serialize(
+                    &self
+                        .bitcoin
+                        .get_block_header(&BlockHash::from(block.hash))?,
+                ),
+            };
+            let raw: [u8; 80] = raw
+                .try_into()
+                .map_err(|_| anyhow!("Header {} is not 80 bytes", block.hash))?;
+
+            raw_headers.push(raw);
+            headers.push((block.height as u64, block.hash));
+        }
+
+        let calls = [Call {
+            to: self.contract,
+            selector: self.selector,
+            calldata: headers_calldata(&raw_headers),
+        }];
+        let nonce = self.starknet.get_nonce(&self.account.address()).await?;
+        let estimate = self
+            .starknet
+            .estimate_invoke_fee(
+                &self.account.address(),
+                &Account::execute_calldata(&calls),
+                &nonce,
+            )
+            .await?;
+        let bounds = resource_bounds(&estimate);
+        let tip = self.config.starknet_relay_tip;
+        if bounds.max_fee(tip) > self.max_fee {
+            return Err(anyhow!(
+                "fee bounds of {:#x} fri exceed starknet_relay_max_fee {:#x}",
+                bounds.max_fee(tip),
+                self.max_fee
+            ));
+        }
+        let invoke = self
+            .account
+            .sign_invoke(&calls, chain_id, nonce, bounds, tip)?;
+        let tx_hash = self.starknet.add_invoke_transaction(&invoke).await?;
+
+        self.db.insert_header_submission(&headers, &tx_hash).await?;
+        debug!("Submitted headers {}..={} in {}", from, to, tx_hash);
+        Ok(())
+    }
+}
+
+/// Resource bounds covering `estimate` plus `FEE_ESTIMATE_MARGIN_PERCENT`
+fn resource_bounds(estimate: &FeeEstimate) -> ResourceBounds {
+    let bound = |amount: u64, price: u128| ResourceBound {
+        max_amount: u64::try_from(with_margin(amount as u128)).unwrap_or(u64::MAX),
+        max_price_per_unit: with_margin(price),
+    };
+    ResourceBounds {
+        l1_gas: bound(estimate.l1_gas_consumed, estimate.l1_gas_price),
+        l1_data_gas: bound(estimate.l1_data_gas_consumed, estimate.l1_data_gas_price),
+        l2_gas: bound(estimate.l2_gas_consumed, estimate.l2_gas_price),
+    }
+}
+
+fn with_margin(value: u128) -> u128 {
+    value.saturating_add(value.saturating_mul(FEE_ESTIMATE_MARGIN_PERCENT) / 100)
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::{db::SqliteDatabase, starknet::mock::MockRpc};
+    use bitcoin::{blockdata::constants::genesis_block, Network};
+    use serde_json::{json, Value};
+
+    const CONTRACT: &str = "0xc0ffee";
+    const ACCOUNT: &str = "0x4d2";
+    const PRIVATE_KEY: &str = "0x139fe4d6f02e666e86a6f58e65060f115cd3c185bd9e98bd829636931458f79";
+    const TX_HASH: &str = "0x7a5";
+
+    /// A node whose transactions all report `status`, or are unknown when it is `null`
+    async fn node(status: Arc<std::sync::Mutex<Value>>) -> MockRpc {
+        MockRpc::start(move |method, _| match method {
+            "starknet_chainId" => Ok(json!("0x534e5f5345504f4c4941")),
+            "starknet_getNonce" => Ok(json!("0x5")),
+            "starknet_estimateFee" => Ok(json!([{
+                "l1_gas_consumed": "0x0",
+                "l1_gas_price": "0x10",
+                "l1_data_gas_consumed": "0x80",
+                "l1_data_gas_price": "0x30",
+                "l2_gas_consumed": "0x100000",
+                "l2_gas_price": "0x20",
+                "overall_fee": "0x2001800",
+                "unit": "FRI",
+            }])),
+            "starknet_addInvokeTransaction" => Ok(json!({ "transaction_hash": TX_HASH })),
+            "starknet_getTransactionStatus" => match status.lock().unwrap().clone() {
+                Value::Null => Err((29, "Transaction hash not found".to_string())),
+                status => Ok(status),
+            },
+            _ => Err((-32601, "method not found".to_string())),
+        })
+        .await
+    }
+
+    /// A relayer with blocks `0..blocks` indexed
+    async fn relayer(rpc: &MockRpc, blocks: u64) -> (HeaderRelayer, Arc<dyn Storage>) {
+        let db = SqliteDatabase::new("sqlite::memory:", 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+        for height in 0..blocks {
+            let mut block = genesis_block(Network::Regtest);
+            block.header.nonce = height as u32;
+            db.insert_block(&block, height, &[0; 32], None)
+                .await
+                .unwrap();
+        }
+
+        let db: Arc<dyn Storage> = Arc::new(db);
+        (relayer_over(rpc, db.clone(), json!({})), db)
+    }
+
+    /// A relayer over `db`, configured with `overrides`
+    fn relayer_over(rpc: &MockRpc, db: Arc<dyn Storage>, overrides: Value) -> HeaderRelayer {
+        let mut config = json!({
+            "starknet_rpc_url": rpc.url,
+            "starknet_relay_contract": CONTRACT,
+            "starknet_account_address": ACCOUNT,
+            "starknet_private_key": PRIVATE_KEY,
+            "starknet_relay_batch_size": 2,
+        });
+        config
+            .as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        HeaderRelayer::new(db, Arc::new(Config::for_tests(config)))
+            .unwrap()
+            .unwrap()
+    }
+
+    fn status(finality: &str, execution: Option<&str>) -> Value {
+        json!({ "finality_status": finality, "execution_status": execution })
+    }
+
+    async fn submissions(db: &dyn Storage) -> Vec<(i64, i64, String)> {
+        let mut submissions = db.get_header_submissions(None, None, 100).await.unwrap();
+        submissions.sort_by_key(|submission| submission.id);
+        submissions
+            .into_iter()
+            .map(|s| (s.from_height, s.to_height, s.status))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn submits_signed_header_batches() {
+        let rpc = node(Arc::new(std::sync::Mutex::new(Value::Null))).await;
+        let (relayer, db) = relayer(&rpc, 3).await;
+
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+
+        let calls = rpc.calls("starknet_addInvokeTransaction");
+        assert_eq!(calls.len(), 1);
+        let invoke = &calls[0]["invoke_transaction"];
+        assert_eq!(invoke["version"], "0x3");
+        assert_eq!(invoke["sender_address"], ACCOUNT);
+        assert_eq!(invoke["nonce"], "0x5");
+
+        // One call to the contract with the first two headers as `Array<[u32; 20]>`
+        let felt = |value: &Value| FieldElement::from_hex_be(value.as_str().unwrap()).unwrap();
+        let calldata: Vec<_> = invoke["calldata"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(felt)
+            .collect();
+        let blocks = db.get_blocks_range(0, 1).await.unwrap();
+        let headers: Vec<[u8; 80]> = blocks
+            .iter()
+            .map(|block| {
+                block
+                    .header
+                    .as_ref()
+                    .unwrap()
+                    .as_bytes()
+                    .try_into()
+                    .unwrap()
+            })
+            .collect();
+        let mut expected = vec![
+            FieldElement::ONE,
+            FieldElement::from_hex_be(CONTRACT).unwrap(),
+            selector_from_name("submit_headers"),
+            FieldElement::from(41u64),
+        ];
+        expected.extend(headers_calldata(&headers));
+        assert_eq!(calldata, expected);
+
+        // Fee estimated without validation, bounded at the estimate plus half
+        let estimate = &rpc.calls("starknet_estimateFee")[0];
+        assert_eq!(estimate["simulation_flags"], json!(["SKIP_VALIDATE"]));
+        assert_eq!(estimate["request"][0]["calldata"], invoke["calldata"]);
+        let bound = |amount: u64, price: u128| ResourceBound {
+            max_amount: amount,
+            max_price_per_unit: price,
+        };
+        let bounds = ResourceBounds {
+            l1_gas: bound(0, 0x18),
+            l1_data_gas: bound(0xc0, 0x48),
+            l2_gas: bound(0x180000, 0x30),
+        };
+        assert_eq!(
+            invoke["resource_bounds"],
+            json!({
+                "l1_gas": { "max_amount": "0x0", "max_price_per_unit": "0x18" },
+                "l1_data_gas": { "max_amount": "0xc0", "max_price_per_unit": "0x48" },
+                "l2_gas": { "max_amount": "0x180000", "max_price_per_unit": "0x30" },
+            })
+        );
+        assert_eq!(invoke["tip"], "0x0");
+
+        // Signed by the configured key over the v3 invoke hash
+        let call = Call {
+            to: FieldElement::from_hex_be(CONTRACT).unwrap(),
+            selector: selector_from_name("submit_headers"),
+            calldata: headers_calldata(&headers),
+        };
+        let expected = Account::new(ACCOUNT, PRIVATE_KEY)
+            .unwrap()
+            .sign_invoke(&[call], "SN_SEPOLIA", FieldElement::from(5u64), bounds, 0)
+            .unwrap();
+        let signature: Vec<_> = invoke["signature"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(felt)
+            .collect();
+        assert_eq!(signature, expected.signature);
+
+        assert_eq!(submissions(db.as_ref()).await, [(0, 1, "submitted".into())]);
+        assert_eq!(db.get_last_relayed_height().await.unwrap(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn settles_submissions_by_transaction_status() {
+        let node_status = Arc::new(std::sync::Mutex::new(Value::Null));
+        let rpc = node(node_status.clone()).await;
+        let (relayer, db) = relayer(&rpc, 1).await;
+        let hash = db.get_blocks_range(0, 0).await.unwrap()[0].hash;
+
+        let cases = [
+            (status("RECEIVED", None), None),
+            (
+                status("ACCEPTED_ON_L2", Some("SUCCEEDED")),
+                Some("accepted"),
+            ),
+            (status("ACCEPTED_ON_L2", Some("REVERTED")), Some("reverted")),
+            (status("REJECTED", None), Some("rejected")),
+            // Unknown to the node, but only just sent
+            (Value::Null, None),
+        ];
+        for (node_reply, expected) in cases {
+            *node_status.lock().unwrap() = node_reply;
+            let id = db
+                .insert_header_submission(&[(0, hash)], TX_HASH)
+                .await
+                .unwrap();
+            let submission = db.get_in_flight_header_submission().await.unwrap().unwrap();
+            assert_eq!(submission.id, id);
+
+            let settled = relayer.settle(&submission).await.unwrap();
+            assert_eq!(settled, expected.is_some());
+            let status = submissions(db.as_ref()).await.pop().unwrap().2;
+            assert_eq!(status, expected.unwrap_or("submitted"));
+            if !settled {
+                db.update_header_submission(id, HeaderRelayStatus::Rejected, None)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        // A transaction the node still doesn't know after the timeout was dropped
+        db.insert_header_submission(&[(0, hash)], TX_HASH)
+            .await
+            .unwrap();
+        let mut submission = db.get_in_flight_header_submission().await.unwrap().unwrap();
+        submission.created_at = Some(Utc::now() - chrono::Duration::hours(1));
+        assert!(relayer.settle(&submission).await.unwrap());
+        let dropped = db.get_header_submissions(None, None, 1).await.unwrap();
+        assert_eq!(dropped[0].error.as_deref(), Some("transaction dropped"));
+
+        // Failed submissions release their headers for the next batch
+        assert_eq!(db.get_last_relayed_height().await.unwrap(), None);
+    }
+
+    async fn last(db: &dyn Storage) -> HeaderRelaySubmissionModel {
+        db.get_header_submissions(None, None, 1).await.unwrap()[0].clone()
+    }
+
+    /// Makes the batch of the last submission due now, as its `attempts`-th failure
+    async fn expire_backoff(db: &dyn Storage, attempts: u32) {
+        let last = last(db).await;
+        db.schedule_header_relay_retry(last.id, attempts, Utc::now())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn refuses_fee_bounds_above_the_max_fee() {
+        let rpc = node(Arc::new(std::sync::Mutex::new(Value::Null))).await;
+        let (_, db) = relayer(&rpc, 2).await;
+        let relayer = relayer_over(
+            &rpc,
+            db.clone(),
+            json!({ "starknet_relay_max_fee": "0x1000", "starknet_relay_tip": 1 }),
+        );
+
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+        assert!(rpc.calls("starknet_addInvokeTransaction").is_empty());
+        let failed = last(db.as_ref()).await;
+        assert_eq!(failed.status, "failed");
+        assert!(failed.error.as_deref().unwrap().contains("exceed"));
+        assert_eq!(failed.attempt, Some(1));
+
+        // Failing to send backs off like any other failure
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+        assert_eq!(rpc.calls("starknet_estimateFee").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn backs_off_parks_and_recovers_failing_batches() {
+        let node_status = Arc::new(std::sync::Mutex::new(status(
+            "ACCEPTED_ON_L2",
+            Some("REVERTED"),
+        )));
+        let rpc = node(node_status.clone()).await;
+        let (relayer, db) = relayer(&rpc, 2).await;
+        let sent = || rpc.calls("starknet_addInvokeTransaction").len();
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+        assert_eq!(sent(), 1);
+
+        // The revert settles, and the batch waits out its backoff
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+        assert_eq!(sent(), 1);
+        let failed = last(db.as_ref()).await;
+        assert_eq!(
+            (failed.status.as_str(), failed.attempt),
+            ("reverted", Some(1))
+        );
+        assert!(failed.retry_after.unwrap() > Utc::now());
+
+        // The backoff is stored, so a restart keeps waiting
+        let restarted = relayer_over(&rpc, db.clone(), json!({}));
+        restarted.poll("SN_SEPOLIA").await.unwrap();
+        assert_eq!(sent(), 1);
+
+        expire_backoff(db.as_ref(), 1).await;
+        restarted.poll("SN_SEPOLIA").await.unwrap();
+        assert_eq!(sent(), 2);
+        restarted.poll("SN_SEPOLIA").await.unwrap();
+        assert_eq!(last(db.as_ref()).await.attempt, Some(2));
+
+        // Out of attempts, the batch is parked for a day
+        expire_backoff(db.as_ref(), MAX_BATCH_ATTEMPTS - 1).await;
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+        assert_eq!(sent(), 3);
+        let parked = last(db.as_ref()).await;
+        assert_eq!(parked.attempt, Some(MAX_BATCH_ATTEMPTS as i32));
+        assert!(parked.retry_after.unwrap() > Utc::now() + chrono::Duration::hours(23));
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+        assert_eq!(sent(), 3);
+
+        // and recovers once it is accepted, without a restart
+        expire_backoff(db.as_ref(), MAX_BATCH_ATTEMPTS).await;
+        *node_status.lock().unwrap() = status("ACCEPTED_ON_L2", Some("SUCCEEDED"));
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+        relayer.poll("SN_SEPOLIA").await.unwrap();
+        assert_eq!(sent(), 4);
+        let accepted = last(db.as_ref()).await;
+        assert_eq!(
+            (accepted.status.as_str(), accepted.attempt),
+            ("accepted", None)
+        );
+        assert_eq!(db.get_last_relayed_height().await.unwrap(), Some(1));
+    }
+}
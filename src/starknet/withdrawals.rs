@@ -0,0 +1,498 @@
+use anyhow::Result;
+use bitcoin::{Address, Amount, Network, ScriptBuf, TxOut};
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+use tokio::time::{sleep, Duration};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::Config,
+    db::{models::BridgeEventData, Storage},
+    indexer::bridge::{match_withdrawal, withdrawal_reference_script, ASSET_BTC},
+    starknet::client::{
+        decode_byte_array, felt_to_u128, normalize_felt, EmittedEvent, EventFilter, StarknetClient,
+    },
+    utils::script::electrum_scripthash,
+};
+
+const SYNC_CURSOR: &str = "starknet_withdrawals";
+const EVENTS_CHUNK_SIZE: u64 = 100;
+
+/// Ingests withdrawal events emitted by the Starknet bridge contract into
+/// `bridge_events`. The indexer matches them to Bitcoin payouts as it reaches
+/// them; payouts it indexed first are matched here on ingestion. Only blocks
+/// `starknet_withdrawal_confirmations` below the latest are read, since
+/// ingested withdrawals are never revisited.
+///
+/// Expected event layout: `keys = [selector, sender]`,
+/// `data = [amount.low, amount.high, ...ByteArray(bitcoin_address)]`.
+pub struct WithdrawalMonitor {
+    client: StarknetClient,
+    db: Arc<dyn Storage>,
+    config: Arc<Config>,
+    contract: String,
+    event_key: String,
+    network: Network,
+}
+
+impl WithdrawalMonitor {
+    /// Returns `None` unless the Starknet endpoint, contract and event key are configured.
+    pub fn new(db: Arc<dyn Storage>, config: Arc<Config>) -> Result<Option<Self>> {
+        let (Some(url), Some(contract), Some(event_key)) = (
+            config.starknet_rpc_url.as_deref(),
+            config.starknet_bridge_contract.as_deref(),
+            config.starknet_withdrawal_event_key.as_deref(),
+        ) else {
+            return Ok(None);
+        };
+
+        let invalid = |name: &str| anyhow::anyhow!("Invalid {}: not a felt", name);
+        let contract =
+            normalize_felt(contract).ok_or_else(|| invalid("starknet_bridge_contract"))?;
+        let event_key =
+            normalize_felt(event_key).ok_or_else(|| invalid("starknet_withdrawal_event_key"))?;
+        let network = config.network()?;
+
+        Ok(Some(Self {
+            client: StarknetClient::new(url),
+            db,
+            config,
+            contract,
+            event_key,
+            network,
+        }))
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        info!("Starting Starknet withdrawal monitor for {}", self.contract);
+
+        loop {
+            if let Err(e) = self.poll().await {
+                error!("Starknet withdrawal sync error: {}", e);
+            }
+
+            if let Err(e) = self
+                .db
+                .flag_unmatched_withdrawals(self.config.bridge_withdrawal_timeout_secs)
+                .await
+            {
+                error!("Error flagging unmatched withdrawals: {}", e);
+            }
+
+            sleep(Duration::from_secs(self.config.starknet_poll_interval_secs)).await;
+        }
+    }
+
+    async fn poll(&self) -> Result<()> {
+        // Only blocks buried deep enough not to be reorged out
+        let latest_block = self.client.block_number().await?;
+        let Some(to_block) =
+            latest_block.checked_sub(self.config.starknet_withdrawal_confirmations)
+        else {
+            return Ok(());
+        };
+        let from_block = match self.db.get_sync_cursor(SYNC_CURSOR).await? {
+            Some(block) => block + 1,
+            None => self.config.starknet_start_block,
+        };
+
+        if from_block > to_block {
+            debug!("Starknet withdrawals caught up to block {}", to_block);
+            return Ok(());
+        }
+
+        let filter = EventFilter {
+            from_block,
+            to_block,
+            address: self.contract.clone(),
+            keys: vec![vec![self.event_key.clone()]],
+            chunk_size: EVENTS_CHUNK_SIZE,
+        };
+
+        // Events are numbered per transaction so re-reads are idempotent
+        let mut event_indexes: HashMap<String, u32> = HashMap::new();
+        let mut continuation_token: Option<String> = None;
+        let mut ingested = 0;
+
+        loop {
+            let chunk = self
+                .client
+                .get_events(&filter, continuation_token.as_deref())
+                .await?;
+
+            for event in &chunk.events {
+                let index = event_indexes
+                    .entry(event.transaction_hash.clone())
+                    .or_default();
+
+                match self.parse_withdrawal(event, *index) {
+                    Some(withdrawal) => {
+                        self.db.insert_bridge_withdrawal(&withdrawal).await?;
+                        if let Some(hash) = &withdrawal.starknet_tx_hash {
+                            self.match_indexed_payout(hash).await?;
+                        }
+                        ingested += 1;
+                    }
+                    None => warn!(
+                        "Skipping malformed withdrawal event in {}",
+                        event.transaction_hash
+                    ),
+                }
+                *index += 1;
+            }
+
+            continuation_token = chunk.continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        self.db.set_sync_cursor(SYNC_CURSOR, to_block).await?;
+
+        if ingested > 0 {
+            info!(
+                "Ingested {} Starknet withdrawal(s) up to block {}",
+                ingested, to_block
+            );
+        }
+        Ok(())
+    }
+
+    /// Links withdrawals requested by `starknet_tx_hash` to a payout the
+    /// indexer reached before this monitor ingested them.
+    async fn match_indexed_payout(&self, starknet_tx_hash: &str) -> Result<()> {
+        let Some(script) = withdrawal_reference_script(starknet_tx_hash) else {
+            return Ok(());
+        };
+        // The earliest payout, which the indexer would have matched first
+        let payouts = self
+            .db
+            .get_scripthash_history(electrum_scripthash(&script))
+            .await?;
+        let Some(payout) = payouts.first() else {
+            return Ok(());
+        };
+        let Some(detail) = self.db.get_transaction_detail(payout.txid).await? else {
+            return Ok(());
+        };
+
+        let outputs: Vec<(u32, TxOut)> = detail
+            .outputs
+            .iter()
+            .map(|output| {
+                let output = &output.output;
+                let txout = TxOut {
+                    value: Amount::from_sat(output.value as u64),
+                    script_pubkey: ScriptBuf::from(&output.script_pubkey),
+                };
+                (output.vout as u32, txout)
+            })
+            .collect();
+
+        for withdrawal in self.db.get_bridge_withdrawals(starknet_tx_hash).await? {
+            if withdrawal.txid.is_some() {
+                continue;
+            }
+
+            let result = match_withdrawal(
+                payout.txid.into(),
+                outputs.iter().map(|(vout, output)| (*vout, output)),
+                &withdrawal,
+                self.network,
+            );
+            self.db
+                .match_bridge_withdrawal(
+                    withdrawal.id,
+                    payout.txid,
+                    result.vout,
+                    payout.block_height as u64,
+                    result.status,
+                    &result.reason,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    fn parse_withdrawal(&self, event: &EmittedEvent, index: u32) -> Option<BridgeEventData> {
+        let sender = event.keys.get(1).and_then(|key| normalize_felt(key));
+
+        let low = felt_to_u128(event.data.first()?)?;
+        let high = felt_to_u128(event.data.get(1)?)?;
+        if high != 0 {
+            return None;
+        }
+        let amount = i64::try_from(low).ok()?;
+
+        let recipient = decode_byte_array(event.data.get(2..)?)?;
+        Address::from_str(&recipient)
+            .ok()?
+            .require_network(self.network)
+            .ok()?;
+
+        Some(BridgeEventData {
+            event_type: "withdrawal".to_string(),
+            txid: None,
+            vout: None,
+            starknet_tx_hash: Some(normalize_felt(&event.transaction_hash)?),
+            starknet_event_index: Some(index),
+            bitcoin_address: Some(recipient),
+            starknet_address: sender,
+            amount: Some(amount),
+            asset: Some(ASSET_BTC.to_string()),
+            metadata: Some(serde_json::json!({
+                "starknet_block_number": event.block_number,
+                "starknet_block_hash": event.block_hash,
+            })),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{models::BridgeEventStatus, SqliteDatabase},
+        starknet::mock::MockRpc,
+    };
+    use bitcoin::{
+        absolute::LockTime, blockdata::constants::genesis_block, transaction::Version, Block,
+        Transaction, TxIn,
+    };
+    use serde_json::{json, Value};
+
+    const CONTRACT: &str = "0x0000000000000000000000000000000000000000000000000000000000000b1d";
+    const EVENT_KEY: &str = "0x0000000000000000000000000000000000000000000000000000000000000e0e";
+    const SENDER: &str = "0x0000000000000000000000000000000000000000000000000000000000000123";
+
+    fn recipient() -> Address {
+        Address::p2wsh(&ScriptBuf::new(), Network::Regtest)
+    }
+
+    fn tx_hash(n: u8) -> String {
+        format!("0x{:064x}", n)
+    }
+
+    /// Serializes a Cairo `ByteArray`
+    fn byte_array(text: &str) -> Vec<String> {
+        let chunks: Vec<&[u8]> = text.as_bytes().chunks(31).collect();
+        let (full, pending): (&[&[u8]], &[u8]) = match chunks.split_last() {
+            Some((last, rest)) if last.len() < 31 => (rest, last),
+            _ => (&chunks, &[]),
+        };
+
+        let word = |bytes: &[u8]| format!("0x{:0>2}", hex::encode(bytes));
+        let mut felts = vec![format!("0x{:x}", full.len())];
+        felts.extend(full.iter().map(|chunk| word(chunk)));
+        felts.push(word(pending));
+        felts.push(format!("0x{:x}", pending.len()));
+        felts
+    }
+
+    fn event(hash: &str, amount: &str, address: &str) -> Value {
+        let mut data = vec![amount.to_string(), "0x0".to_string()];
+        data.extend(byte_array(address));
+        json!({
+            "from_address": CONTRACT,
+            "keys": [EVENT_KEY, SENDER],
+            "data": data,
+            "block_hash": "0x1",
+            "block_number": 3,
+            "transaction_hash": hash,
+        })
+    }
+
+    async fn monitor(rpc: &MockRpc) -> (WithdrawalMonitor, Arc<dyn Storage>) {
+        let db = SqliteDatabase::new("sqlite::memory:", 1).await.unwrap();
+        db.run_migrations().await.unwrap();
+        let db: Arc<dyn Storage> = Arc::new(db);
+        let config = Config::for_tests(json!({
+            "starknet_rpc_url": rpc.url,
+            "starknet_bridge_contract": CONTRACT,
+            "starknet_withdrawal_event_key": EVENT_KEY,
+            "starknet_withdrawal_confirmations": 2,
+        }));
+
+        let monitor = WithdrawalMonitor::new(db.clone(), Arc::new(config))
+            .unwrap()
+            .unwrap();
+        (monitor, db)
+    }
+
+    /// Serves `pages` of events, each page's continuation token being the
+    /// index of the next one.
+    async fn serve_events(pages: Vec<Vec<Value>>) -> MockRpc {
+        MockRpc::start(move |method, params| match method {
+            "starknet_blockNumber" => Ok(json!(5)),
+            "starknet_getEvents" => {
+                let page = params["filter"]["continuation_token"]
+                    .as_str()
+                    .map_or(0, |token| token.parse().unwrap());
+                let token = (page + 1 < pages.len()).then(|| (page + 1).to_string());
+                Ok(json!({ "events": pages[page], "continuation_token": token }))
+            }
+            _ => Err((-32601, "method not found".to_string())),
+        })
+        .await
+    }
+
+    fn emitted(value: Value) -> EmittedEvent {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn parses_withdrawal_events() {
+        let rpc = serve_events(vec![vec![]]).await;
+        let (monitor, _) = monitor(&rpc).await;
+        let address = recipient().to_string();
+
+        let withdrawal = monitor
+            .parse_withdrawal(&emitted(event("0xabc", "0x2710", &address)), 2)
+            .unwrap();
+        assert_eq!(withdrawal.event_type, "withdrawal");
+        // Hashes are stored zero-padded
+        let hash = format!("0x{:064x}", 0xabc);
+        assert_eq!(withdrawal.starknet_tx_hash, Some(hash));
+        assert_eq!(withdrawal.starknet_event_index, Some(2));
+        assert_eq!(withdrawal.starknet_address.as_deref(), Some(SENDER));
+        assert_eq!(withdrawal.bitcoin_address, Some(address.clone()));
+        assert_eq!(withdrawal.amount, Some(10_000));
+        assert_eq!(withdrawal.asset.as_deref(), Some(ASSET_BTC));
+
+        // Amounts above 64 bits and addresses for another network are rejected
+        let mut wide = event("0xabc", "0x2710", &address);
+        wide["data"][1] = json!("0x1");
+        assert!(monitor.parse_withdrawal(&emitted(wide), 0).is_none());
+        let mainnet = Address::p2wsh(&ScriptBuf::new(), Network::Bitcoin).to_string();
+        let foreign = emitted(event("0xabc", "0x2710", &mainnet));
+        assert!(monitor.parse_withdrawal(&foreign, 0).is_none());
+    }
+
+    #[tokio::test]
+    async fn follows_continuation_tokens() {
+        let address = recipient().to_string();
+        let rpc = serve_events(vec![
+            vec![
+                event(&tx_hash(1), "0x1", &address),
+                event(&tx_hash(1), "0x2", &address),
+            ],
+            vec![event(&tx_hash(2), "0x3", &address)],
+        ])
+        .await;
+        let (monitor, db) = monitor(&rpc).await;
+
+        monitor.poll().await.unwrap();
+
+        let calls = rpc.calls("starknet_getEvents");
+        assert_eq!(calls.len(), 2);
+        assert!(calls[0]["filter"].get("continuation_token").is_none());
+        assert_eq!(calls[1]["filter"]["continuation_token"], json!("1"));
+        // Up to the latest block (5) less the confirmation depth
+        assert_eq!(calls[0]["filter"]["to_block"], json!({ "block_number": 3 }));
+
+        // Events of one transaction are numbered in order
+        let first = db.get_bridge_withdrawals(&tx_hash(1)).await.unwrap();
+        let indexes: Vec<_> = first.iter().map(|w| w.starknet_event_index).collect();
+        assert_eq!(indexes, [Some(0), Some(1)]);
+        assert_eq!(
+            db.get_bridge_withdrawals(&tx_hash(2)).await.unwrap().len(),
+            1
+        );
+        assert_eq!(db.get_sync_cursor(SYNC_CURSOR).await.unwrap(), Some(3));
+
+        // Re-reading the same blocks adds nothing
+        db.set_sync_cursor(SYNC_CURSOR, 0).await.unwrap();
+        monitor.poll().await.unwrap();
+        assert_eq!(
+            db.get_bridge_withdrawals(&tx_hash(1)).await.unwrap().len(),
+            2
+        );
+    }
+
+    /// Indexes a block at `height` holding `tx`
+    async fn index_payout(db: &dyn Storage, height: u64, tx: &Transaction) {
+        let mut block: Block = genesis_block(Network::Regtest);
+        block.header.nonce = height as u32;
+        block.txdata = vec![tx.clone()];
+        db.insert_block(&block, height, &[0; 32], None)
+            .await
+            .unwrap();
+        db.insert_transaction(
+            tx,
+            0,
+            height,
+            &block.header,
+            None,
+            &[None],
+            Network::Regtest,
+        )
+        .await
+        .unwrap();
+        db.insert_block_stats(height).await.unwrap();
+    }
+
+    fn payout(starknet_tx_hash: &str, value: u64) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![
+                TxOut {
+                    value: Amount::from_sat(1_000),
+                    script_pubkey: ScriptBuf::new(),
+                },
+                TxOut {
+                    value: Amount::from_sat(value),
+                    script_pubkey: recipient().script_pubkey(),
+                },
+                TxOut {
+                    value: Amount::ZERO,
+                    script_pubkey: withdrawal_reference_script(starknet_tx_hash).unwrap(),
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn matches_payouts_indexed_before_the_withdrawal() {
+        let address = recipient().to_string();
+        let rpc = serve_events(vec![vec![
+            event(&tx_hash(1), "0x2710", &address),
+            event(&tx_hash(2), "0x2710", &address),
+            event(&tx_hash(3), "0x2710", &address),
+        ]])
+        .await;
+        let (monitor, db) = monitor(&rpc).await;
+
+        let paid = payout(&tx_hash(1), 10_000);
+        let short = payout(&tx_hash(2), 9_000);
+        index_payout(db.as_ref(), 7, &paid).await;
+        index_payout(db.as_ref(), 8, &short).await;
+
+        monitor.poll().await.unwrap();
+
+        let matched = &db.get_bridge_withdrawals(&tx_hash(1)).await.unwrap()[0];
+        assert_eq!(matched.txid, Some(paid.txid().into()));
+        assert_eq!(matched.vout, Some(1));
+        assert_eq!(matched.block_height, Some(7));
+        assert_eq!(
+            matched.status.as_deref(),
+            Some(BridgeEventStatus::Pending.to_string().as_str())
+        );
+
+        let mismatched = &db.get_bridge_withdrawals(&tx_hash(2)).await.unwrap()[0];
+        assert_eq!(mismatched.txid, Some(short.txid().into()));
+        assert_eq!(
+            mismatched.status.as_deref(),
+            Some(BridgeEventStatus::Mismatched.to_string().as_str())
+        );
+
+        // Without a payout the withdrawal waits for the indexer
+        let waiting = &db.get_bridge_withdrawals(&tx_hash(3)).await.unwrap()[0];
+        assert_eq!(waiting.txid, None);
+
+        // Both payouts' blocks had their stats already, so the matches are added
+        let stats = db.get_chain_stats().await.unwrap();
+        assert_eq!(stats.bridge_withdrawal_volume, 20_000);
+    }
+}
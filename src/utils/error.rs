@@ -1,4 +1,11 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use serde_json::Value;
 use thiserror::Error;
+use tracing::error;
 
 #[derive(Error, Debug)]
 pub enum IndexerError {
@@ -20,28 +27,193 @@ pub enum IndexerError {
     #[error("API error: {0}")]
     Api(String),
 
+    /// A request parameter failed validation
+    #[error("Invalid {field}: {message}")]
+    InvalidInput {
+        field: &'static str,
+        message: String,
+    },
+
+    #[error("{0} not found")]
+    NotFound(String),
+
+    /// Dropped by a retention policy
+    #[error("{0} has been pruned")]
+    Pruned(String),
+
+    #[error("Not acceptable: {0}")]
+    NotAcceptable(String),
+
     #[error("Generic error: {0}")]
     Generic(#[from] anyhow::Error),
 }
 
 pub type Result<T> = std::result::Result<T, IndexerError>;
 
-impl axum::response::IntoResponse for IndexerError {
-    fn into_response(self) -> axum::response::Response {
-        use axum::http::StatusCode;
-
-        let (status, message) = match self {
-            IndexerError::Database(_) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Database error".to_string(),
-            ),
-            IndexerError::Api(msg) => (StatusCode::BAD_REQUEST, msg),
-            _ => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Internal server error".to_string(),
-            ),
+impl IndexerError {
+    pub fn invalid(field: &'static str, message: impl Into<String>) -> Self {
+        IndexerError::InvalidInput {
+            field,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(what: impl Into<String>) -> Self {
+        IndexerError::NotFound(what.into())
+    }
+
+    /// Whether a query hit the statement timeout or found no free connection
+    /// in time, which clears once load drops
+    fn is_database_timeout(&self) -> bool {
+        let err = match self {
+            IndexerError::Database(e) => Some(e),
+            IndexerError::Generic(e) => e.downcast_ref::<sqlx::Error>(),
+            _ => None,
+        };
+        match err {
+            Some(sqlx::Error::PoolTimedOut) => true,
+            // query_canceled, raised by `statement_timeout`
+            Some(sqlx::Error::Database(e)) => e.code().as_deref() == Some("57014"),
+            _ => false,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        if self.is_database_timeout() {
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        match self {
+            IndexerError::Api(_) | IndexerError::InvalidInput { .. } => StatusCode::BAD_REQUEST,
+            IndexerError::NotFound(_) => StatusCode::NOT_FOUND,
+            IndexerError::Pruned(_) => StatusCode::GONE,
+            IndexerError::NotAcceptable(_) => StatusCode::NOT_ACCEPTABLE,
+            IndexerError::BitcoinRpc(_) => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable machine-readable code, shared by REST bodies and GraphQL extensions
+    pub fn code(&self) -> &'static str {
+        if self.is_database_timeout() {
+            return "database_timeout";
+        }
+        match self {
+            IndexerError::Api(_) => "bad_request",
+            IndexerError::InvalidInput { .. } => "invalid_input",
+            IndexerError::NotFound(_) => "not_found",
+            IndexerError::Pruned(_) => "pruned",
+            IndexerError::NotAcceptable(_) => "not_acceptable",
+            IndexerError::BitcoinRpc(_) => "node_error",
+            IndexerError::Database(_) => "database_error",
+            _ => "internal_error",
+        }
+    }
+
+    /// Message safe to show clients; internal failures are only logged
+    pub fn public_message(&self) -> String {
+        if self.is_database_timeout() {
+            return "Database query timed out".to_string();
+        }
+        match self {
+            IndexerError::Api(_)
+            | IndexerError::InvalidInput { .. }
+            | IndexerError::NotFound(_)
+            | IndexerError::Pruned(_)
+            | IndexerError::NotAcceptable(_) => self.to_string(),
+            IndexerError::BitcoinRpc(_) => "Bitcoin node error".to_string(),
+            IndexerError::Database(_) => "Database error".to_string(),
+            _ => "Internal server error".to_string(),
+        }
+    }
+
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            IndexerError::InvalidInput { field, .. } => Some(serde_json::json!({ "field": field })),
+            _ => None,
+        }
+    }
+}
+
+/// The `error` object of every failed API response
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    pub details: Option<Value>,
+    /// Filled in by the request id middleware
+    pub request_id: Option<String>,
+}
+
+impl ErrorBody {
+    /// Envelope for errors produced outside the handlers, such as extractor rejections
+    pub fn from_status(status: StatusCode, message: impl Into<String>) -> Self {
+        let code = match status {
+            StatusCode::BAD_REQUEST => "bad_request",
+            StatusCode::NOT_FOUND => "not_found",
+            StatusCode::GONE => "pruned",
+            StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+            StatusCode::UNPROCESSABLE_ENTITY => "invalid_input",
+            status if status.is_server_error() => "internal_error",
+            _ => "error",
         };
 
-        (status, message).into_response()
+        let mut message = message.into();
+        if message.is_empty() {
+            message = status.canonical_reason().unwrap_or("Error").to_string();
+        }
+
+        Self {
+            code: code.to_string(),
+            message,
+            details: None,
+            request_id: None,
+        }
+    }
+
+    pub fn into_response(self, status: StatusCode) -> Response {
+        let body = serde_json::json!({ "error": &self });
+        let mut response = (status, Json(body)).into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+impl From<&IndexerError> for ErrorBody {
+    fn from(err: &IndexerError) -> Self {
+        Self {
+            code: err.code().to_string(),
+            message: err.public_message(),
+            details: err.details(),
+            request_id: None,
+        }
+    }
+}
+
+impl IntoResponse for IndexerError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            error!("Request failed: {}", self);
+        }
+
+        ErrorBody::from(&self).into_response(status)
+    }
+}
+
+impl async_graphql::ErrorExtensions for IndexerError {
+    fn extend(&self) -> async_graphql::Error {
+        if self.status().is_server_error() {
+            error!("Request failed: {}", self);
+        }
+
+        async_graphql::Error::new(self.public_message()).extend_with(|_, extensions| {
+            extensions.set("code", self.code());
+            if let Some(details) = self.details() {
+                if let Ok(details) = async_graphql::Value::from_json(details) {
+                    extensions.set("details", details);
+                }
+            }
+        })
     }
 }
@@ -0,0 +1,278 @@
+use anyhow::{anyhow, Result};
+use bitcoin::{
+    block::Header,
+    consensus::deserialize,
+    hashes::{sha256d, Hash, HashEngine},
+    Txid,
+};
+use serde::Serialize;
+use starknet_crypto::FieldElement;
+
+use crate::{
+    db::{models::Hash256, Storage},
+    starknet::encoding::{felt_hex, hash_words, header_words},
+};
+
+/// SPV proof that a transaction is included in an indexed block
+#[derive(Debug, Clone, Serialize)]
+pub struct MerkleProof {
+    pub txid: String,
+    pub block_height: i64,
+    pub block_hash: String,
+    /// Raw 80-byte block header, hex
+    pub header: String,
+    /// Position of the transaction in the block
+    pub index: u32,
+    /// Sibling hashes from leaf to root, in display byte order like txids
+    pub branch: Vec<String>,
+    pub cairo: CairoMerkleProof,
+}
+
+/// The same proof as u32 words, each hash in internal byte order
+#[derive(Debug, Clone, Serialize)]
+pub struct CairoMerkleProof {
+    pub header: Vec<u32>,
+    pub txid: Vec<u32>,
+    pub branch: Vec<Vec<u32>>,
+    pub index: u32,
+    /// `(header: [u32; 20], txid: [u32; 8], branch: Array<[u32; 8]>, index: u32)` as felts
+    pub calldata: Vec<String>,
+}
+
+/// Builds the inclusion proof for `txid` from its stored block, or `None` if
+/// the transaction is not indexed.
+pub async fn transaction_proof(db: &dyn Storage, txid: Hash256) -> Result<Option<MerkleProof>> {
+    let Some(tx) = db.get_transaction(txid).await? else {
+        return Ok(None);
+    };
+    let block = db
+        .get_block_by_height(tx.block_height as u64)
+        .await?
+        .ok_or_else(|| anyhow!("Block {} not found", tx.block_height))?;
+    let raw_header = block
+        .header
+        .as_ref()
+        .ok_or_else(|| anyhow!("Block {} was indexed without its header", block.height))?;
+
+    let header_bytes: [u8; 80] = raw_header
+        .as_bytes()
+        .try_into()
+        .map_err(|_| anyhow!("Block {} header is not 80 bytes", block.height))?;
+    let header: Header = deserialize(&header_bytes)?;
+
+    let txids: Vec<Txid> = db
+        .get_block_txids(block.height as u64)
+        .await?
+        .into_iter()
+        .map(Txid::from)
+        .collect();
+    let leaf = Txid::from(txid);
+    let index = txids
+        .iter()
+        .position(|t| *t == leaf)
+        .ok_or_else(|| anyhow!("{} has no position in block {}", txid, block.height))?;
+
+    let branch = merkle_branch(&txids, index);
+    let root = branch
+        .iter()
+        .enumerate()
+        .fold(leaf.to_byte_array(), |node, (depth, sibling)| {
+            if (index >> depth) & 1 == 0 {
+                hash_pair(&node, sibling)
+            } else {
+                hash_pair(sibling, &node)
+            }
+        });
+    // An incomplete txid list would yield a proof that does not verify
+    if root != header.merkle_root.to_byte_array() {
+        return Err(anyhow!(
+            "Stored transactions of block {} do not match its merkle root",
+            block.height
+        ));
+    }
+
+    let cairo_header = header_words(&header_bytes).to_vec();
+    let cairo_txid = hash_words(&leaf.to_byte_array()).to_vec();
+    let cairo_branch: Vec<Vec<u32>> = branch.iter().map(|h| hash_words(h).to_vec()).collect();
+
+    let mut calldata: Vec<FieldElement> = Vec::new();
+    calldata.extend(cairo_header.iter().map(|&w| FieldElement::from(w)));
+    calldata.extend(cairo_txid.iter().map(|&w| FieldElement::from(w)));
+    calldata.push(FieldElement::from(cairo_branch.len() as u64));
+    calldata.extend(
+        cairo_branch
+            .iter()
+            .flatten()
+            .map(|&w| FieldElement::from(w)),
+    );
+    calldata.push(FieldElement::from(index as u64));
+
+    Ok(Some(MerkleProof {
+        txid: txid.to_string(),
+        block_height: block.height,
+        block_hash: block.hash.to_string(),
+        header: raw_header.to_string(),
+        index: index as u32,
+        branch: branch
+            .iter()
+            .map(|h| {
+                let mut display = *h;
+                display.reverse();
+                hex::encode(display)
+            })
+            .collect(),
+        cairo: CairoMerkleProof {
+            header: cairo_header,
+            txid: cairo_txid,
+            branch: cairo_branch,
+            index: index as u32,
+            calldata: calldata.iter().map(felt_hex).collect(),
+        },
+    }))
+}
+
+/// Returns the sibling hashes, leaf to root, authenticating `txids[index]`.
+/// Odd levels pair their last node with itself, as in Bitcoin.
+pub fn merkle_branch(txids: &[Txid], index: usize) -> Vec<[u8; 32]> {
+    let leaves: Vec<[u8; 32]> = txids.iter().map(|t| t.to_byte_array()).collect();
+    MerkleTree::new(leaves).branch(index)
+}
+
+/// Every level of a Bitcoin-style merkle tree, so that branches for many
+/// leaves can be read without rehashing.
+pub struct MerkleTree {
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut levels = vec![leaves];
+        while levels[levels.len() - 1].len() > 1 {
+            let level = &levels[levels.len() - 1];
+            let next = level
+                .chunks(2)
+                .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    pub fn root(&self) -> Option<[u8; 32]> {
+        self.levels[self.levels.len() - 1].first().copied()
+    }
+
+    /// Sibling hashes, leaf to root, authenticating leaf `index`
+    pub fn branch(&self, index: usize) -> Vec<[u8; 32]> {
+        let mut position = index;
+        let mut branch = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            branch.push(*level.get(position ^ 1).unwrap_or(&level[position]));
+            position /= 2;
+        }
+        branch
+    }
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut engine = sha256d::Hash::engine();
+    engine.input(left);
+    engine.input(right);
+    sha256d::Hash::from_engine(engine).to_byte_array()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{blockdata::constants::genesis_block, merkle_tree, MerkleBlock, Network};
+
+    /// Hashes `leaf` up `branch` to the root, as a verifier would
+    fn fold(leaf: [u8; 32], branch: &[[u8; 32]], index: usize) -> [u8; 32] {
+        branch
+            .iter()
+            .enumerate()
+            .fold(leaf, |node, (depth, sibling)| {
+                if (index >> depth) & 1 == 0 {
+                    hash_pair(&node, sibling)
+                } else {
+                    hash_pair(sibling, &node)
+                }
+            })
+    }
+
+    #[test]
+    fn matches_bitcoin_roots_for_any_leaf_count() {
+        for count in 1..=9u8 {
+            let leaves: Vec<sha256d::Hash> =
+                (0..count).map(|i| sha256d::Hash::hash(&[i])).collect();
+            let expected = merkle_tree::calculate_root(leaves.iter().copied())
+                .unwrap()
+                .to_byte_array();
+
+            let tree = MerkleTree::new(leaves.iter().map(|l| l.to_byte_array()).collect());
+            assert_eq!(tree.len(), count as usize);
+            assert_eq!(tree.root(), Some(expected), "{} leaves", count);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let branch = tree.branch(index);
+                assert_eq!(
+                    fold(leaf.to_byte_array(), &branch, index),
+                    expected,
+                    "leaf {} of {}",
+                    index,
+                    count
+                );
+            }
+        }
+
+        assert!(MerkleTree::new(Vec::new()).root().is_none());
+    }
+
+    #[test]
+    fn proves_the_only_transaction_of_a_block() {
+        let block = genesis_block(Network::Bitcoin);
+        let txid = block.txdata[0].txid();
+
+        assert!(merkle_branch(&[txid], 0).is_empty());
+        let tree = MerkleTree::new(vec![txid.to_byte_array()]);
+        assert_eq!(tree.root(), Some(block.header.merkle_root.to_byte_array()));
+    }
+
+    #[test]
+    fn matches_gettxoutproof() {
+        // `bitcoin-cli gettxoutproof '["5a4ebf66822b0b2d56bd9dc64ece0bc38ee7844a23ff1d7320a88c5fdb2ad3e2"]'`
+        // for the second of two transactions in its block
+        let proof = hex::decode(
+            "01000000ba8b9cda965dd8e536670f9ddec10e53aab14b20bacad27b913719000000000019\
+             0760b278fe7b8565fda3b968b918d5fd997f993b23674c0af3b6fde300b38f33a5914ce6ed5b\
+             1b01e32f570200000002252bf9d75c4f481ebb6278d708257d1f12beb6dd30301d26c623f789\
+             b2ba6fc0e2d32adb5f8ca820731dff234a84e78ec30bce4ec69dbd562d0b2b8266bf4e5a0105",
+        )
+        .unwrap();
+        let proof: MerkleBlock = deserialize(&proof).unwrap();
+        let txids: Vec<Txid> = proof
+            .txn
+            .hashes()
+            .iter()
+            .map(|h| Txid::from_byte_array(h.to_byte_array()))
+            .collect();
+        assert_eq!(
+            txids[1].to_string(),
+            "5a4ebf66822b0b2d56bd9dc64ece0bc38ee7844a23ff1d7320a88c5fdb2ad3e2"
+        );
+
+        let branch = merkle_branch(&txids, 1);
+        assert_eq!(branch, [txids[0].to_byte_array()]);
+        assert_eq!(
+            fold(txids[1].to_byte_array(), &branch, 1),
+            proof.header.merkle_root.to_byte_array()
+        );
+    }
+}
@@ -1,3 +1,6 @@
 pub mod error;
+pub mod merkle;
+pub mod script;
+pub mod transaction;
 
 pub use error::{IndexerError, Result};
@@ -0,0 +1,63 @@
+use bitcoin::{
+    hashes::{sha256, Hash},
+    Script,
+};
+
+use crate::db::models::Hash256;
+
+/// Classifies a scriptPubKey using Bitcoin Core's `scriptPubKey.type` names.
+pub fn script_type(script: &Script) -> &'static str {
+    if script.is_p2pkh() {
+        "pubkeyhash"
+    } else if script.is_p2sh() {
+        "scripthash"
+    } else if script.is_p2wpkh() {
+        "witness_v0_keyhash"
+    } else if script.is_p2wsh() {
+        "witness_v0_scripthash"
+    } else if script.is_p2tr() {
+        "witness_v1_taproot"
+    } else if script.is_op_return() {
+        "nulldata"
+    } else if script.is_p2pk() {
+        "pubkey"
+    } else if script.is_multisig() {
+        "multisig"
+    } else if script.is_witness_program() {
+        "witness_unknown"
+    } else {
+        "nonstandard"
+    }
+}
+
+/// Classifies a scriptPubKey using Esplora's `scriptpubkey_type` names.
+pub fn esplora_script_type(script: &Script) -> &'static str {
+    if script.is_empty() {
+        "empty"
+    } else if script.is_p2pkh() {
+        "p2pkh"
+    } else if script.is_p2sh() {
+        "p2sh"
+    } else if script.is_p2wpkh() {
+        "v0_p2wpkh"
+    } else if script.is_p2wsh() {
+        "v0_p2wsh"
+    } else if script.is_p2tr() {
+        "v1_p2tr"
+    } else if script.is_op_return() {
+        "op_return"
+    } else if script.is_p2pk() {
+        "p2pk"
+    } else if script.is_multisig() {
+        "multisig"
+    } else if script.is_provably_unspendable() {
+        "provably_unspendable"
+    } else {
+        "unknown"
+    }
+}
+
+/// Electrum's scripthash: the script's sha256, displayed byte-reversed.
+pub fn electrum_scripthash(script: &Script) -> Hash256 {
+    Hash256::from_byte_array(sha256::Hash::hash(script.as_bytes()).to_byte_array())
+}
@@ -0,0 +1,56 @@
+use anyhow::{anyhow, Result};
+use bitcoin::{
+    absolute::LockTime, transaction::Version, Amount, OutPoint, ScriptBuf, Sequence, Transaction,
+    TxIn, TxOut, Txid, Witness,
+};
+
+use crate::db::models::{Hash256, HexBytes, TransactionDetail};
+
+/// Rebuilds the consensus transaction from its stored inputs and outputs.
+pub fn rebuild_transaction(detail: &TransactionDetail) -> Result<Transaction> {
+    let input = detail
+        .inputs
+        .iter()
+        .map(|input| {
+            let previous_output = match (&input.prev_txid, input.prev_vout) {
+                (Some(txid), Some(vout)) => OutPoint::new(Txid::from(*txid), vout as u32),
+                _ => OutPoint::null(),
+            };
+            let witness: Vec<&[u8]> = input.witness.iter().map(HexBytes::as_bytes).collect();
+
+            Ok(TxIn {
+                previous_output,
+                script_sig: ScriptBuf::from(&input.script_sig),
+                sequence: Sequence(input.sequence as u32),
+                witness: Witness::from_slice(&witness),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let output = detail
+        .outputs
+        .iter()
+        .map(|output| {
+            Ok(TxOut {
+                value: Amount::from_sat(output.output.value as u64),
+                script_pubkey: ScriptBuf::from(&output.output.script_pubkey),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let tx = Transaction {
+        version: Version(detail.transaction.version),
+        lock_time: LockTime::from_consensus(detail.transaction.locktime as u32),
+        input,
+        output,
+    };
+
+    // Transactions indexed before inputs were stored cannot be rebuilt
+    if Hash256::from(tx.txid()) != detail.transaction.txid {
+        return Err(anyhow!(
+            "Stored data for {} does not rebuild the transaction",
+            detail.transaction.txid
+        ));
+    }
+    Ok(tx)
+}
@@ -0,0 +1,414 @@
+//! End-to-end run on an in-memory SQLite database: the indexer follows a
+//! mock bitcoind through a reorg while the REST API answers from what it
+//! indexed.
+
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use bitcoin::{
+    absolute::LockTime,
+    block::{Header, Version},
+    blockdata::constants::genesis_block,
+    consensus::encode::serialize_hex,
+    hashes::Hash,
+    script::PushBytesBuf,
+    transaction, Address, Amount, Block, Network, OutPoint, ScriptBuf, Sequence, Transaction, TxIn,
+    TxMerkleNode, TxOut, WPubkeyHash, Witness,
+};
+use serde_json::{json, Value};
+use std::{
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tower::ServiceExt;
+
+use starknet_btc_indexer::{
+    api::{create_api_router, ApiState},
+    db::SqliteDatabase,
+    BitcoinIndexer, Config, Storage,
+};
+
+const COIN: u64 = 100_000_000;
+const FEE: u64 = 10_000;
+
+type Chain = Arc<Mutex<Vec<Block>>>;
+
+/// `estimatesmartfee` calls the mock node has answered
+static FEE_ESTIMATES: AtomicUsize = AtomicUsize::new(0);
+
+/// Serves `chain` as bitcoind's JSON-RPC on its own thread, since the
+/// indexer's RPC client blocks.
+fn bitcoind(chain: Chain) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async move {
+            let router = Router::new().route(
+                "/",
+                post(move |Json(request): Json<Value>| {
+                    let chain = chain.clone();
+                    async move {
+                        let chain = chain.lock().unwrap();
+                        let result = answer(&chain, request["method"].as_str(), &request["params"]);
+                        Json(json!({
+                            "jsonrpc": "2.0",
+                            "id": request["id"],
+                            "result": result,
+                            "error": if result.is_null() {
+                                json!({ "code": -5, "message": "not found" })
+                            } else {
+                                Value::Null
+                            },
+                        }))
+                    }
+                }),
+            );
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service())
+                .await
+                .unwrap();
+        });
+    });
+
+    url
+}
+
+fn answer(chain: &[Block], method: Option<&str>, params: &Value) -> Value {
+    let by_hash = || {
+        let hash = params[0].as_str()?;
+        chain
+            .iter()
+            .enumerate()
+            .find(|(_, block)| block.block_hash().to_string() == hash)
+    };
+
+    match method {
+        Some("getblockcount") => json!(chain.len() - 1),
+        Some("getblockhash") => params[0]
+            .as_u64()
+            .and_then(|height| chain.get(height as usize))
+            .map_or(Value::Null, |block| json!(block.block_hash())),
+        Some("getblock") => by_hash().map_or(Value::Null, |(_, block)| json!(serialize_hex(block))),
+        Some("getrawtransaction") => chain
+            .iter()
+            .flat_map(|block| &block.txdata)
+            .find(|tx| Some(tx.txid().to_string().as_str()) == params[0].as_str())
+            .map_or(Value::Null, |tx| json!(serialize_hex(tx))),
+        Some("getblockheader") => by_hash().map_or(Value::Null, |(height, block)| {
+            json!({
+                "hash": block.block_hash(),
+                "confirmations": chain.len() - height,
+                "height": height,
+                "version": block.header.version.to_consensus(),
+                "merkleroot": block.header.merkle_root,
+                "time": block.header.time,
+                "mediantime": block.header.time,
+                "nonce": block.header.nonce,
+                "bits": format!("{:08x}", block.header.bits.to_consensus()),
+                "difficulty": 1.0,
+                "chainwork": format!("{:064x}", height + 1),
+                "nTx": block.txdata.len(),
+                "previousblockhash": (height > 0).then_some(block.header.prev_blockhash),
+            })
+        }),
+        // 2 sat/vB for every target but the next block, which fails
+        Some("estimatesmartfee") => {
+            FEE_ESTIMATES.fetch_add(1, Ordering::SeqCst);
+            match params[0].as_u64() {
+                Some(1) => Value::Null,
+                target => json!({ "feerate": 0.00002, "blocks": target }),
+            }
+        }
+        _ => Value::Null,
+    }
+}
+
+fn script(byte: u8) -> ScriptBuf {
+    ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array([byte; 20]))
+}
+
+fn address(script: &ScriptBuf) -> String {
+    Address::from_script(script, Network::Regtest)
+        .unwrap()
+        .to_string()
+}
+
+fn transaction(input: TxIn, outputs: &[(u64, &ScriptBuf)]) -> Transaction {
+    Transaction {
+        version: transaction::Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![input],
+        output: outputs
+            .iter()
+            .map(|(value, script)| TxOut {
+                value: Amount::from_sat(*value),
+                script_pubkey: (*script).clone(),
+            })
+            .collect(),
+    }
+}
+
+/// A coinbase made unique by `tag`
+fn coinbase(tag: &[u8], value: u64, script: &ScriptBuf) -> Transaction {
+    let script_sig = ScriptBuf::builder()
+        .push_slice(PushBytesBuf::try_from(tag.to_vec()).unwrap())
+        .into_script();
+    let input = TxIn {
+        previous_output: OutPoint::null(),
+        script_sig,
+        sequence: Sequence::MAX,
+        witness: Witness::new(),
+    };
+    transaction(input, &[(value, script)])
+}
+
+fn spend(outpoint: OutPoint, outputs: &[(u64, &ScriptBuf)]) -> Transaction {
+    let input = TxIn {
+        previous_output: outpoint,
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::MAX,
+        witness: Witness::new(),
+    };
+    transaction(input, outputs)
+}
+
+fn block(prev: &Block, txdata: Vec<Transaction>) -> Block {
+    let mut block = Block {
+        header: Header {
+            version: Version::TWO,
+            prev_blockhash: prev.block_hash(),
+            merkle_root: TxMerkleNode::all_zeros(),
+            time: prev.header.time + 600,
+            bits: prev.header.bits,
+            nonce: 0,
+        },
+        txdata,
+    };
+    block.header.merkle_root = block.compute_merkle_root().unwrap();
+    block
+}
+
+async fn get(router: &Router, uri: &str) -> (StatusCode, Value) {
+    let response = router
+        .clone()
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Waits for the indexer to store `block` as its tip.
+async fn wait_for_tip(db: &dyn Storage, height: u64, block: &Block) {
+    for _ in 0..300 {
+        if let Some(tip) = db.get_tip_block().await.unwrap() {
+            if tip.height as u64 == height
+                && tip.hash.to_string() == block.block_hash().to_string()
+                && db.has_block_stats(height).await.unwrap()
+            {
+                return;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("indexer did not reach block {}", height);
+}
+
+/// Genesis, a block paying Alice and one where she pays Bob, with that payment
+fn fixture_chain(
+    alice: &ScriptBuf,
+    bob: &ScriptBuf,
+    miner: &ScriptBuf,
+) -> (Vec<Block>, Transaction) {
+    let genesis = genesis_block(Network::Regtest);
+    let first = block(&genesis, vec![coinbase(b"1", 50 * COIN, alice)]);
+    let funded = OutPoint::new(first.txdata[0].txid(), 0);
+    let payment = spend(funded, &[(30 * COIN, bob), (20 * COIN - FEE, alice)]);
+    let second = block(
+        &first,
+        vec![coinbase(b"2", 50 * COIN + FEE, miner), payment.clone()],
+    );
+    (vec![genesis, first, second], payment)
+}
+
+struct Node {
+    db: Arc<dyn Storage>,
+    router: Router,
+    indexer: tokio::task::JoinHandle<anyhow::Result<()>>,
+    cache_dir: std::path::PathBuf,
+}
+
+/// Starts the indexer on `chain` from `start_height`, with the API over its database
+async fn start(chain: &Chain, start_height: u64, name: &str) -> Node {
+    let cache_dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    let config: Config = serde_json::from_value(json!({
+        "bitcoin_rpc_url": bitcoind(chain.clone()),
+        "bitcoin_rpc_user": "user",
+        "bitcoin_rpc_pass": "pass",
+        "bitcoin_network": "regtest",
+        "database_url": "sqlite::memory:",
+        "api_host": "127.0.0.1",
+        "api_port": 0,
+        "indexer_start_height": start_height,
+        "indexer_batch_size": 10,
+        "indexer_poll_interval_secs": 1,
+        "utxo_cache_dir": cache_dir,
+        "esplora_enabled": true,
+    }))
+    .unwrap();
+    let config = Arc::new(config);
+
+    let db = SqliteDatabase::new(&config.database_url, 1).await.unwrap();
+    db.run_migrations().await.unwrap();
+    let db: Arc<dyn Storage> = Arc::new(db);
+    let router = create_api_router(ApiState {
+        db: db.clone(),
+        writer: db.clone(),
+        config: config.clone(),
+        content_store: None,
+    });
+
+    let indexer = BitcoinIndexer::new(db.clone(), config.clone())
+        .await
+        .unwrap();
+    let indexer = tokio::spawn(async move { indexer.start().await });
+
+    Node {
+        db,
+        router,
+        indexer,
+        cache_dir,
+    }
+}
+
+impl Node {
+    async fn stop(self) {
+        self.indexer.abort();
+        let _ = self.indexer.await;
+        std::fs::remove_dir_all(self.cache_dir).unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn indexes_and_rolls_back_on_sqlite() {
+    let (alice, bob, miner) = (script(1), script(2), script(3));
+    let (blocks, payment) = fixture_chain(&alice, &bob, &miner);
+    let (first, second) = (blocks[1].clone(), blocks[2].clone());
+    let funded = payment.input[0].previous_output;
+
+    let chain: Chain = Arc::new(Mutex::new(blocks));
+    let node = start(&chain, 0, "sqlite-utxo-cache").await;
+    let (db, router) = (node.db.clone(), node.router.clone());
+
+    wait_for_tip(db.as_ref(), 2, &second).await;
+
+    let (status, tip) = get(&router, "/blocks/tip").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tip["height"], 2);
+    assert_eq!(tip["hash"], second.block_hash().to_string());
+
+    let (status, tx) = get(&router, &format!("/transactions/{}", payment.txid())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tx["fee"], FEE);
+    assert_eq!(tx["inputs"][0]["address"], address(&alice));
+    assert_eq!(tx["outputs"][0]["address"], address(&bob));
+    assert_eq!(tx["outputs"][0]["value"], 30 * COIN);
+
+    let (_, bob_stats) = get(&router, &format!("/addresses/{}", address(&bob))).await;
+    assert_eq!(bob_stats["balance"], 30 * COIN);
+    let (_, alice_utxos) = get(&router, &format!("/addresses/{}/utxos", address(&alice))).await;
+    let utxos = alice_utxos["utxos"].as_array().unwrap();
+    assert_eq!(utxos.len(), 1);
+    assert_eq!(utxos[0]["txid"], payment.txid().to_string());
+
+    let (_, esplora_tx) = get(&router, &format!("/esplora/tx/{}", payment.txid())).await;
+    assert_eq!(esplora_tx["fee"], FEE);
+    let coinbase_txid = second.txdata[0].txid();
+    let (_, esplora_tx) = get(&router, &format!("/esplora/tx/{}", coinbase_txid)).await;
+    assert_eq!(esplora_tx["fee"], 0);
+    let (status, tx_status) = get(&router, &format!("/esplora/tx/{}/status", payment.txid())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tx_status["block_height"], 2);
+    let unknown = format!("/esplora/tx/{}/status", "00".repeat(32));
+    assert_eq!(get(&router, &unknown).await.0, StatusCode::NOT_FOUND);
+
+    // The failing target is left out, and the answer is cached
+    let (status, estimates) = get(&router, "/esplora/fee-estimates").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(estimates.get("1").is_none());
+    assert_eq!(estimates["2"], 2.0);
+    assert_eq!(estimates.as_object().unwrap().len(), 27);
+    let calls = FEE_ESTIMATES.load(Ordering::SeqCst);
+    assert_eq!(get(&router, "/esplora/fee-estimates").await.1, estimates);
+    assert_eq!(FEE_ESTIMATES.load(Ordering::SeqCst), calls);
+
+    // A mainnet address can't be on this regtest chain
+    let mainnet = Address::from_script(&bob, Network::Bitcoin).unwrap();
+    let (status, _) = get(&router, &format!("/esplora/address/{}/utxo", mainnet)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = get(&router, &format!("/addresses/{}", mainnet)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Replace block 2 with a longer branch that leaves the payment out
+    let fork = block(&first, vec![coinbase(b"2b", 50 * COIN, &miner)]);
+    let third = block(&fork, vec![coinbase(b"3b", 50 * COIN, &miner)]);
+    {
+        let mut chain = chain.lock().unwrap();
+        chain.truncate(2);
+        chain.extend([fork.clone(), third.clone()]);
+    }
+
+    wait_for_tip(db.as_ref(), 3, &third).await;
+    node.stop().await;
+
+    let (_, tip) = get(&router, "/blocks/tip").await;
+    assert_eq!(tip["height"], 3);
+    let (_, replaced) = get(&router, "/blocks/2").await;
+    assert_eq!(replaced["hash"], fork.block_hash().to_string());
+
+    let (status, _) = get(&router, &format!("/transactions/{}", payment.txid())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Bob's payment is gone and Alice's coinbase output is unspent again
+    let (_, bob_stats) = get(&router, &format!("/addresses/{}", address(&bob))).await;
+    assert_eq!(bob_stats["balance"], 0);
+    assert_eq!(bob_stats["tx_count"], 0);
+    let (_, alice_stats) = get(&router, &format!("/addresses/{}", address(&alice))).await;
+    assert_eq!(alice_stats["balance"], 50 * COIN);
+    let (_, alice_utxos) = get(&router, &format!("/addresses/{}/utxos", address(&alice))).await;
+    let utxos = alice_utxos["utxos"].as_array().unwrap();
+    assert_eq!(utxos.len(), 1);
+    assert_eq!(utxos[0]["txid"], funded.txid.to_string());
+    assert_eq!(utxos[0]["vout"], 0);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn asks_the_node_for_outputs_before_the_start_height() {
+    let (alice, bob, miner) = (script(1), script(2), script(3));
+    let (blocks, payment) = fixture_chain(&alice, &bob, &miner);
+    let second = blocks[2].clone();
+
+    // Block 1, which funds the payment, is never indexed
+    let chain: Chain = Arc::new(Mutex::new(blocks));
+    let node = start(&chain, 2, "sqlite-utxo-cache-late").await;
+    wait_for_tip(node.db.as_ref(), 2, &second).await;
+
+    let (status, tx) = get(&node.router, &format!("/transactions/{}", payment.txid())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tx["fee"], FEE);
+    node.stop().await;
+}